@@ -0,0 +1,330 @@
+//! Printing the AST in machine-readable formats.
+//!
+//! The `Debug` representation of the AST is good for debugging the parser,
+//! but it changes every time we touch AST nodes, and it's not something
+//! other tools can parse. So, there are a few stable formats
+//! that `rlci parse --format` can print:
+//!
+//! + JSON, for tools that want to know everything about the tree.
+//! + S-expressions, for humans (and lisps) that want to see the structure.
+//! + Short representation, the structure of the tree without names.
+//!
+//! # JSON schema
+//!
+//! The module is an object with the list of statements:
+//!
+//! ```text
+//! {"stmts": [STMT, ...]}
+//! ```
+//!
+//! Each statement and expression is an object with the `type` field telling
+//! what kind of node it is, the fields specific for that kind,
+//! and the `span` field with the position of the node in the source:
+//!
+//! ```text
+//! {"type": "assign", "target": "id", "expr": EXPR, "span": SPAN}
+//! {"type": "import", "path": "lib.rb", "span": SPAN}
+//! {"type": "export", "names": ["head", "tail"], "span": SPAN}
+//! {"type": "fixity", "assoc": "left", "precedence": 6, "operators": ["+", "-"], "span": SPAN}
+//! {"type": "expr", "expr": EXPR, "span": SPAN}
+//!
+//! {"type": "def", "arg": "x", "expr": EXPR, "span": SPAN}
+//! {"type": "call", "target": EXPR, "arg": EXPR, "span": SPAN}
+//! {"type": "id", "name": "x", "span": SPAN}
+//!
+//! {"file": "main.rb", "start": 0, "end": 6, "line": 1, "col": 1}
+//! ```
+//!
+//! The `assoc` is one of `left`, `right`, or `none`. The `file` of a span
+//! is `null` if the source wasn't read from a file. The `start` and `end`
+//! are byte offsets, the `line` and `col` start from 1.
+//!
+//! Keep in mind that literals (numbers are an exception) are desugared
+//! by the parser, so a string literal in JSON is a (pretty big) tree of lambdas.
+use crate::ast_nodes::{Assoc, Expr, Module, Span, Stmt};
+use std::fmt::Write;
+
+impl Module {
+    /// Represent the module as a single-line JSON object.
+    ///
+    /// See the module docs for the schema.
+    pub fn to_json(&self) -> String {
+        let stmts: Vec<_> = self.stmts.iter().map(Stmt::to_json).collect();
+        format!("{{\"stmts\": [{}]}}", stmts.join(", "))
+    }
+
+    /// Represent the module as an S-expression, each statement on its own line.
+    ///
+    /// `(module (assign id (def x x)) (call id y))`
+    pub fn to_sexpr(&self) -> String {
+        let mut out = "(module".to_string();
+        for stmt in &self.stmts {
+            write!(out, "\n  {}", stmt.to_sexpr()).unwrap();
+        }
+        out.push(')');
+        out
+    }
+
+    /// The short representation of all statements, one per line.
+    ///
+    /// See `Stmt::short_repr`.
+    pub fn short_repr(&self) -> String {
+        let stmts: Vec<_> = self.stmts.iter().map(Stmt::short_repr).collect();
+        stmts.join("\n")
+    }
+}
+
+impl Stmt {
+    /// Represent the statement as a JSON object.
+    pub fn to_json(&self) -> String {
+        let fields = match self {
+            Stmt::Assign { target, expr, .. } => format!(
+                "\"type\": \"assign\", \"target\": {}, \"expr\": {}",
+                json_str(target),
+                expr.to_json(),
+            ),
+            Stmt::Import { path, .. } => {
+                format!("\"type\": \"import\", \"path\": {}", json_str(path))
+            }
+            Stmt::Export { names, .. } => {
+                format!("\"type\": \"export\", \"names\": {}", json_list(names))
+            }
+            Stmt::Fixity {
+                assoc,
+                precedence,
+                operators,
+                ..
+            } => format!(
+                "\"type\": \"fixity\", \"assoc\": \"{}\", \"precedence\": {precedence}, \"operators\": {}",
+                assoc_name(*assoc),
+                json_list(operators),
+            ),
+            Stmt::Expr { expr, .. } => format!("\"type\": \"expr\", \"expr\": {}", expr.to_json()),
+        };
+        format!("{{{fields}, \"span\": {}}}", span_json(self.span()))
+    }
+
+    /// Represent the statement as an S-expression.
+    ///
+    /// Names are written as they are, without quotes. They can't have spaces
+    /// or braces inside, so it's never ambiguous. The import path, however,
+    /// can be anything, so it's quoted.
+    pub fn to_sexpr(&self) -> String {
+        match self {
+            Stmt::Assign { target, expr, .. } => format!("(assign {target} {})", expr.to_sexpr()),
+            Stmt::Import { path, .. } => format!("(import {})", json_str(path)),
+            Stmt::Export { names, .. } => format!("(export {})", names.join(" ")),
+            Stmt::Fixity {
+                assoc,
+                precedence,
+                operators,
+                ..
+            } => format!(
+                "(fixity {} {precedence} {})",
+                assoc_name(*assoc),
+                operators.join(" ")
+            ),
+            Stmt::Expr { expr, .. } => expr.to_sexpr(),
+        }
+    }
+}
+
+impl Expr {
+    /// Represent the expression as a JSON object.
+    pub fn to_json(&self) -> String {
+        let fields = match self {
+            Expr::Def { arg, expr, .. } => format!(
+                "\"type\": \"def\", \"arg\": {}, \"expr\": {}",
+                json_str(arg),
+                expr.to_json()
+            ),
+            Expr::Call { target, arg, .. } => format!(
+                "\"type\": \"call\", \"target\": {}, \"arg\": {}",
+                target.to_json(),
+                arg.to_json()
+            ),
+            Expr::Id { name, .. } => format!("\"type\": \"id\", \"name\": {}", json_str(name)),
+            Expr::Numeral { n, .. } => format!("\"type\": \"numeral\", \"n\": {n}"),
+        };
+        format!("{{{fields}, \"span\": {}}}", span_json(self.span()))
+    }
+
+    /// Represent the expression as an S-expression.
+    ///
+    /// A name is just the name, a definition is `(def arg body)`,
+    /// a call is `(call target arg)`, and a numeral is `(numeral n)`.
+    pub fn to_sexpr(&self) -> String {
+        match self {
+            Expr::Def { arg, expr, .. } => format!("(def {arg} {})", expr.to_sexpr()),
+            Expr::Call { target, arg, .. } => {
+                format!("(call {} {})", target.to_sexpr(), arg.to_sexpr())
+            }
+            Expr::Id { name, .. } => name.clone(),
+            Expr::Numeral { n, .. } => format!("(numeral {n})"),
+        }
+    }
+}
+
+/// The name of the associativity, the same in JSON and S-expressions.
+fn assoc_name(assoc: Assoc) -> &'static str {
+    match assoc {
+        Assoc::Left => "left",
+        Assoc::Right => "right",
+        Assoc::None => "none",
+    }
+}
+
+pub(crate) fn span_json(span: &Span) -> String {
+    let file = match &span.file {
+        Some(file) => json_str(file),
+        None => "null".to_string(),
+    };
+    format!(
+        "{{\"file\": {file}, \"start\": {}, \"end\": {}, \"line\": {}, \"col\": {}}}",
+        span.start, span.end, span.line, span.col
+    )
+}
+
+fn json_list(items: &[String]) -> String {
+    let items: Vec<_> = items.iter().map(|item| json_str(item)).collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// Make a JSON string literal.
+///
+/// We don't need a whole JSON library just to write a few strings.
+/// Quotes, backslashes, and control characters must be escaped,
+/// everything else, including any unicode, can be written as it is.
+pub(crate) fn json_str(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if ch.is_control() => write!(out, "\\u{:04x}", ch as u32).unwrap(),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, parse_file};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("id = \\x x", "(assign id (def x x))")]
+    #[case("f a b", "(call (call f a) b)")]
+    #[case("f (g a)", "(call f (call g a))")]
+    #[case("\\a b. a", "(def a (def b a))")]
+    #[case("nat.add 2 3", "(call (call nat.add 2) 3)")]
+    #[case("f '🦀'", "(call f (numeral 129408))")]
+    #[case("import \"a \\\"b\\\".rb\"", "(import \"a \\\"b\\\".rb\")")]
+    #[case("export head tail", "(export head tail)")]
+    #[case("infixr 5 ++ $", "(fixity right 5 ++ $)")]
+    #[case("infixl 6 +\na + b", "(fixity left 6 +)\n  (call (call + a) b)")]
+    fn to_sexpr(#[case] input: &str, #[case] exp: &str) {
+        let module = parse(input).unwrap();
+        assert_eq!(module.to_sexpr(), format!("(module\n  {exp})"));
+    }
+
+    #[test]
+    fn to_sexpr_empty() {
+        assert_eq!(parse("# nothing").unwrap().to_sexpr(), "(module)");
+    }
+
+    #[rstest]
+    #[case(
+        "x",
+        r#"{"type": "expr", "expr": {"type": "id", "name": "x", "span": SPAN}, "span": SPAN}"#
+    )]
+    #[case(
+        "x = y",
+        r#"{"type": "assign", "target": "x", "expr": {"type": "id", "name": "y", "span": SPAN}, "span": SPAN}"#
+    )]
+    #[case(
+        "\\x x",
+        r#"{"type": "expr", "expr": {"type": "def", "arg": "x", "expr": {"type": "id", "name": "x", "span": SPAN}, "span": SPAN}, "span": SPAN}"#
+    )]
+    #[case(
+        "f x",
+        r#"{"type": "expr", "expr": {"type": "call", "target": {"type": "id", "name": "f", "span": SPAN}, "arg": {"type": "id", "name": "x", "span": SPAN}, "span": SPAN}, "span": SPAN}"#
+    )]
+    #[case(
+        "'🦀'",
+        r#"{"type": "expr", "expr": {"type": "numeral", "n": 129408, "span": SPAN}, "span": SPAN}"#
+    )]
+    #[case(
+        "import \"a.rb\"",
+        r#"{"type": "import", "path": "a.rb", "span": SPAN}"#
+    )]
+    #[case(
+        "export a +",
+        r#"{"type": "export", "names": ["a", "+"], "span": SPAN}"#
+    )]
+    #[case(
+        "infix 4 ==",
+        r#"{"type": "fixity", "assoc": "none", "precedence": 4, "operators": ["=="], "span": SPAN}"#
+    )]
+    fn stmt_to_json(#[case] input: &str, #[case] exp: &str) {
+        let module = parse(input).unwrap();
+        // Spans are tested separately, here we care only about the structure.
+        let re_span = |json: &str| {
+            let mut out = String::new();
+            let mut rest = json;
+            while let Some(start) = rest.find("{\"file\"") {
+                let end = start + rest[start..].find('}').unwrap() + 1;
+                out.push_str(&rest[..start]);
+                out.push_str("SPAN");
+                rest = &rest[end..];
+            }
+            out.push_str(rest);
+            out
+        };
+        assert_eq!(
+            re_span(&module.to_json()),
+            format!("{{\"stmts\": [{exp}]}}")
+        );
+    }
+
+    #[test]
+    fn span_to_json() {
+        let module = parse_file("\n  f x", "main.rb").unwrap();
+        let Stmt::Expr { expr, .. } = &module.stmts[0] else {
+            panic!("not an expression")
+        };
+        let Expr::Call { arg, .. } = expr else {
+            panic!("not a call")
+        };
+        assert_eq!(
+            span_json(arg.span()),
+            r#"{"file": "main.rb", "start": 5, "end": 6, "line": 2, "col": 5}"#
+        );
+        let module = parse("x").unwrap();
+        assert!(module.to_json().contains(r#"{"file": null, "start": 0"#));
+    }
+
+    #[rstest]
+    #[case("abc", r#""abc""#)]
+    #[case("a\"b", r#""a\"b""#)]
+    #[case("a\\b", r#""a\\b""#)]
+    #[case("a\nb\t", r#""a\nb\t""#)]
+    #[case("\u{1}", r#""\u0001""#)]
+    #[case("λ🦀", r#""λ🦀""#)]
+    fn escape_json_str(#[case] input: &str, #[case] exp: &str) {
+        assert_eq!(json_str(input), exp);
+    }
+
+    #[test]
+    fn short_repr() {
+        let module = parse("x = \\a a\nf x\nimport \"a.rb\"").unwrap();
+        assert_eq!(module.short_repr(), "let(def(id))\ncall(id, id)\nimport");
+    }
+}
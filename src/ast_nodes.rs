@@ -1,5 +1,51 @@
 //! Definitions for AST nodes.
 
+use std::fmt;
+use std::rc::Rc;
+
+/// The position of a node in the source code.
+///
+/// Every AST node carries its span, and runtime values inherit spans
+/// from the nodes they are built from. That way, errors that happen
+/// long after parsing can still point at the exact place in the source.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Span {
+    /// The path to the file, if the source was read from a file.
+    ///
+    /// It's wrapped into `Rc` because every single node has a copy of it,
+    /// and values get cloned a lot during evaluation.
+    pub file: Option<Rc<str>>,
+    /// The byte offset of the first character of the node.
+    pub start: usize,
+    /// The byte offset right after the last character of the node.
+    pub end: usize,
+    /// The line number, starting from 1.
+    pub line: usize,
+    /// The column number (in characters), starting from 1.
+    pub col: usize,
+}
+
+impl Span {
+    /// Make a span starting where this span starts and ending where `other` ends.
+    pub fn to(&self, other: &Span) -> Span {
+        Span {
+            end: other.end,
+            ..self.clone()
+        }
+    }
+}
+
+// The format is the same as used by rustc, grep, and most of other tools,
+// so that editors and terminals can recognize it and make it clickable.
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{file}:")?;
+        }
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
 /// The root of the AST for a single file
 #[derive(Debug, PartialEq)]
 pub struct Module {
@@ -12,12 +58,36 @@ pub enum Stmt {
     /// Assign an expression to a name.
     ///
     /// `id = \x x`
-    Assign { target: String, expr: Box<Expr> },
+    Assign {
+        target: String,
+        expr: Box<Expr>,
+        span: Span,
+    },
+
+    /// Evaluate another file in the current session.
+    ///
+    /// `import "lib.rb"`
+    Import { path: String, span: Span },
+
+    /// Make names from the current module visible in all modules.
+    ///
+    /// `export head tail`
+    Export { names: Vec<String>, span: Span },
+
+    /// Declare how to parse infix operators.
+    ///
+    /// `infixl 6 + -`
+    Fixity {
+        assoc: Assoc,
+        precedence: u8,
+        operators: Vec<String>,
+        span: Span,
+    },
 
     /// A single module-level expression.
     /// It doesn't make sense in the real-world scripts
     /// but essential for REPL.
-    Expr { expr: Expr },
+    Expr { expr: Expr, span: Span },
 }
 
 impl Stmt {
@@ -28,33 +98,86 @@ impl Stmt {
     /// inconsistent or unexpected results in ambiguous situations.
     pub fn short_repr(&self) -> String {
         match self {
-            Stmt::Assign { target: _, expr } => format!("let({})", expr.short_repr()),
-            Stmt::Expr { expr } => expr.short_repr(),
+            Stmt::Assign { expr, .. } => format!("let({})", expr.short_repr()),
+            Stmt::Import { .. } => "import".to_string(),
+            Stmt::Export { .. } => "export".to_string(),
+            Stmt::Fixity { .. } => "fixity".to_string(),
+            Stmt::Expr { expr, .. } => expr.short_repr(),
+        }
+    }
+
+    /// The position of the statement in the source code.
+    pub fn span(&self) -> &Span {
+        match self {
+            Stmt::Assign { span, .. }
+            | Stmt::Import { span, .. }
+            | Stmt::Export { span, .. }
+            | Stmt::Fixity { span, .. }
+            | Stmt::Expr { span, .. } => span,
         }
     }
 }
 
+/// How to group infix operators of the same precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assoc {
+    /// `infixl`: `a - b - c` is `(a - b) - c`.
+    Left,
+    /// `infixr`: `a $ b $ c` is `a $ (b $ c)`.
+    Right,
+    /// `infix`: `a == b == c` is an error.
+    None,
+}
+
 /// Expression is a node that can be used anywhere.
 #[derive(Debug, PartialEq)]
 pub enum Expr {
     /// Definition of a lambda.
-    Def { arg: String, expr: Box<Expr> },
+    Def {
+        arg: String,
+        expr: Box<Expr>,
+        span: Span,
+    },
 
     /// Application. Calling a lambda with an argument.
-    Call { target: Box<Expr>, arg: Box<Expr> },
+    Call {
+        target: Box<Expr>,
+        arg: Box<Expr>,
+        span: Span,
+    },
 
     /// Identifier, a name of a lambda.
-    Id { name: String },
+    ///
+    /// For qualified names, like `nat.add`, the name includes the module name.
+    Id { name: String, span: Span },
+
+    /// A Church numeral for a character of a string or character literal.
+    ///
+    /// Numbers written in the source are names (see `desugar::as_numeral`),
+    /// but numbers for characters can't be redefined, and they are often
+    /// too big to be spelled out as definitions and calls (see `desugar::numeral`).
+    Numeral { n: u64, span: Span },
 }
 
 impl Expr {
     pub fn short_repr(&self) -> String {
         match self {
-            Expr::Def { arg: _, expr } => format!("def({})", expr.short_repr()),
-            Expr::Call { target, arg } => {
+            Expr::Def { expr, .. } => format!("def({})", expr.short_repr()),
+            Expr::Call { target, arg, .. } => {
                 format!("call({}, {})", target.short_repr(), arg.short_repr())
             }
-            Expr::Id { name: _ } => "id".to_string(),
+            Expr::Id { .. } => "id".to_string(),
+            Expr::Numeral { .. } => "numeral".to_string(),
+        }
+    }
+
+    /// The position of the expression in the source code.
+    pub fn span(&self) -> &Span {
+        match self {
+            Expr::Def { span, .. }
+            | Expr::Call { span, .. }
+            | Expr::Id { span, .. }
+            | Expr::Numeral { span, .. } => span,
         }
     }
 }
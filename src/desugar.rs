@@ -0,0 +1,93 @@
+//! Building blocks for literals.
+//!
+//! Literals are syntactic sugar: each of them can be written in plain lambda calculus,
+//! it's just too long to do by hand. The functions here produce such plain
+//! expressions, so that the runtime doesn't need to know anything about literals.
+//! The only exception is numerals, see `numeral`.
+use crate::ast_nodes::{Expr, Span};
+
+/// If the name is a natural number literal, get its value.
+///
+/// Numbers are valid identifiers, so the parser doesn't treat them in any special way.
+/// Instead, the runtime checks if the name is a number when it isn't defined.
+/// That way, if someone wants to define `0` differently, they still can.
+pub fn as_numeral(name: &str) -> Option<u64> {
+    if !name.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    // Nothing can count up to a number too big to fit into u64
+    // in a reasonable time anyway. So, such numbers are just names.
+    name.parse().ok()
+}
+
+/// Make a Church numeral for the given natural number.
+///
+/// A Church numeral `n` is a function that accepts a function `a` and a value `b`
+/// and calls `a` on `b` `n` times: `λa λb a (a (a b))` is 3.
+///
+/// Unlike other literals, the numeral isn't spelled out. The number for `'🦀'`
+/// is 129408, and a tree of definitions and calls that deep is too much
+/// to build, walk, and print. So, it's a single node, and the runtime unfolds it
+/// only as far as needed, see `Value::Numeral`.
+pub fn numeral(n: u64, span: &Span) -> Expr {
+    Expr::Numeral {
+        n,
+        span: span.clone(),
+    }
+}
+
+/// Make a list from the given items.
+///
+/// The list has the same structure as lists built with the stdlib functions.
+/// Each node of the list is a pair. The first item of the pair is `true`
+/// if that's the end of the list. Otherwise, the second item is another pair
+/// of the list item and the rest of the list.
+///
+/// We can't refer to `cons`, `true`, and `false` from the stdlib by their names
+/// because the user might have redefined them. Instead, we inline their definitions.
+pub fn list(items: Vec<Expr>, span: &Span) -> Expr {
+    let empty = pair(bool(true, span), bool(true, span), span);
+    items.into_iter().rev().fold(empty, |rest, item| {
+        pair(bool(false, span), pair(item, rest, span), span)
+    })
+}
+
+/// `cons a b` where `cons` is `λa λb λc c a b`, the same as in the stdlib.
+fn pair(a: Expr, b: Expr, span: &Span) -> Expr {
+    let body = call(
+        call(id("c", span), id("a", span), span),
+        id("b", span),
+        span,
+    );
+    let cons = def("a", def("b", def("c", body, span), span), span);
+    call(call(cons, a, span), b, span)
+}
+
+/// `λa λb a` for true and `λa λb b` for false.
+fn bool(value: bool, span: &Span) -> Expr {
+    let body = id(if value { "a" } else { "b" }, span);
+    def("a", def("b", body, span), span)
+}
+
+fn def(arg: &str, expr: Expr, span: &Span) -> Expr {
+    Expr::Def {
+        arg: arg.to_string(),
+        expr: Box::new(expr),
+        span: span.clone(),
+    }
+}
+
+fn call(target: Expr, arg: Expr, span: &Span) -> Expr {
+    Expr::Call {
+        target: Box::new(target),
+        arg: Box::new(arg),
+        span: span.clone(),
+    }
+}
+
+fn id(name: &str, span: &Span) -> Expr {
+    Expr::Id {
+        name: name.to_string(),
+        span: span.clone(),
+    }
+}
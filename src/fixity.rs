@@ -0,0 +1,124 @@
+//! Infix operators.
+//!
+//! The grammar knows nothing about operators, for the grammar `a + b * c`
+//! is just a call with 5 atoms. It's the parser who looks up the fixity
+//! declarations and rebuilds the call into the right tree of `Expr::Call` nodes.
+use crate::ast_nodes::{Assoc, Expr, Span};
+use std::collections::HashMap;
+
+/// How an infix operator should be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fixity {
+    pub assoc: Assoc,
+    pub precedence: u8,
+}
+
+/// All fixity declarations known to the parser.
+///
+/// A declaration in one module affects how other modules are parsed,
+/// so the table lives as long as the session does, and the session passes it
+/// to the parser for every new input.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Fixities {
+    table: HashMap<String, Fixity>,
+}
+
+impl Fixities {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare the fixity of the operator, overriding the previous declaration.
+    pub fn declare(&mut self, name: &str, fixity: Fixity) {
+        self.table.insert(name.to_string(), fixity);
+    }
+
+    /// Get the fixity of the operator if it was declared.
+    pub fn get(&self, name: &str) -> Option<Fixity> {
+        self.table.get(name).copied()
+    }
+}
+
+/// An infix operator found in the middle of a call.
+pub struct Operator {
+    pub name: String,
+    pub fixity: Fixity,
+    pub span: Span,
+}
+
+/// Build the call tree for operands separated by infix operators.
+///
+/// That's the [shunting yard] algorithm. We go from left to right and keep
+/// the operators we've seen on a stack. Before pushing a new operator,
+/// we pop from the stack all operators that bind tighter than the new one
+/// and apply them to the operands on their sides. When we reach the end,
+/// we apply all operators that are left on the stack.
+///
+/// For operators of the same precedence, it depends on the associativity.
+/// For left-associative operators, the one on the left binds tighter.
+/// For right-associative operators, the one on the right does. And if they
+/// don't agree or are non-associative, it's ambiguous, and we report an error
+/// with the index of the operator where we've noticed it.
+///
+/// [shunting yard]: https://en.wikipedia.org/wiki/Shunting_yard_algorithm
+pub fn resolve(first: Expr, rest: Vec<(Operator, Expr)>) -> Result<Expr, (usize, String)> {
+    let mut operands = vec![first];
+    let mut stack: Vec<Operator> = Vec::new();
+    for (index, (op, rhs)) in rest.into_iter().enumerate() {
+        while let Some(top) = stack.last() {
+            if top.fixity.precedence < op.fixity.precedence {
+                break;
+            }
+            if top.fixity.precedence == op.fixity.precedence {
+                match (top.fixity.assoc, op.fixity.assoc) {
+                    (Assoc::Left, Assoc::Left) => {}
+                    (Assoc::Right, Assoc::Right) => break,
+                    (Assoc::None, Assoc::None) if top.name == op.name => {
+                        let msg = format!(
+                            "non-associative operator `{}` cannot be chained, add braces",
+                            op.name
+                        );
+                        return Err((index, msg));
+                    }
+                    _ => {
+                        let msg = format!(
+                            "cannot mix `{}` and `{}` of the same precedence, add braces",
+                            top.name, op.name,
+                        );
+                        return Err((index, msg));
+                    }
+                }
+            }
+            let top = stack.pop().unwrap();
+            apply(&mut operands, top);
+        }
+        stack.push(op);
+        operands.push(rhs);
+    }
+    while let Some(top) = stack.pop() {
+        apply(&mut operands, top);
+    }
+    Ok(operands.pop().unwrap())
+}
+
+/// Replace the two topmost operands with the operator applied to them.
+///
+/// `a + b` is the same as `+ a b`, which is `(+ a) b`. The inner call spans
+/// from the left operand to the operator, and the outer one covers the whole thing.
+fn apply(operands: &mut Vec<Expr>, op: Operator) {
+    let rhs = operands.pop().unwrap();
+    let lhs = operands.pop().unwrap();
+    let target = Expr::Call {
+        span: lhs.span().to(&op.span),
+        target: Box::new(Expr::Id {
+            name: op.name,
+            span: op.span,
+        }),
+        arg: Box::new(lhs),
+    };
+    operands.push(Expr::Call {
+        span: target.span().to(rhs.span()),
+        target: Box::new(target),
+        arg: Box::new(rhs),
+    });
+}
@@ -0,0 +1,856 @@
+//! The code formatter, prints the AST back into the canonical source code.
+//!
+//! The grammar is quite relaxed about spaces and braces, so the same code
+//! can be written in many ways. The formatter picks one of them:
+//!
+//! + Exactly one space between atoms of a call and around `=` and infix operators.
+//! + Braces only where they are required.
+//! + `λ` or `\` for all definitions, depending on the style.
+//! + Each statement on its own line, at most one blank line between statements.
+//! + Line breaks inside of braces and list literals stay where they were written,
+//!   and each broken line is indented by 4 spaces more than the line it continues.
+//!
+//! The AST doesn't keep literals, `let`, multi-argument definitions,
+//! and infix operators: the parser desugars all of them. So, when printing,
+//! we look at the source code of each node (thanks to spans) to tell if the node
+//! was written with syntactic sugar. The same goes for comments, they're not in
+//! the AST at all, and we find them in the source code separately.
+use crate::ast_nodes::{Assoc, Expr, Module, Span, Stmt};
+use crate::fixity::{Fixities, Fixity};
+use crate::parser::{
+    find_dialect, is_word_char, parse_source_recovering, split_statements, Dialect, Rule,
+};
+use pest::error::Error;
+use std::cell::Cell;
+use std::collections::HashMap;
+
+/// Which symbol to use for lambdas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LambdaStyle {
+    /// `\x x`, easy to type on any keyboard.
+    #[default]
+    Backslash,
+    /// `λx x`, looks like in the books.
+    Lambda,
+}
+
+/// Format the source code.
+///
+/// The path, if given, is used in error messages. If the input has syntax
+/// errors, all of them are returned, and nothing is formatted.
+///
+/// The dialect is used unless the input has a pragma with another one.
+/// The output is in the same dialect as the input.
+#[allow(clippy::result_large_err)]
+pub fn format_source(
+    input: &str,
+    path: Option<&str>,
+    style: LambdaStyle,
+    dialect: Dialect,
+) -> Result<String, Vec<Error<Rule>>> {
+    // The parser doesn't accept an empty input, but there is nothing to format.
+    if input.trim().is_empty() {
+        return Ok(String::new());
+    }
+    let mut fixities = Fixities::new();
+    let (module, errors) = parse_source_recovering(input, path, &mut fixities, dialect);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    // If the pragma was wrong, the parser would have reported it.
+    let dialect = find_dialect(input).ok().flatten().unwrap_or(dialect);
+    let printer = Printer {
+        source: input,
+        fixities: &fixities,
+        style,
+        dialect,
+        braces: match_braces(input),
+        depth: Cell::new(0),
+    };
+    Ok(printer.module(&module))
+}
+
+/// A node of the AST as it was written in the source, with all syntactic sugar.
+enum Node<'a> {
+    /// A string or character literal. We print it exactly as it was written.
+    Literal(&'a str),
+    /// A list literal with its items and where `]` is in the source.
+    List(Vec<&'a Expr>, usize),
+    /// `let` with all its bindings and the body.
+    ///
+    /// Each binding has the name, where the binding starts in the source, and the value.
+    Let(Vec<(&'a str, usize, &'a Expr)>, &'a Expr),
+    /// A definition with one or more arguments.
+    Def(Vec<&'a str>, &'a Expr),
+    /// An infix operator with where it starts in the source, and both operands.
+    Infix(&'a str, usize, Fixity, &'a Expr, &'a Expr),
+    /// A regular call.
+    Call(&'a Expr, &'a Expr),
+    /// A name.
+    Id(&'a str),
+}
+
+/// Where in the outer expression the expression is printed.
+///
+/// That's what decides if the expression needs braces around it.
+#[derive(Clone, Copy)]
+enum Pos {
+    /// The expression is on its own: a statement, a list item,
+    /// the body of a definition, or inside of braces.
+    Top,
+    /// The value in a `let` binding.
+    Value,
+    /// The target of a call.
+    Head,
+    /// The argument of a call.
+    Arg,
+    /// The left operand of an infix operator.
+    Lhs(Fixity),
+    /// The right operand of an infix operator.
+    Rhs(Fixity),
+}
+
+struct Printer<'a> {
+    source: &'a str,
+    fixities: &'a Fixities,
+    style: LambdaStyle,
+    dialect: Dialect,
+    /// The position of the closing brace for each opening brace in the source.
+    braces: HashMap<usize, usize>,
+    /// How many braces and list literals are open around the printed expression.
+    ///
+    /// A line break is allowed only inside of them. Outside, the newline
+    /// would end the statement.
+    depth: Cell<usize>,
+}
+
+impl<'s> Printer<'s> {
+    /// Print all statements with comments and blank lines between them.
+    ///
+    /// We go through the input statement by statement, the same way as the parser
+    /// does when recovering from errors. Each chunk is either a statement,
+    /// a comment on its own line, or a blank line.
+    fn module(&self, module: &Module) -> String {
+        let (chunks, _) = split_statements(self.source);
+        let mut stmts = module.stmts.iter().peekable();
+        let mut lines: Vec<String> = Vec::new();
+        for chunk in chunks {
+            let text = &self.source[chunk.clone()];
+            let stmt = stmts.next_if(|stmt| chunk.contains(&stmt.span().start));
+            let line = match stmt {
+                Some(stmt) => self.stmt_line(stmt, text, chunk.start),
+                // A comment on its own line or a blank line.
+                None => trim_lines(text),
+            };
+            // Collapse multiple blank lines into one, and drop blank lines
+            // at the beginning of the module.
+            if line.is_empty() && lines.last().is_none_or(String::is_empty) {
+                continue;
+            }
+            lines.push(line);
+        }
+        while lines.last().is_some_and(String::is_empty) {
+            lines.pop();
+        }
+        let mut out = lines.join("\n");
+        out.push('\n');
+        out
+    }
+
+    /// Print the statement and the comment after it.
+    ///
+    /// If there are comments in the middle of the statement, we can't tell
+    /// where to put them when the statement is printed on one line. So, such
+    /// statements are left as they are, only trailing spaces are removed.
+    fn stmt_line(&self, stmt: &Stmt, chunk: &str, start: usize) -> String {
+        let comments = find_comments(chunk);
+        match comments.as_slice() {
+            [] => self.stmt(stmt),
+            [(pos, comment)] if start + pos >= stmt.span().end => {
+                format!("{}  {}", self.stmt(stmt), comment.trim_end())
+            }
+            _ => trim_lines(chunk),
+        }
+    }
+
+    fn stmt(&self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Assign { target, expr, .. } => {
+                format!("{target} = {}", self.expr(expr, Pos::Top, true))
+            }
+            // The path is printed as it was written, with all the escapes.
+            Stmt::Import { span, .. } => {
+                let text = self.text(span);
+                format!("import {}", text[text.find('"').unwrap()..].trim_end())
+            }
+            Stmt::Export { names, .. } => format!("export {}", names.join(" ")),
+            Stmt::Fixity {
+                assoc,
+                precedence,
+                operators,
+                ..
+            } => {
+                let kind = match assoc {
+                    Assoc::Left => "infixl",
+                    Assoc::Right => "infixr",
+                    Assoc::None => "infix",
+                };
+                format!("{kind} {precedence} {}", operators.join(" "))
+            }
+            Stmt::Expr { expr, .. } => self.expr(expr, Pos::Top, true),
+        }
+    }
+
+    /// Print the expression, adding braces if it's required in the given position.
+    ///
+    /// The `first` flag tells if the expression is the first thing in a call.
+    /// Only there an operator can be written without braces. Anywhere else,
+    /// it might be taken for an infix operator.
+    fn expr(&self, expr: &Expr, pos: Pos, first: bool) -> String {
+        let node = self.node(expr);
+        let braces = match (&node, pos) {
+            (Node::Def(..) | Node::Let(..), Pos::Top) | (Node::Def(..), Pos::Value) => false,
+            (Node::Def(..) | Node::Let(..), _)
+            | (Node::Call(..), Pos::Arg)
+            | (Node::Infix(..), Pos::Head | Pos::Arg) => true,
+            (Node::Infix(_, _, inner, ..), Pos::Lhs(outer)) => {
+                needs_braces(*inner, outer, Assoc::Left)
+            }
+            (Node::Infix(_, _, inner, ..), Pos::Rhs(outer)) => {
+                needs_braces(*inner, outer, Assoc::Right)
+            }
+            (Node::Id(name), _) => {
+                is_operator(name) && !(first && matches!(pos, Pos::Top | Pos::Head))
+            }
+            _ => false,
+        };
+        // Braces that aren't required are still kept if the user broke
+        // the lines inside of them. Without braces, the newline is not allowed.
+        let (open, close) = self.braces_around(expr.span()).unwrap_or_default();
+        let broken = self.source[open..close].contains('\n');
+        if !braces && !broken {
+            return self.node_text(node, first);
+        }
+        self.depth.set(self.depth.get() + 1);
+        let text = self.node_text(node, true);
+        self.depth.set(self.depth.get() - 1);
+        let mut out = String::from("(");
+        if broken && starts_with_newline(&self.source[open + 1..]) {
+            out.push_str(BREAK);
+        }
+        append(&mut out, &text);
+        if broken && ends_with_newline(&self.source[..close]) {
+            out.push('\n');
+        }
+        out.push(')');
+        out
+    }
+
+    fn node_text(&self, node: Node, first: bool) -> String {
+        match node {
+            Node::Literal(text) => text.to_string(),
+            Node::Id(name) => name.to_string(),
+            Node::List(items, close) => {
+                self.depth.set(self.depth.get() + 1);
+                let mut out = String::from("[");
+                for (i, item) in items.iter().enumerate() {
+                    let text = self.expr(item, Pos::Top, true);
+                    let sep = if i == 0 { "" } else { ", " };
+                    if self.breaks(item.span().start) {
+                        out.push_str(sep.trim_end());
+                        out.push_str(BREAK);
+                    } else {
+                        out.push_str(sep);
+                    }
+                    append(&mut out, &text);
+                }
+                self.depth.set(self.depth.get() - 1);
+                // When `]` is on its own line, each item ends with a comma,
+                // so that adding one more item changes only one line.
+                let items_end = self.source[..close].trim_end();
+                let items_end = items_end.strip_suffix(',').unwrap_or(items_end);
+                if !items.is_empty() && self.source[items_end.len()..close].contains('\n') {
+                    out.push_str(",\n");
+                }
+                out.push(']');
+                out
+            }
+            Node::Let(bindings, body) => {
+                let mut out = String::from("let ");
+                for (i, (name, start, value)) in bindings.into_iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                        out.push_str(if self.breaks(start) { BREAK } else { " " });
+                    }
+                    let value = self.expr(value, Pos::Value, true);
+                    append(&mut out, &format!("{name} = {value}"));
+                }
+                // The body goes on the next line with the same indentation
+                // as `let`, the same way as a statement after an assignment.
+                out.push_str(if self.breaks(body.span().start) {
+                    LET_BREAK
+                } else {
+                    " in "
+                });
+                append(&mut out, &self.expr(body, Pos::Top, true));
+                out
+            }
+            Node::Def(args, body) => {
+                let lambda = match self.style {
+                    LambdaStyle::Backslash => '\\',
+                    LambdaStyle::Lambda => 'λ',
+                };
+                let broken = self.breaks(body.span().start);
+                let text = self.expr(body, Pos::Top, true);
+                // In the dot dialect, the dot is always there. All arguments
+                // are single letters, so they can be written together: `λxy.x`.
+                let mut out = if self.dialect == Dialect::Dot {
+                    let letters = args.iter().all(|arg| !is_operator(arg));
+                    let args = args.join(if letters { "" } else { " " });
+                    format!("{lambda}{args}.")
+                } else if args.len() > 1 {
+                    format!("{lambda}{}.", args.join(" "))
+                } else {
+                    format!("{lambda}{}", args[0])
+                };
+                if broken {
+                    out.push_str(BREAK);
+                } else if self.dialect == Dialect::Standard {
+                    out.push(' ');
+                }
+                append(&mut out, &text);
+                out
+            }
+            Node::Infix(name, start, fixity, lhs, rhs) => {
+                let mut out = self.expr(lhs, Pos::Lhs(fixity), first);
+                out.push_str(if self.breaks(start) { BREAK } else { " " });
+                out.push_str(name);
+                out.push_str(if self.breaks(rhs.span().start) {
+                    BREAK
+                } else {
+                    " "
+                });
+                append(&mut out, &self.expr(rhs, Pos::Rhs(fixity), false));
+                out
+            }
+            Node::Call(target, arg) => {
+                let mut out = self.expr(target, Pos::Head, first);
+                out.push_str(if self.breaks(arg.span().start) {
+                    BREAK
+                } else {
+                    " "
+                });
+                append(&mut out, &self.expr(arg, Pos::Arg, false));
+                out
+            }
+        }
+    }
+
+    /// Find out how the expression was written in the source.
+    fn node<'e>(&self, expr: &'e Expr) -> Node<'e>
+    where
+        's: 'e,
+    {
+        let text = self.text(expr.span());
+        match expr {
+            // All nodes of a desugared literal have the span of the whole literal.
+            // So, if the source of a node is a literal, it's the literal itself,
+            // not a call that happens to start with a literal.
+            Expr::Call { .. } if is_literal_node(expr) => {
+                if text.starts_with('[') {
+                    Node::List(list_items(expr), expr.span().end - 1)
+                } else {
+                    Node::Literal(text)
+                }
+            }
+            Expr::Numeral { .. } => Node::Literal(text),
+            // `let x = a, y = b in c` is `(\x (\y c) b) a`. The calls for all bindings
+            // have the span of the whole `let`, and the definitions
+            // have the span of the binding, which starts with the name.
+            Expr::Call { target, arg, span } if self.is_let(target, text) => {
+                let mut bindings = Vec::new();
+                let mut value = arg.as_ref();
+                let mut def = target.as_ref();
+                loop {
+                    let Expr::Def {
+                        arg: name,
+                        expr,
+                        span: binding,
+                    } = def
+                    else {
+                        unreachable!()
+                    };
+                    bindings.push((name.as_str(), binding.start, value));
+                    match expr.as_ref() {
+                        Expr::Call {
+                            target,
+                            arg,
+                            span: inner,
+                        } if inner == span && matches!(target.as_ref(), Expr::Def { .. }) => {
+                            def = target;
+                            value = arg;
+                        }
+                        body => return Node::Let(bindings, body),
+                    }
+                }
+            }
+            // In `\a b. c`, the inner definition spans from `b`, not from a lambda.
+            Expr::Def { arg, expr, .. } => {
+                let mut args = vec![arg.as_str()];
+                let mut body = expr.as_ref();
+                while let Expr::Def { arg, expr, span } = body {
+                    let text = self.text(span);
+                    if !text.starts_with(arg.as_str()) {
+                        break;
+                    }
+                    args.push(arg);
+                    body = expr;
+                }
+                Node::Def(args, body)
+            }
+            // `a + b` is `+ a b`, and the inner call spans from `a` to `+`.
+            // If it was written as `+ a b`, the inner call starts with `+`.
+            Expr::Call {
+                target, arg: rhs, ..
+            } => {
+                if let Expr::Call {
+                    target: op,
+                    arg: lhs,
+                    ..
+                } = target.as_ref()
+                {
+                    if let Expr::Id { name, span } = op.as_ref() {
+                        if let Some(fixity) = self.fixities.get(name) {
+                            if span.start > lhs.span().start {
+                                return Node::Infix(name, span.start, fixity, lhs, rhs);
+                            }
+                        }
+                    }
+                }
+                Node::Call(target, rhs)
+            }
+            Expr::Id { name, .. } => Node::Id(name),
+        }
+    }
+
+    fn is_let(&self, target: &Expr, text: &str) -> bool {
+        let Expr::Def { span, .. } = target else {
+            return false;
+        };
+        let def_text = self.text(span);
+        let is_keyword = text.starts_with("let") && text[3..].starts_with([' ', '\t', '\\']);
+        is_keyword && !def_text.starts_with(['\\', 'λ'])
+    }
+
+    fn text(&self, span: &Span) -> &'s str {
+        &self.source[span.start..span.end]
+    }
+
+    /// Check if there is a line break in the source right before the node
+    /// that starts at the given position, and it can be kept.
+    ///
+    /// The break must be before any braces that open right before the node:
+    /// in `f\n  (g a)` it's a break in the call, and in `f (\n  g a)`,
+    /// it's inside of the braces around `g a`.
+    fn breaks(&self, pos: usize) -> bool {
+        let before = &self.source[..pos];
+        let before = before.trim_end_matches(|ch: char| ch.is_whitespace() || ch == '(');
+        let gap = &self.source[before.len()..pos];
+        self.depth.get() > 0 && gap.split('(').next().unwrap().contains('\n')
+    }
+
+    /// Find the braces around the expression in the source, if there are any.
+    ///
+    /// The span of an expression doesn't include its braces. And it may start
+    /// or end inside of braces around its first or last atom: the span of `(g a) b`
+    /// starts at `g`. So, we look at each `(` right before the expression,
+    /// from the closest one, and take the first one that closes after the expression.
+    fn braces_around(&self, span: &Span) -> Option<(usize, usize)> {
+        let before =
+            self.source[..span.start].trim_end_matches(|ch: char| ch.is_whitespace() || ch == '(');
+        self.source[before.len()..span.start]
+            .rmatch_indices('(')
+            .map(|(pos, _)| before.len() + pos)
+            .filter_map(|open| Some((open, *self.braces.get(&open)?)))
+            .find(|&(_, close)| {
+                close >= span.end
+                    && self.source[span.end..close]
+                        .chars()
+                        .all(|ch| ch.is_whitespace() || ch == ')')
+            })
+    }
+}
+
+/// A line break in the output. The indentation is relative to the line where
+/// the broken expression starts, and `append` adds the indentation of that line.
+const BREAK: &str = "\n    ";
+
+/// A line break before the body of `let`.
+///
+/// The body is aligned with `let`, but we can tell where `let` is only when
+/// it's added to the output. If `let` starts its line, the body has the same
+/// indentation. If not, it's indented like any other broken line.
+/// The zero char never gets into the output: a literal can't have a newline before it.
+const LET_BREAK: &str = " in\n\0";
+
+/// Check if the text starts with spaces and a newline.
+fn starts_with_newline(text: &str) -> bool {
+    text[..text.len() - text.trim_start().len()].contains('\n')
+}
+
+/// Check if the text ends with a newline and spaces.
+fn ends_with_newline(text: &str) -> bool {
+    text[text.trim_end().len()..].contains('\n')
+}
+
+/// Find the closing brace for each opening brace in the source.
+///
+/// Same as `find_comments`, it skips braces inside of comments
+/// and string and character literals.
+fn match_braces(source: &str) -> HashMap<usize, usize> {
+    let mut braces = HashMap::new();
+    let mut open = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some((pos, ch)) = chars.next() {
+        match ch {
+            '(' => open.push(pos),
+            ')' => {
+                if let Some(start) = open.pop() {
+                    braces.insert(start, pos);
+                }
+            }
+            '#' => while chars.next_if(|(_, ch)| *ch != '\n').is_some() {},
+            '"' | '\'' => {
+                let mut escaped = false;
+                while let Some((_, next)) = chars.next_if(|(_, ch)| *ch != '\n') {
+                    match next {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        _ if next == ch => break,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    braces
+}
+
+/// Add the text to the end of the output.
+///
+/// The text may have multiple lines. Its lines after the first one are indented
+/// relative to the first one, and so they get the same indentation as the last line
+/// of the output, where the first line of the text goes.
+fn append(out: &mut String, text: &str) {
+    let line = &out[out.rfind('\n').map_or(0, |pos| pos + 1)..];
+    let indent = line.len() - line.trim_start().len();
+    let let_indent = if line.trim_start().is_empty() { 0 } else { 4 };
+    let mut lines = text.split('\n');
+    out.push_str(lines.next().unwrap_or_default());
+    for line in lines {
+        out.push('\n');
+        out.push_str(&" ".repeat(indent));
+        match line.strip_prefix('\0') {
+            Some(line) => {
+                out.push_str(&" ".repeat(let_indent));
+                out.push_str(line);
+            }
+            None => out.push_str(line),
+        }
+    }
+}
+
+/// Check if an operand needs braces.
+///
+/// An operand that binds weaker than the operator needs braces.
+/// If it has the same precedence, it can go without braces only on the side
+/// the operator is associative to: `a - b - c` is `(a - b) - c`.
+fn needs_braces(inner: Fixity, outer: Fixity, side: Assoc) -> bool {
+    if inner.precedence != outer.precedence {
+        return inner.precedence < outer.precedence;
+    }
+    !(inner.assoc == side && outer.assoc == side)
+}
+
+/// Check if the expression is a node of a desugared string or list literal.
+///
+/// Each node is a pair, `cons a b`, and `cons` is a definition.
+/// All of them have exactly the same span.
+fn is_literal_node(expr: &Expr) -> bool {
+    let Expr::Call { target, span, .. } = expr else {
+        return false;
+    };
+    let Expr::Call {
+        target: cons,
+        span: inner,
+        ..
+    } = target.as_ref()
+    else {
+        return false;
+    };
+    matches!(cons.as_ref(), Expr::Def { span: def, .. } if def == span) && inner == span
+}
+
+/// Get items of a desugared list literal.
+///
+/// Each node of the list is `cons is_end (cons item rest)`,
+/// and the last one is `cons true true`. See `desugar::list`.
+fn list_items(expr: &Expr) -> Vec<&Expr> {
+    let mut items = Vec::new();
+    let mut node = expr;
+    loop {
+        let (is_end, rest) = as_pair(node);
+        if is_true(is_end) {
+            return items;
+        }
+        let (item, rest) = as_pair(rest);
+        items.push(item);
+        node = rest;
+    }
+}
+
+/// Check if the expression is `λa λb a`.
+fn is_true(expr: &Expr) -> bool {
+    let Expr::Def { expr, .. } = expr else {
+        return false;
+    };
+    let Expr::Def { expr, .. } = expr.as_ref() else {
+        return false;
+    };
+    matches!(expr.as_ref(), Expr::Id { name, .. } if name == "a")
+}
+
+fn as_pair(expr: &Expr) -> (&Expr, &Expr) {
+    let Expr::Call { target, arg: b, .. } = expr else {
+        unreachable!()
+    };
+    let Expr::Call { arg: a, .. } = target.as_ref() else {
+        unreachable!()
+    };
+    (a, b)
+}
+
+/// Check if the name is made of symbols, like `+`.
+fn is_operator(name: &str) -> bool {
+    !name.starts_with(is_word_char)
+}
+
+/// Find all comments in the text.
+///
+/// Returns the byte offset of each comment and its text, including `#`.
+/// Same as `split_statements`, it skips `#` inside of string and character literals.
+fn find_comments(text: &str) -> Vec<(usize, &str)> {
+    let mut comments = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((pos, ch)) = chars.next() {
+        match ch {
+            '#' => {
+                let end = text[pos..].find('\n').map_or(text.len(), |end| pos + end);
+                comments.push((pos, &text[pos..end]));
+                while chars.next_if(|(next, _)| *next < end).is_some() {}
+            }
+            '"' | '\'' => {
+                let mut escaped = false;
+                while let Some((_, next)) = chars.next_if(|(_, ch)| *ch != '\n') {
+                    match next {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        _ if next == ch => break,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    comments
+}
+
+/// Remove spaces at the beginning of the text and at the end of each line.
+fn trim_lines(text: &str) -> String {
+    let lines: Vec<_> = text.trim().lines().map(str::trim_end).collect();
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use rstest::rstest;
+
+    fn fmt(input: &str) -> String {
+        format_source(input, None, LambdaStyle::Backslash, Dialect::Standard).unwrap()
+    }
+
+    #[rstest]
+    #[case::id("x", "x")]
+    #[case::spaces("  f   a  b  ", "f a b")]
+    #[case::assign("id=\\x   x", "id = \\x x")]
+    #[case::lambda("id = λx x", "id = \\x x")]
+    #[case::braces("(f a) b", "f a b")]
+    #[case::braces("f (g a)", "f (g a)")]
+    #[case::braces("(\\x x) a", "(\\x x) a")]
+    #[case::braces("f (\\x x)", "f (\\x x)")]
+    #[case::braces("\\x (f x)", "\\x f x")]
+    #[case::multi_arg("\\a  b .a", "\\a b. a")]
+    #[case::multi_arg("\\a.a", "\\a a")]
+    #[case::multi_arg("\\a \\b a", "\\a \\b a")]
+    #[case::qualified("\\a. nat.inc a", "\\a nat.inc a")]
+    #[case::qualified("\\a (b nat.inc)", "\\a b nat.inc")]
+    #[case::qualified("\\a (f (nat.inc a))", "\\a f (nat.inc a)")]
+    #[case::let_in("let x=a ,y = x in  f y", "let x = a, y = x in f y")]
+    #[case::let_in("let x = a in let y = x in y", "let x = a in let y = x in y")]
+    #[case::let_in("f (let x = a in x)", "f (let x = a in x)")]
+    #[case::let_in("let f = \\x x in f", "let f = \\x x in f")]
+    #[case::let_in("let x = (let y = a in y) in x", "let x = (let y = a in y) in x")]
+    #[case::list("[ a,b ,  [c] ]", "[a, b, [c]]")]
+    #[case::list("f []", "f []")]
+    #[case::list("[f a] b", "[f a] b")]
+    #[case::list("[\\x x, let y = a in y]", "[\\x x, let y = a in y]")]
+    #[case::string("f  \"a\\\"b\" 'c'", "f \"a\\\"b\" 'c'")]
+    #[case::string("\"ab\" x", "\"ab\" x")]
+    #[case::string("\\x '\\n'", "\\x '\\n'")]
+    #[case::string("f  '🦀'  \"λ🦀\"", "f '🦀' \"λ🦀\"")]
+    #[case::number("add 2  3", "add 2 3")]
+    #[case::import("import   \"a.rb\"", "import \"a.rb\"")]
+    #[case::export("export  a   b", "export a b")]
+    #[case::fixity("infixl  6  +  -", "infixl 6 + -")]
+    #[case::continuation("f \\\n  a", "f a")]
+    #[case::unicode("α  =  λβ   β  Ω", "α = \\β β Ω")]
+    fn format_expr(#[case] input: &str, #[case] exp: &str) {
+        assert_eq!(fmt(input), format!("{exp}\n"));
+    }
+
+    #[rstest]
+    #[case("a + b * c", "a + b * c")]
+    #[case("(a + b) * c", "(a + b) * c")]
+    #[case("a * (b + c)", "a * (b + c)")]
+    #[case("(a - b) - c", "a - b - c")]
+    #[case("a - (b - c)", "a - (b - c)")]
+    #[case("a $ (b $ c)", "a $ b $ c")]
+    #[case("(a $ b) $ c", "(a $ b) $ c")]
+    #[case("(a == b) == c", "(a == b) == c")]
+    #[case("f a + g b", "f a + g b")]
+    #[case("f (a + b)", "f (a + b)")]
+    #[case("(a + b) c", "(a + b) c")]
+    #[case("+ a b", "+ a b")]
+    #[case("(+) a b", "+ a b")]
+    #[case("f (+) b", "f (+) b")]
+    #[case("a * (+ b c)", "a * (+) b c")]
+    #[case("a + (\\x x)", "a + (\\x x)")]
+    fn format_operators(#[case] input: &str, #[case] exp: &str) {
+        let decls = "infixl 6 + -\ninfixl 7 *\ninfixr 0 $\ninfix 4 ==\n";
+        let output = fmt(&format!("{decls}{input}"));
+        assert_eq!(output, format!("{decls}{exp}\n"));
+    }
+
+    #[rstest]
+    #[case::empty("", "")]
+    #[case::blank("\n\n  \n", "")]
+    #[case::comment("# hello  \n", "# hello\n")]
+    #[case::trailing("a   # hello\n", "a  # hello\n")]
+    #[case::blank_lines("\n\na\n\n\n\nb\n\n", "a\n\nb\n")]
+    #[case::comments("# a\n  # b\nx = y\n", "# a\n# b\nx = y\n")]
+    #[case::inner("f (a # first\n  b  \n)\n", "f (a # first\n  b\n)\n")]
+    #[case::hash_in_string("f \"#\"  # c", "f \"#\"  # c\n")]
+    fn format_layout(#[case] input: &str, #[case] exp: &str) {
+        assert_eq!(fmt(input), exp);
+    }
+
+    // Line breaks are kept inside of braces and lists, and only there.
+    #[rstest]
+    #[case::call("f (a\n  b)", "f (a\n    b)")]
+    #[case::call("f (a b\n   c\n   d)", "f (a b\n    c\n    d)")]
+    #[case::call("f (\n  a b\n)", "f (\n    a b\n)")]
+    #[case::call("f (g\n  (h a))", "f (g\n    (h a))")]
+    #[case::nested("f (g (a\n  b)\n  c)", "f (g (a\n    b)\n    c)")]
+    #[case::def("f (\\x\n  x)", "f (\\x\n    x)")]
+    #[case::def("f (\\a b.\n  a)", "f (\\a b.\n    a)")]
+    #[case::list("[a,\n  b,\n]", "[a,\n    b,\n]")]
+    #[case::list("[\n a,\n b]", "[\n    a,\n    b]")]
+    #[case::list("[a,\n  f (b\n  c)]", "[a,\n    f (b\n        c)]")]
+    #[case::let_in(
+        "(let a = b,\n  c = d in\n  f a c)",
+        "(let a = b,\n    c = d in\n    f a c)"
+    )]
+    #[case::let_in("(\\x\n  let a = b in\n  a)", "(\\x\n    let a = b in\n    a)")]
+    #[case::braces("x = (f a\n  b)", "x = (f a\n    b)")]
+    #[case::braces("x = (f a b)", "x = f a b")]
+    #[case::continuation("f \\\n  a", "f a")]
+    fn format_line_breaks(#[case] input: &str, #[case] exp: &str) {
+        assert_eq!(fmt(input), format!("{exp}\n"));
+    }
+
+    #[test]
+    fn format_line_breaks_operators() {
+        let input = "infixl 6 +\nx = (a +\n  b\n  + c)\n";
+        assert_eq!(fmt(input), "infixl 6 +\nx = (a +\n    b\n    + c)\n");
+    }
+
+    // A multiline definition is printed exactly as it was written.
+    #[test]
+    fn format_multiline() {
+        let input = "fib = Y (\\f \\n\n    let prev = dec n, prev2 = dec prev in\n    lte n 2\n        1\n        (add (f prev) (f prev2))\n)\n";
+        assert_eq!(fmt(input), input);
+        let messy = "fib = Y (\\f   \\n\n  let prev = dec n ,prev2 = dec prev in\n  lte n 2\n   1\n   (add (f prev)   (f prev2))\n  )\n";
+        assert_eq!(fmt(messy), input);
+        assert_eq!(
+            parse(&fmt(messy)).unwrap().to_sexpr(),
+            parse(messy).unwrap().to_sexpr()
+        );
+    }
+
+    #[test]
+    fn format_style() {
+        let output = format_source(
+            "f = \\a \\b c. a",
+            None,
+            LambdaStyle::Lambda,
+            Dialect::Standard,
+        )
+        .unwrap();
+        assert_eq!(output, "f = λa λb c. a\n");
+    }
+
+    #[rstest]
+    #[case("λx y . x y", "\\xy.x y")]
+    #[case("(λx.x x)(λy.y)", "(\\x.x x) (\\y.y)")]
+    #[case("λx.λy.x", "\\x.\\y.x")]
+    #[case("λ+ x.x", "\\+ x.x")]
+    fn format_dot_dialect(#[case] input: &str, #[case] exp: &str) {
+        let output = format_source(input, None, LambdaStyle::Backslash, Dialect::Dot).unwrap();
+        assert_eq!(output, format!("{exp}\n"));
+        let input = format!("# dialect: dot\n{input}");
+        let output = format_source(&input, None, LambdaStyle::Backslash, Dialect::Standard);
+        assert_eq!(output.unwrap(), format!("# dialect: dot\n{exp}\n"));
+    }
+
+    #[test]
+    fn format_errors() {
+        let errors = format_source(
+            "a = \nb\nc = ]",
+            Some("a.rb"),
+            LambdaStyle::Lambda,
+            Dialect::Standard,
+        );
+        let errors = errors.unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].to_string().contains("a.rb:1:5"));
+    }
+
+    // The formatted code must mean exactly the same as the original,
+    // and formatting it again must change nothing.
+    #[test]
+    fn format_stdlib() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/stdlib");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let source = std::fs::read_to_string(&path).unwrap();
+            let output = fmt(&source);
+            let original = parse(&source).unwrap().to_sexpr();
+            assert_eq!(parse(&output).unwrap().to_sexpr(), original, "{path:?}");
+            assert_eq!(fmt(&output), output, "{path:?}");
+        }
+    }
+}
@@ -0,0 +1,689 @@
+//! Binary Lambda Calculus, the lambda calculus as a bit string.
+//!
+//! [BLC] is the encoding of lambda terms designed by John Tromp.
+//! It's based on de Bruijn notation (see `Term`), and each term is encoded as:
+//!
+//! + `00` followed by the body for a lambda,
+//! + `01` followed by the function and then the argument for a call,
+//! + `1` repeated `n` times followed by `0` for the variable with the index `n`.
+//!
+//! So, `λ λ 2` (`K`) is `0000110`. The code doesn't need any separators,
+//! it's always clear where a term ends. A program is a closed term,
+//! and it can be written either as a text of `0` and `1` characters
+//! or packed into bytes, 8 bits in each byte, the most significant bit first.
+//!
+//! A program is a function that accepts its input as a list
+//! and returns its output as a list. Lists are made of pairs `λz z head tail`
+//! and the end of the list is `λa λb b`. Bits are booleans, with `0` being
+//! `λa λb a` (`true`) and `1` being `λa λb b` (`false`). In the bytes mode
+//! (BLC8), each item of a list is a byte, which is a list of 8 bits.
+//! In the bits mode, each item of the input is a bit of the input bytes,
+//! and each bit of the output is printed as a `0` or `1` character.
+//!
+//! Keep in mind that lists here are not the same as lists in the stdlib,
+//! so the stdlib functions for lists can't be used for BLC programs.
+//!
+//! [BLC]: https://tromp.github.io/cl/Binary_lambda_calculus.html
+use crate::interpreter::Term;
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+use std::sync::LazyLock;
+
+/// How a BLC program sees its input and output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IoMode {
+    /// Input and output are lists of bytes, each byte is a list of 8 bits.
+    #[default]
+    Bytes,
+    /// Input and output are lists of bits.
+    Bits,
+}
+
+impl Term {
+    /// Encode the term as a BLC bit string, like `0000110`.
+    ///
+    /// Only closed terms can be encoded, free variables have no index.
+    /// BLC writes each node before its children, and that's the order
+    /// of `Term::nodes`, so even very deep terms are encoded in a loop.
+    pub fn to_blc(&self) -> anyhow::Result<String> {
+        let mut bits = String::new();
+        for term in self.nodes() {
+            match term {
+                Term::Var(index) => {
+                    bits.extend(std::iter::repeat_n('1', *index));
+                    bits.push('0');
+                }
+                Term::Free(name) => {
+                    anyhow::bail!("cannot encode free variable `{name}`, the term must be closed")
+                }
+                Term::Lam(_) => bits.push_str("00"),
+                Term::App(..) => bits.push_str("01"),
+            }
+        }
+        Ok(bits)
+    }
+
+    /// Decode a BLC program, either as text or packed into bytes.
+    ///
+    /// If the input has only `0` and `1` characters (and whitespace between them),
+    /// it's a text, and it must have exactly one term. Otherwise, it's packed,
+    /// and the bits after the term are ignored because they are the padding
+    /// for the last byte.
+    ///
+    /// It's never ambiguous for valid programs: both `0` and `1` characters
+    /// start with the bits `0011`, and a packed program can't start with `λ 2`.
+    pub fn from_blc(input: &[u8]) -> anyhow::Result<Term> {
+        let is_text = input.iter().any(|b| matches!(b, b'0' | b'1'))
+            && input
+                .iter()
+                .all(|b| matches!(b, b'0' | b'1') || b.is_ascii_whitespace());
+        let bits: Vec<bool> = if is_text {
+            input
+                .iter()
+                .filter(|b| !b.is_ascii_whitespace())
+                .map(|b| *b == b'1')
+                .collect()
+        } else {
+            input.iter().flat_map(|b| byte_bits(*b)).collect()
+        };
+        let mut pos = 0;
+        let term = decode(&bits, &mut pos, 0)?;
+        if is_text && pos != bits.len() {
+            anyhow::bail!("unexpected bits after the end of the program at bit {pos}");
+        }
+        Ok(term)
+    }
+}
+
+/// Decode a single term starting at the given bit, moving the position past the term.
+///
+/// The depth is the number of lambdas around the term,
+/// so we know if a variable points outside of the program.
+///
+/// Terms can be very deep, so instead of calling itself for the children,
+/// it keeps the nodes still waiting for them on a stack.
+fn decode(bits: &[bool], pos: &mut usize, mut depth: usize) -> anyhow::Result<Term> {
+    /// A node that is waiting for its children to be decoded.
+    enum Waiting {
+        Lam,
+        Target,
+        Arg(Term),
+    }
+    let mut stack = Vec::new();
+    loop {
+        let start = *pos;
+        let mut next = || {
+            let bit = bits.get(*pos).copied();
+            *pos += 1;
+            bit.ok_or_else(|| {
+                anyhow::anyhow!("unexpected end of the program in the term at bit {start}")
+            })
+        };
+        let mut term = match (next()?, next()?) {
+            (false, false) => {
+                stack.push(Waiting::Lam);
+                depth += 1;
+                continue;
+            }
+            (false, true) => {
+                stack.push(Waiting::Target);
+                continue;
+            }
+            (true, is_one) => {
+                let mut index = 1;
+                if is_one {
+                    index += 1;
+                    while next()? {
+                        index += 1;
+                    }
+                }
+                if index > depth {
+                    anyhow::bail!("variable {index} at bit {start} doesn't point to any lambda");
+                }
+                Term::Var(index)
+            }
+        };
+        // The term is complete, so are all the nodes waiting only for it.
+        loop {
+            match stack.pop() {
+                None => return Ok(term),
+                Some(Waiting::Lam) => {
+                    term = Term::Lam(term.into());
+                    depth -= 1;
+                }
+                Some(Waiting::Target) => {
+                    stack.push(Waiting::Arg(term));
+                    break;
+                }
+                Some(Waiting::Arg(target)) => term = Term::App(target.into(), term.into()),
+            }
+        }
+    }
+}
+
+/// Pack a BLC bit string into bytes, padding the last byte with zeros.
+///
+/// Everything except `0` and `1` is ignored.
+#[must_use]
+pub fn pack_bits(bits: &str) -> Vec<u8> {
+    let bits: Vec<_> = bits.chars().filter(|c| matches!(c, '0' | '1')).collect();
+    bits.chunks(8)
+        .map(|chunk| {
+            let byte = chunk
+                .iter()
+                .fold(0u8, |byte, bit| (byte << 1) | u8::from(*bit == '1'));
+            // The last chunk might be shorter, the missing bits are zeros.
+            byte << (8 - chunk.len())
+        })
+        .collect()
+}
+
+/// Run the BLC program on the input and write its output.
+///
+/// The output is written as soon as each item of the output list is known,
+/// so programs producing infinite output can be run too.
+pub fn run_blc(
+    program: &Term,
+    input: &[u8],
+    mode: IoMode,
+    out: &mut impl Write,
+) -> anyhow::Result<()> {
+    let machine = Machine { input };
+    let input = thunk(match mode {
+        IoMode::Bytes => State::Bytes(0),
+        IoMode::Bits => State::Bits(0, input.len() * 8),
+    });
+    let program = thunk(State::Delayed(program, None));
+    let mut node = thunk(State::Done(machine.call(program, vec![input])?));
+    while let Some((head, tail)) = machine.uncons(&node)? {
+        match mode {
+            IoMode::Bytes => {
+                let mut byte = 0u8;
+                let mut bits = head;
+                for _ in 0..8 {
+                    let Some((bit, rest)) = machine.uncons(&bits)? else {
+                        anyhow::bail!("a byte in the output has less than 8 bits");
+                    };
+                    byte = (byte << 1) | machine.as_bit(bit)?;
+                    bits = rest;
+                }
+                if machine.uncons(&bits)?.is_some() {
+                    anyhow::bail!("a byte in the output has more than 8 bits");
+                }
+                out.write_all(&[byte])?;
+            }
+            IoMode::Bits => {
+                out.write_all(&[b'0' + machine.as_bit(head)?])?;
+            }
+        }
+        out.flush()?;
+        node = tail;
+    }
+    Ok(())
+}
+
+/// Bits of the byte, the most significant bit first.
+fn byte_bits(byte: u8) -> impl Iterator<Item = bool> {
+    (0..8).rev().map(move |i| byte & (1 << i) != 0)
+}
+
+/// A lazy evaluator for BLC programs.
+///
+/// `Value::eval` and the reducer replace arguments in the body of a function
+/// with their values. That's fine for small terms, but the input of a BLC
+/// program is a huge term, and each step of walking through it would copy
+/// the whole rest of it. So, cat takes cubic time.
+///
+/// Instead, the machine never changes terms. A function is its body together
+/// with the environment: values for the variables pointing outside of the body.
+/// Variable 1 is the first value in the environment, variable 2 is the second one,
+/// and so on. Calling a function just adds the argument in front of the environment.
+/// The argument isn't evaluated until something needs it, and then it's evaluated
+/// only once, and all places it was passed to share the result. That's call-by-need,
+/// the same as `Strategy::CallByNeed` but without substitution.
+///
+/// The input list is made lazily too: the next pair is made only when
+/// the program looks at it. So, each step of walking through the input or
+/// the output takes the same time, no matter how long the input is.
+///
+/// The machine keeps everything it hasn't finished yet on its own stack
+/// rather than on the Rust one, so long chains of calls don't overflow it.
+struct Machine<'t> {
+    input: &'t [u8],
+}
+
+/// A value that might be not evaluated yet.
+///
+/// When it's evaluated, the state is replaced by the result,
+/// and so all the places it was passed to see the result.
+type Thunk<'t> = Rc<RefCell<State<'t>>>;
+
+/// The environment of a function, see `Machine`.
+type Env<'t> = Option<Rc<Frame<'t>>>;
+
+/// One value in the environment and the rest of it.
+struct Frame<'t> {
+    value: Thunk<'t>,
+    next: Env<'t>,
+}
+
+/// Frames are dropped one by one rather than recursively.
+///
+/// A list the program has walked through is a chain of frames and thunks
+/// as long as the list, and dropping it recursively would overflow the stack.
+impl Drop for Frame<'_> {
+    fn drop(&mut self) {
+        let mut envs = vec![self.next.take()];
+        envs.extend(take_env(&self.value));
+        while let Some(env) = envs.pop() {
+            // If something else still holds the frame, it's not dropped yet.
+            if let Some(Ok(mut frame)) = env.map(Rc::try_unwrap) {
+                envs.push(frame.next.take());
+                envs.extend(take_env(&frame.value));
+            }
+        }
+    }
+}
+
+/// If nothing else holds the thunk, take the environment out of it.
+fn take_env<'t>(value: &Thunk<'t>) -> Option<Env<'t>> {
+    if Rc::strong_count(value) != 1 {
+        return None;
+    }
+    match value.replace(State::Forced) {
+        State::Delayed(_, env) | State::Done(Whnf::Lam(_, env)) => Some(env),
+        _ => None,
+    }
+}
+
+enum State<'t> {
+    /// Not evaluated yet: the term and its environment.
+    Delayed(&'t Term, Env<'t>),
+    /// Being evaluated right now. If the value is needed again before
+    /// it's done, the value depends on itself and will never be done.
+    Forced,
+    /// Already evaluated.
+    Done(Whnf<'t>),
+    /// A part of the input from the bit `start` to the bit `end`,
+    /// as a list of bits. It's the whole input in the bits mode
+    /// or a single byte in the bytes mode.
+    Bits(usize, usize),
+    /// A part of the input from the byte, as a list of bytes.
+    Bytes(usize),
+}
+
+/// A value in the weak head normal form, the result of the evaluation.
+#[derive(Clone)]
+enum Whnf<'t> {
+    /// A function: its body and its environment.
+    Lam(&'t Term, Env<'t>),
+    /// A marker passed to the output to see what it does with it, see `as_bit`.
+    Marker(u8),
+    /// A marker that was called. The output isn't what we expected,
+    /// and whoever looks at it reports that.
+    Stuck,
+}
+
+/// What to do with the value when it's evaluated.
+enum Cont<'t> {
+    /// Call it with the argument.
+    Arg(Thunk<'t>),
+    /// Store it as the value of the thunk.
+    Update(Thunk<'t>),
+}
+
+/// What the machine is doing right now.
+enum Control<'t> {
+    /// Evaluating the term in the environment.
+    Eval(&'t Term, Env<'t>),
+    /// Evaluating the thunk, unless it's already done.
+    Force(Thunk<'t>),
+    /// Passing the value to what is on top of the stack.
+    Return(Whnf<'t>),
+}
+
+impl<'t> Machine<'t> {
+    /// Call the function with the given arguments and evaluate the result.
+    fn call(&self, target: Thunk<'t>, args: Vec<Thunk<'t>>) -> anyhow::Result<Whnf<'t>> {
+        let mut stack: Vec<_> = args.into_iter().rev().map(Cont::Arg).collect();
+        let mut control = Control::Force(target);
+        loop {
+            control = match control {
+                Control::Eval(term, env) => match term {
+                    Term::Var(index) => Control::Force(lookup(&env, *index)?),
+                    Term::Free(name) => anyhow::bail!("unbound variable `{name}`"),
+                    Term::Lam(body) => Control::Return(Whnf::Lam(body, env)),
+                    Term::App(target, arg) => {
+                        stack.push(Cont::Arg(delay(arg, &env)?));
+                        Control::Eval(target, env)
+                    }
+                },
+                Control::Force(value) => match value.replace(State::Forced) {
+                    State::Delayed(term, env) => {
+                        stack.push(Cont::Update(value));
+                        Control::Eval(term, env)
+                    }
+                    State::Forced => {
+                        anyhow::bail!("the program never stops: a value depends on itself")
+                    }
+                    State::Done(result) => {
+                        value.replace(State::Done(result.clone()));
+                        Control::Return(result)
+                    }
+                    State::Bits(start, end) => {
+                        let result = self.bits(start, end);
+                        value.replace(State::Done(result.clone()));
+                        Control::Return(result)
+                    }
+                    State::Bytes(index) => {
+                        let result = self.bytes(index);
+                        value.replace(State::Done(result.clone()));
+                        Control::Return(result)
+                    }
+                },
+                Control::Return(result) => match stack.pop() {
+                    None => return Ok(result),
+                    Some(Cont::Update(value)) => {
+                        value.replace(State::Done(result.clone()));
+                        Control::Return(result)
+                    }
+                    Some(Cont::Arg(arg)) => match result {
+                        Whnf::Lam(body, env) => {
+                            let env = Some(Rc::new(Frame {
+                                value: arg,
+                                next: env,
+                            }));
+                            Control::Eval(body, env)
+                        }
+                        // Everything waiting for the result is stuck too.
+                        Whnf::Marker(_) | Whnf::Stuck => {
+                            for cont in stack.drain(..) {
+                                if let Cont::Update(value) = cont {
+                                    value.replace(State::Done(Whnf::Stuck));
+                                }
+                            }
+                            return Ok(Whnf::Stuck);
+                        }
+                    },
+                },
+            };
+        }
+    }
+
+    /// The list of the input bits from `start` to `end`, see `State::Bits`.
+    fn bits(&self, start: usize, end: usize) -> Whnf<'t> {
+        if start == end {
+            return bit(true);
+        }
+        let is_one = self.input[start / 8] & (1 << (7 - start % 8)) != 0;
+        let head = thunk(State::Done(bit(is_one)));
+        pair(head, thunk(State::Bits(start + 1, end)))
+    }
+
+    /// The list of the input bytes from the index, see `State::Bytes`.
+    fn bytes(&self, index: usize) -> Whnf<'t> {
+        if index == self.input.len() {
+            return bit(true);
+        }
+        let head = thunk(State::Bits(index * 8, index * 8 + 8));
+        pair(head, thunk(State::Bytes(index + 1)))
+    }
+
+    /// If the list has items, return the first item and the rest of the list.
+    ///
+    /// We can't look at the list to tell if it's a pair or the end of the list:
+    /// the value may be not evaluated deep enough. Instead, we let the list
+    /// tell it to us. A pair calls the function with the head and the tail,
+    /// and the end of the list returns the second argument. So, `list (λh λt λd 1) 0`
+    /// is `1` for a pair and `0` for the end of the list, see `marker`.
+    ///
+    /// The head and the tail aren't evaluated, they are `list 0` and `list 1`.
+    fn uncons(&self, list: &Thunk<'t>) -> anyhow::Result<Option<(Thunk<'t>, Thunk<'t>)>> {
+        let is_pair = thunk(State::Done(Whnf::Lam(&IS_PAIR, None)));
+        let is_pair = self.call(list.clone(), vec![is_pair, thunk(State::Done(bit(false)))])?;
+        match self.marker(thunk(State::Done(is_pair)))? {
+            Some(1) => {}
+            Some(_) => return Ok(None),
+            None => anyhow::bail!("the output is not a list"),
+        }
+        let part = |is_one| {
+            let env = Frame {
+                value: thunk(State::Done(bit(is_one))),
+                next: None,
+            };
+            let env = Frame {
+                value: list.clone(),
+                next: Some(env.into()),
+            };
+            thunk(State::Delayed(&CALL, Some(env.into())))
+        };
+        Ok(Some((part(false), part(true))))
+    }
+
+    /// Evaluate the bit and return it as a number, `0` or `1`.
+    fn as_bit(&self, bit: Thunk<'t>) -> anyhow::Result<u8> {
+        self.marker(bit)?
+            .ok_or_else(|| anyhow::anyhow!("an item in the output is not a bit"))
+    }
+
+    /// Call the value with two markers, `0` and `1`, and see which one we get.
+    ///
+    /// A bit returns one of them, and anything else returns something else.
+    fn marker(&self, value: Thunk<'t>) -> anyhow::Result<Option<u8>> {
+        let markers = vec![
+            thunk(State::Done(Whnf::Marker(0))),
+            thunk(State::Done(Whnf::Marker(1))),
+        ];
+        match self.call(value, markers)? {
+            Whnf::Marker(bit) => Ok(Some(bit)),
+            Whnf::Lam(..) | Whnf::Stuck => Ok(None),
+        }
+    }
+}
+
+/// Find the value of the variable in the environment.
+fn lookup<'t>(env: &Env<'t>, index: usize) -> anyhow::Result<Thunk<'t>> {
+    let mut frame = env.as_ref();
+    for _ in 1..index {
+        frame = frame.and_then(|frame| frame.next.as_ref());
+    }
+    let frame =
+        frame.ok_or_else(|| anyhow::anyhow!("variable {index} doesn't point to any lambda"))?;
+    Ok(frame.value.clone())
+}
+
+/// Make the thunk for the argument of a call.
+///
+/// Variables and lambdas don't need to wait to be evaluated,
+/// and a variable is better shared than wrapped into another thunk.
+fn delay<'t>(arg: &'t Term, env: &Env<'t>) -> anyhow::Result<Thunk<'t>> {
+    Ok(match arg {
+        Term::Var(index) => lookup(env, *index)?,
+        Term::Lam(body) => thunk(State::Done(Whnf::Lam(body, env.clone()))),
+        arg => thunk(State::Delayed(arg, env.clone())),
+    })
+}
+
+fn thunk(state: State<'_>) -> Thunk<'_> {
+    Rc::new(RefCell::new(state))
+}
+
+/// `λ λ 2` for `0` and `λ λ 1` for `1`. The latter is also the end of a list.
+fn bit<'t>(is_one: bool) -> Whnf<'t> {
+    Whnf::Lam(if is_one { &SECOND } else { &FIRST }, None)
+}
+
+/// The pair `λ 1 head tail`.
+fn pair<'t>(head: Thunk<'t>, tail: Thunk<'t>) -> Whnf<'t> {
+    let tail = Frame {
+        value: tail,
+        next: None,
+    };
+    let head = Frame {
+        value: head,
+        next: Some(tail.into()),
+    };
+    Whnf::Lam(&PAIR, Some(head.into()))
+}
+
+// Bodies of the functions the machine makes on its own. They aren't
+// in the program, so they live here.
+
+/// The body of `λ λ 2`.
+static FIRST: LazyLock<Term> = LazyLock::new(|| Term::Lam(Term::Var(2).into()));
+/// The body of `λ λ 1`.
+static SECOND: LazyLock<Term> = LazyLock::new(|| Term::Lam(Term::Var(1).into()));
+/// The body of a pair, `1 2 3`, with the head and the tail in the environment.
+static PAIR: LazyLock<Term> = LazyLock::new(|| app(app(Term::Var(1), Term::Var(2)), Term::Var(3)));
+/// The body of `λh λt λd 1`, where `1` is `λ λ 1`.
+static IS_PAIR: LazyLock<Term> =
+    LazyLock::new(|| (0..4).fold(Term::Var(1), |body, _| Term::Lam(body.into())));
+/// `1 2`, to call a function with an argument, both in the environment.
+static CALL: LazyLock<Term> = LazyLock::new(|| app(Term::Var(1), Term::Var(2)));
+
+fn app(target: Term, arg: Term) -> Term {
+    Term::App(target.into(), arg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Value;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("λ 1", "0010")]
+    #[case("λ λ 2", "0000110")]
+    #[case("λ λ 1", "000010")]
+    #[case("λ λ λ 3 1 (2 1)", "00000001011110100111010")]
+    #[case("λ 1 1", "00011010")]
+    fn encode_decode(#[case] input: &str, #[case] bits: &str) {
+        let term = Term::parse(input).unwrap();
+        assert_eq!(term.to_blc().unwrap(), bits);
+        assert_eq!(Term::from_blc(bits.as_bytes()).unwrap(), term);
+        assert_eq!(Term::from_blc(&pack_bits(bits)).unwrap(), term);
+    }
+
+    // The numeral for the crab is 129408 calls deep.
+    #[test]
+    fn encode_big_numeral() {
+        let module = crate::parse("'🦀'").unwrap();
+        let crate::ast_nodes::Stmt::Expr { expr, .. } = &module.stmts[0] else {
+            panic!("bad statement")
+        };
+        let term = Value::from_expr(expr).to_debruijn();
+        let bits = term.to_blc().unwrap();
+        assert!(bits.starts_with("0000011100111001110"));
+        assert_eq!(bits.len(), 4 + 129_408 * 5 + 2);
+        assert_eq!(Term::from_blc(bits.as_bytes()).unwrap(), term);
+    }
+
+    #[test]
+    fn encode_free() {
+        let term = Term::parse("λ x 1").unwrap();
+        let err = term.to_blc().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot encode free variable `x`, the term must be closed"
+        );
+    }
+
+    #[rstest]
+    #[case("", "unexpected end of the program in the term at bit 0")]
+    #[case("00", "unexpected end of the program in the term at bit 2")]
+    #[case("0011", "unexpected end of the program in the term at bit 2")]
+    #[case("0000110 1", "unexpected bits after the end of the program at bit 7")]
+    #[case("110", "variable 2 at bit 0 doesn't point to any lambda")]
+    #[case("00 01 10 110", "variable 2 at bit 6 doesn't point to any lambda")]
+    fn decode_err(#[case] input: &str, #[case] exp: &str) {
+        let err = Term::from_blc(input.as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), exp);
+    }
+
+    #[test]
+    fn decode_whitespace() {
+        let term = Term::from_blc(b"00 00\n110\n").unwrap();
+        assert_eq!(term.to_string(), "λ λ 2");
+    }
+
+    #[rstest]
+    #[case("0010", &[0b0010_0000])]
+    #[case("00000001011110100111010", &[0b0000_0001, 0b0111_1010, 0b0111_0100])]
+    #[case("00100000", &[0b0010_0000])]
+    fn pack(#[case] bits: &str, #[case] exp: &[u8]) {
+        assert_eq!(pack_bits(bits), exp);
+    }
+
+    fn run(program: &str, input: &[u8], mode: IoMode) -> anyhow::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        run_blc(&Term::parse(program).unwrap(), input, mode, &mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn run_cat() {
+        assert_eq!(run("λ 1", b"hi!", IoMode::Bytes).unwrap(), b"hi!");
+        assert_eq!(run("λ 1", b"", IoMode::Bytes).unwrap(), b"");
+        assert_eq!(run("λ 1", b"A", IoMode::Bits).unwrap(), b"01000001");
+    }
+
+    #[test]
+    fn run_ignore_input() {
+        // Ignore the input and output the bits 1 and 0.
+        let program = "λ λ 1 (λ λ 1) (λ 1 (λ λ 2) (λ λ 1))";
+        assert_eq!(run(program, b"abc", IoMode::Bits).unwrap(), b"10");
+    }
+
+    #[test]
+    fn run_tail() {
+        // Drop the first byte of the input: `λl l false`.
+        assert_eq!(run("λ 1 (λ λ 1)", b"xyz", IoMode::Bytes).unwrap(), b"yz");
+    }
+
+    // Each step through the input takes the same time, no matter how long
+    // the input is. When it didn't, cat took half a minute on 400 bytes.
+    #[test]
+    fn run_long_cat() {
+        let input: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+        let start = std::time::Instant::now();
+        assert_eq!(run("λ 1", &input, IoMode::Bytes).unwrap(), input);
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn run_long_walk() {
+        // Walk to the end of the input, keeping all of it,
+        // and then output nothing, dropping it all at once:
+        // `λl walk l l empty`, where `walk = Y λr λm m (λh λt λd r t) m`.
+        let y = "(λ (λ 2 (1 1)) (λ 2 (1 1)))";
+        let program = format!("λ {y} (λ λ 1 (λ λ λ 5 2) 1) 1 1 (λ λ 1)");
+        let input = vec![b'x'; 50_000];
+        assert_eq!(run(&program, &input, IoMode::Bytes).unwrap(), b"");
+    }
+
+    #[rstest]
+    #[case("λ λ 1", IoMode::Bits, "the output is not a list")]
+    #[case(
+        "λ λ 1 (λ 1) (λ λ 1)",
+        IoMode::Bits,
+        "an item in the output is not a bit"
+    )]
+    #[case(
+        "λ λ 1 (λ λ 1) (λ λ 1)",
+        IoMode::Bytes,
+        "a byte in the output has less than 8 bits"
+    )]
+    fn run_err(#[case] program: &str, #[case] mode: IoMode, #[case] exp: &str) {
+        let err = run(program, b"", mode).unwrap_err();
+        assert_eq!(err.to_string(), exp);
+    }
+
+    #[test]
+    fn run_short_byte() {
+        // Output a single byte that has only one bit.
+        let program = "λ λ 1 (λ 1 (λ λ 2) (λ λ 1)) (λ λ 1)";
+        let err = run(program, b"", IoMode::Bytes).unwrap_err();
+        assert_eq!(err.to_string(), "a byte in the output has less than 8 bits");
+    }
+}
@@ -0,0 +1,538 @@
+//! Nameless representation of values using de Bruijn indices.
+//!
+//! In the usual notation, a variable refers to a lambda by its argument name.
+//! In [de Bruijn notation], lambdas have no argument names, and a variable
+//! is a number telling how many lambdas up (counting from 1) is the one
+//! that introduced it. For example, `λa λb a` is `λ λ 2`, and `λa λb b` is `λ λ 1`.
+//!
+//! The nice thing about it is that the same function always has the same
+//! representation, no matter how the arguments are called. `λa a` and `λx x`
+//! are both `λ 1`. So, terms can be compared just by comparing their structure.
+//! Also, many tools for lambda calculus speak de Bruijn notation,
+//! and that's how `rlci eval --format debruijn` can talk to them.
+//!
+//! [de Bruijn notation]: https://en.wikipedia.org/wiki/De_Bruijn_index
+use crate::ast_nodes::{Expr, Span};
+use crate::interpreter::Value;
+use pest::error::{Error, ErrorVariant};
+use pest::iterators::Pair;
+use pest::Parser;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// The parser for terms in de Bruijn notation, generated from the grammar file.
+///
+/// It has nothing to do with the language parser, it's a separate
+/// (and much smaller) grammar for reading the output of other tools.
+#[derive(Parser)]
+#[grammar = "interpreter/debruijn.pest"]
+struct DeBruijnParser;
+
+/// A lambda term in de Bruijn notation.
+///
+/// Unlike `Value`, it has no names for arguments and no spans,
+/// so two terms are equal if and only if they have the same structure.
+///
+/// Terms can be very deep: the numeral for `'🦀'` is 129408 calls nested
+/// into each other. So, everything that walks the whole term (comparing,
+/// hashing, printing, and dropping it) does that in a loop rather than
+/// recursively, to not overflow the stack.
+#[derive(Debug, Clone)]
+pub enum Term {
+    /// A variable bound by a lambda. The index is 1 for the innermost lambda,
+    /// 2 for the one around it, and so on.
+    Var(usize),
+    /// A variable not bound by any lambda, like an undefined name.
+    Free(String),
+    /// A lambda. Its argument is referred by indices in the body.
+    Lam(Box<Term>),
+    /// A function application.
+    App(Box<Term>, Box<Term>),
+}
+
+impl Term {
+    /// Read a term written in de Bruijn notation: `λ λ 2 (1 x)`.
+    ///
+    /// Both `λ` and `\` can be used for lambdas, and names that
+    /// aren't numbers are free variables. Every index must point
+    /// to one of the lambdas around it.
+    #[allow(clippy::result_large_err)]
+    pub fn parse(input: &str) -> Result<Term, Error<Rule>> {
+        let mut pairs = DeBruijnParser::parse(Rule::term, input)?;
+        // The `term` rule always has exactly one expression inside.
+        let expr = pairs.next().unwrap().into_inner().next().unwrap();
+        Term::from_pair(expr, 0)
+    }
+
+    /// Convert the parsed expression into a term.
+    ///
+    /// The depth is the number of lambdas around the expression,
+    /// so we know which indices point outside of the term.
+    #[allow(clippy::result_large_err)]
+    fn from_pair(pair: Pair<Rule>, depth: usize) -> Result<Term, Error<Rule>> {
+        match pair.as_rule() {
+            Rule::lambda => {
+                let body = pair.into_inner().next().unwrap();
+                Ok(Term::Lam(Term::from_pair(body, depth + 1)?.into()))
+            }
+            Rule::app => {
+                let mut items = pair.into_inner();
+                let mut term = Term::from_pair(items.next().unwrap(), depth)?;
+                for item in items {
+                    let arg = Term::from_pair(item, depth)?;
+                    term = Term::App(term.into(), arg.into());
+                }
+                Ok(term)
+            }
+            Rule::index => {
+                // Too big numbers can't point to any lambda either.
+                let index = pair.as_str().parse().unwrap_or(0);
+                if index == 0 || index > depth {
+                    let message = format!(
+                        "index {} doesn't point to any lambda, there are {depth} around it",
+                        pair.as_str()
+                    );
+                    let err = ErrorVariant::CustomError { message };
+                    return Err(Error::new_from_span(err, pair.as_span()));
+                }
+                Ok(Term::Var(index))
+            }
+            Rule::name => Ok(Term::Free(pair.as_str().to_string())),
+            _ => unreachable!("unexpected rule {:?}", pair.as_rule()),
+        }
+    }
+
+    /// All names of free variables in the term.
+    #[must_use]
+    pub fn free_names(&self) -> HashSet<&str> {
+        self.nodes()
+            .filter_map(|term| match term {
+                Term::Free(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// All nodes of the term, each node before its children,
+    /// and the target of a call before its argument.
+    ///
+    /// It's the order in which BLC writes them, see `to_blc`.
+    pub(super) fn nodes(&self) -> impl Iterator<Item = &Term> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let term = stack.pop()?;
+            match term {
+                Term::Lam(body) => stack.push(body),
+                Term::App(target, arg) => {
+                    stack.push(arg);
+                    stack.push(target);
+                }
+                Term::Var(_) | Term::Free(_) => {}
+            }
+            Some(term)
+        })
+    }
+
+    /// Convert the term into an AST node, giving names to all arguments.
+    ///
+    /// Arguments are named `a`, `b`, `c`, and so on, counting from the outermost
+    /// lambda. If there are more than 26 nested lambdas, the names get a number,
+    /// like `a1`. Names of free variables are never used for arguments,
+    /// so that the free variables don't get captured by a lambda.
+    ///
+    /// The nodes have no position in the source because there is no source.
+    /// All spans are empty.
+    #[must_use]
+    pub fn to_expr(&self) -> Expr {
+        let free = self.free_names();
+        self.to_expr_in(&mut Vec::new(), &free)
+    }
+
+    /// Convert the term into a value, the same as `to_expr` does.
+    ///
+    /// Free variables become unbound names, use `Value::bind_global`
+    /// to bind them.
+    #[must_use]
+    pub fn to_value(&self) -> Value {
+        Value::from_expr(&self.to_expr())
+    }
+
+    fn to_expr_in(&self, names: &mut Vec<String>, free: &HashSet<&str>) -> Expr {
+        let span = Span::default();
+        // Spelled out, a big numeral is too deep to convert recursively.
+        // As a numeral node, it doesn't have to be spelled out at all.
+        // Small ones are kept as they are, so that `λ λ 1` is still `λa λb b`.
+        if let Some(n) = self.as_numeral().filter(|n| *n > SMALL_NUMERAL) {
+            return Expr::Numeral { n, span };
+        }
+        match self {
+            Term::Var(index) => {
+                // If the index points outside of the term (it can happen only
+                // if the term was built by hand), it becomes an unbound name
+                // that can't be written in the source code.
+                let name = match names.len().checked_sub(*index) {
+                    Some(pos) => names[pos].clone(),
+                    None => format!("#{index}"),
+                };
+                Expr::Id { name, span }
+            }
+            Term::Free(name) => Expr::Id {
+                name: name.clone(),
+                span,
+            },
+            Term::Lam(body) => {
+                // There are only so many names taken, so we'll find a free one soon.
+                let mut arg = arg_name(0);
+                for n in 1.. {
+                    if !free.contains(arg.as_str()) && !names.contains(&arg) {
+                        break;
+                    }
+                    arg = arg_name(n);
+                }
+                names.push(arg.clone());
+                let expr = body.to_expr_in(names, free);
+                names.pop();
+                Expr::Def {
+                    arg,
+                    expr: expr.into(),
+                    span,
+                }
+            }
+            Term::App(target, arg) => Expr::Call {
+                target: target.to_expr_in(names, free).into(),
+                arg: arg.to_expr_in(names, free).into(),
+                span,
+            },
+        }
+    }
+}
+
+/// The biggest numeral `Term::to_expr` spells out, see there.
+const SMALL_NUMERAL: u64 = 1000;
+
+impl Term {
+    /// If the term is a Church numeral, `λ λ 2 (2 (2 1))`, return its number.
+    fn as_numeral(&self) -> Option<u64> {
+        let Term::Lam(body) = self else {
+            return None;
+        };
+        let Term::Lam(body) = body.as_ref() else {
+            return None;
+        };
+        let mut n = 0;
+        let mut body = body.as_ref();
+        while let Term::App(target, arg) = body {
+            if **target != Term::Var(2) {
+                return None;
+            }
+            n += 1;
+            body = arg;
+        }
+        (*body == Term::Var(1)).then_some(n)
+    }
+}
+
+/// The n'th name in the sequence of `a`, `b`, ..., `z`, `a1`, `b1`, ..., `z1`, `a2`, ...
+fn arg_name(n: usize) -> String {
+    // There are exactly 26 letters, so the cast never truncates.
+    #[allow(clippy::cast_possible_truncation)]
+    let letter = char::from(b'a' + (n % 26) as u8);
+    match n / 26 {
+        0 => letter.to_string(),
+        round => format!("{letter}{round}"),
+    }
+}
+
+impl PartialEq for Term {
+    fn eq(&self, other: &Term) -> bool {
+        let mut stack = vec![(self, other)];
+        while let Some(pair) = stack.pop() {
+            match pair {
+                (Term::Var(a), Term::Var(b)) if a == b => {}
+                (Term::Free(a), Term::Free(b)) if a == b => {}
+                (Term::Lam(a), Term::Lam(b)) => stack.push((a, b)),
+                (Term::App(target_a, arg_a), Term::App(target_b, arg_b)) => {
+                    stack.push((arg_a, arg_b));
+                    stack.push((target_a, target_b));
+                }
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+impl Eq for Term {}
+
+// Each node is hashed with its kind, and that's enough to tell
+// where its children end, the same as with BLC.
+impl Hash for Term {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for term in self.nodes() {
+            std::mem::discriminant(term).hash(state);
+            match term {
+                Term::Var(index) => index.hash(state),
+                Term::Free(name) => name.hash(state),
+                Term::Lam(_) | Term::App(..) => {}
+            }
+        }
+    }
+}
+
+impl Drop for Term {
+    fn drop(&mut self) {
+        // Children are taken out before the node is dropped,
+        // so dropping the node itself never goes deeper.
+        let mut stack = Vec::new();
+        take_children(self, &mut stack);
+        while let Some(mut term) = stack.pop() {
+            take_children(&mut term, &mut stack);
+        }
+    }
+}
+
+/// Move children of the term to the stack, leaving leaves in their place.
+fn take_children(term: &mut Term, stack: &mut Vec<Term>) {
+    let mut take = |child: &mut Term| {
+        if matches!(child, Term::Lam(_) | Term::App(..)) {
+            stack.push(std::mem::replace(child, Term::Var(0)));
+        }
+    };
+    match term {
+        Term::Lam(body) => take(body),
+        Term::App(target, arg) => {
+            take(target);
+            take(arg);
+        }
+        Term::Var(_) | Term::Free(_) => {}
+    }
+}
+
+/// A part of the output of `Display` for `Term` still to be written.
+enum Part<'t> {
+    Term(&'t Term),
+    Text(&'static str),
+}
+
+// The output is the same notation that `Term::parse` reads.
+// A lambda is wrapped into braces when it's called or passed as an argument,
+// and a call is wrapped when it's an argument. `Term::parse` would understand
+// a lambda as the last argument without braces, but other tools might not.
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Parts are written from the end of the stack,
+        // so they are pushed in the reverse order.
+        let mut stack = vec![Part::Term(self)];
+        while let Some(part) = stack.pop() {
+            let term = match part {
+                Part::Text(text) => {
+                    f.write_str(text)?;
+                    continue;
+                }
+                Part::Term(term) => term,
+            };
+            match term {
+                Term::Var(index) => write!(f, "{index}")?,
+                Term::Free(name) => f.write_str(name)?,
+                Term::Lam(body) => {
+                    f.write_str("λ ")?;
+                    stack.push(Part::Term(body));
+                }
+                Term::App(target, arg) => {
+                    match arg.as_ref() {
+                        Term::App(..) | Term::Lam(_) => {
+                            stack.extend([Part::Text(")"), Part::Term(arg), Part::Text(" (")]);
+                        }
+                        _ => stack.extend([Part::Term(arg), Part::Text(" ")]),
+                    }
+                    match target.as_ref() {
+                        Term::Lam(_) => {
+                            stack.extend([Part::Text(")"), Part::Term(target), Part::Text("(")]);
+                        }
+                        _ => stack.push(Part::Term(target)),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Value {
+    /// Convert the value into a term in de Bruijn notation.
+    ///
+    /// Globals are replaced by their values, so the term is self-contained
+    /// and can be understood without the stdlib. Local bound variables are
+    /// replaced by their values too, the same as `repr` does. Only names
+    /// that aren't bound to anything become free variables.
+    pub fn to_debruijn(&self) -> Term {
+        self.to_term(&mut Vec::new())
+    }
+
+    /// Convert the value into a term, knowing names of arguments of all lambdas around it.
+    ///
+    /// The last name in the list is the argument of the innermost lambda.
+    fn to_term(&self, names: &mut Vec<String>) -> Term {
+        match self {
+            Value::Def { arg, value, .. } => {
+                names.push(arg.clone());
+                let body = value.to_term(names);
+                names.pop();
+                Term::Lam(body.into())
+            }
+            // An argument name might be bound to a global with the same name,
+            // it will be rebound when the function is called. So, we look
+            // if it's an argument first. See `Value::bind_global`.
+            Value::Id { name, .. }
+            | Value::BoundId {
+                name, global: true, ..
+            } => {
+                if let Some(pos) = names.iter().rposition(|arg| arg == name) {
+                    return Term::Var(names.len() - pos);
+                }
+                match self {
+                    // A global value was defined outside of all these lambdas,
+                    // so none of their arguments are visible inside of it.
+                    Value::BoundId { value, .. } => value.to_term(&mut Vec::new()),
+                    _ => Term::Free(name.clone()),
+                }
+            }
+            // A local value comes from the outside of all these lambdas too,
+            // it's substituted when the function around it is called.
+            Value::BoundId { value, .. } => value.to_term(&mut Vec::new()),
+            Value::Call { target, arg, .. } => {
+                let target = target.to_term(names);
+                let arg = arg.to_term(names);
+                Term::App(target.into(), arg.into())
+            }
+            // `λ λ 2 (2 (2 1))`, built in a loop because the numeral might be big.
+            Value::Numeral { n, .. } => {
+                let mut body = Term::Var(1);
+                for _ in 0..*n {
+                    body = Term::App(Term::Var(2).into(), body.into());
+                }
+                Term::Lam(Term::Lam(body.into()).into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_nodes::Stmt;
+    use crate::parse;
+    use rstest::rstest;
+
+    fn value(input: &str) -> Value {
+        let module = parse(input).unwrap();
+        let Stmt::Expr { expr, .. } = &module.stmts[0] else {
+            panic!("bad statement")
+        };
+        Value::from_expr(expr)
+    }
+
+    #[rstest]
+    #[case(r"\x x", "λ 1")]
+    #[case(r"\a \b a", "λ λ 2")]
+    #[case(r"\a \b b", "λ λ 1")]
+    #[case(r"\a \a a", "λ λ 1")]
+    #[case(r"\f \x f (f x)", "λ λ 2 (2 1)")]
+    #[case(r"\a b c. a c (b c)", "λ λ λ 3 1 (2 1)")]
+    #[case(r"(\x x) (\y y)", "(λ 1) (λ 1)")]
+    #[case(r"\x x (\y y x)", "λ 1 (λ 1 2)")]
+    #[case(r"f x", "f x")]
+    #[case(r"\x f x", "λ f 1")]
+    fn to_debruijn(#[case] input: &str, #[case] exp: &str) {
+        assert_eq!(value(input).to_debruijn().to_string(), exp);
+    }
+
+    #[test]
+    fn to_debruijn_alpha() {
+        assert_eq!(
+            value(r"\a \b a b").to_debruijn(),
+            value(r"\x \y x y").to_debruijn()
+        );
+        assert_ne!(
+            value(r"\a \b a b").to_debruijn(),
+            value(r"\x \y y x").to_debruijn()
+        );
+    }
+
+    // The code point of the crab is 129408, and the term is that deep.
+    #[test]
+    fn to_debruijn_big_numeral() {
+        let term = value("'🦀'").to_debruijn();
+        let other = value("'🦀'").to_debruijn();
+        assert_eq!(term, other);
+        assert_ne!(term, value("'🦞'").to_debruijn());
+        let hash = |term: &Term| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            term.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(&term), hash(&other));
+        let text = term.to_string();
+        assert!(text.starts_with("λ λ 2 (2 (2 "));
+        assert!(text.ends_with(&format!("2 1{}", ")".repeat(129_407))));
+        assert_eq!(term.free_names().len(), 0);
+        assert!(matches!(term.to_value(), Value::Numeral { n: 129_408, .. }));
+    }
+
+    #[rstest]
+    #[case("λ 1", "λ 1")]
+    #[case(r"\ \ 2", "λ λ 2")]
+    #[case("λλ2", "λ λ 2")]
+    #[case("λ 1 λ 1 2", "λ 1 (λ 1 2)")]
+    #[case("(λ 1) (λ 1)", "(λ 1) (λ 1)")]
+    #[case("λ (1 1) 1", "λ 1 1 1")]
+    #[case("λ 1 (1 1)", "λ 1 (1 1)")]
+    #[case("λ nat.add 1 1 # double", "λ nat.add 1 1")]
+    #[case("\n  λ\n  1\n", "λ 1")]
+    fn parse_and_display(#[case] input: &str, #[case] exp: &str) {
+        assert_eq!(Term::parse(input).unwrap().to_string(), exp);
+    }
+
+    #[rstest]
+    #[case("1", "index 1 doesn't point to any lambda, there are 0 around it")]
+    #[case("λ λ 3", "index 3 doesn't point to any lambda, there are 2 around it")]
+    #[case("λ 0", "index 0 doesn't point to any lambda, there are 1 around it")]
+    #[case("λ 1 )", "expected EOI, lambda, index, or name")]
+    #[case("λ", "expected lambda or app")]
+    #[case("", "expected term")]
+    #[case("(λ 1", "expected lambda, index, or name")]
+    fn parse_err(#[case] input: &str, #[case] exp: &str) {
+        let err = Term::parse(input).unwrap_err();
+        assert!(err.to_string().contains(exp), "{err}");
+    }
+
+    #[rstest]
+    #[case("λ 1", "λa a")]
+    #[case("λ λ 2 1", "λa λb a b")]
+    #[case("λ (λ 1) 1", "λa (λb b) a")]
+    #[case("λ a 1", "λb a b")]
+    #[case("λ λ a b 1", "λc λd a b d")]
+    fn to_value(#[case] input: &str, #[case] exp: &str) {
+        let term = Term::parse(input).unwrap();
+        let val = term.to_value();
+        assert_eq!(val.repr(), exp);
+        assert_eq!(val.to_debruijn(), term);
+    }
+
+    #[test]
+    fn to_value_many_args() {
+        let input = format!("{}1", "λ".repeat(28));
+        let val = Term::parse(&input).unwrap().to_value();
+        assert!(val.repr().ends_with("λz λa1 λb1 b1"), "{}", val.repr());
+    }
+
+    #[rstest]
+    #[case(0, "a")]
+    #[case(25, "z")]
+    #[case(26, "a1")]
+    #[case(53, "b2")]
+    fn arg_names(#[case] n: usize, #[case] exp: &str) {
+        assert_eq!(arg_name(n), exp);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::interpreter::{Strategy, Value};
+
+/// How many steps to spend on evaluating a part of a value to recognize it.
+///
+/// Values are recognized to print them, and printing shouldn't hang.
+/// But a part of something that looks like a list might be an infinite loop
+/// or just take forever to evaluate. If it takes longer than that,
+/// the value isn't recognized and is printed as it is.
+const RECOGNIZE_STEPS: usize = 10_000;
+
+/// Recognize values that encode data structures from the stdlib.
+///
+/// In lambda calculus, there is no way to tell a boolean from a function
+/// accepting two arguments and returning the first one because they are the same thing.
+/// So, everything here is a guess based on the shape of the value.
+/// If a value looks like a list, we say it's a list.
+///
+/// These methods are used by `Value::repr_with` to show the values
+/// using syntactic sugar.
+impl Value {
+    /// If the value is a boolean, return its Rust equivalent.
+    ///
+    /// True is `λa λb a` and false is `λa λb b`.
+    pub(crate) fn as_bool(&self) -> Option<bool> {
+        // Zero is `λa λb b`, the same as false.
+        if let Value::Numeral { n: 0, .. } = self.unwrap_bound() {
+            return Some(false);
+        }
+        let Value::Def { arg: a, value, .. } = self.unwrap_bound() else {
+            return None;
+        };
+        let Value::Def { arg: b, value, .. } = value.unwrap_bound() else {
+            return None;
+        };
+        if a == b {
+            return None;
+        }
+        match value.local_name()? {
+            name if name == a => Some(true),
+            name if name == b => Some(false),
+            _ => None,
+        }
+    }
+
+    /// If the value is a list, return all its items.
+    ///
+    /// A list is a chain of pairs, the same as produced by the list literal
+    /// or by `prepend` from the stdlib. See `desugar::list` for details.
+    ///
+    /// Values inside of the value we get are not evaluated, so we evaluate them
+    /// as we go down the chain, see `eval_briefly`. If anything fails
+    /// to evaluate, it's not a list. The items of the list are returned as is,
+    /// without evaluation.
+    pub(crate) fn as_list(&self) -> Option<Vec<Value>> {
+        let mut items = Vec::new();
+        let mut node = self.unwrap_bound().clone();
+        loop {
+            let (is_end, rest) = node.as_pair()?;
+            if is_end.eval_briefly()?.as_bool()? {
+                return Some(items);
+            }
+            let (item, tail) = rest.eval_briefly()?.as_pair()?;
+            items.push(item);
+            node = tail.eval_briefly()?;
+        }
+    }
+
+    /// If the value is a Church numeral, return its Rust equivalent.
+    ///
+    /// A numeral is `λa λb a (a (a b))`, see `desugar::numeral`. The innermost
+    /// `b` may also be `m a b` where `m` is a numeral too. That's how the reducer
+    /// unfolds numerals (see `Value::Numeral`), and that's what `inc` makes:
+    /// `inc 2` evaluates into `λa λb a (2 a b)`, which is 3.
+    pub(crate) fn as_nat(&self) -> Option<u64> {
+        if let Value::Numeral { n, .. } = self.unwrap_bound() {
+            return Some(*n);
+        }
+        let Value::Def { arg: a, value, .. } = self.unwrap_bound() else {
+            return None;
+        };
+        let Value::Def { arg: b, value, .. } = value.unwrap_bound() else {
+            return None;
+        };
+        if a == b {
+            return None;
+        }
+        let mut n = 0;
+        let mut body = value.unwrap_bound();
+        while let Value::Call { target, arg, .. } = body {
+            if target.local_name() != Some(a) {
+                // It must be `m a b` then.
+                let Value::Call {
+                    target: m,
+                    arg: m_a,
+                    ..
+                } = target.unwrap_bound()
+                else {
+                    return None;
+                };
+                if m_a.local_name()? != a || arg.local_name()? != b {
+                    return None;
+                }
+                return Some(n + m.as_nat()?);
+            }
+            n += 1;
+            body = arg.unwrap_bound();
+        }
+        if body.local_name()? != b {
+            return None;
+        }
+        Some(n)
+    }
+
+    /// If the value is a string, return its Rust equivalent.
+    ///
+    /// A string is a list of code points, the same as produced by the string literal.
+    /// Since any list of small enough numbers is a string, we take a stricter
+    /// definition to avoid false positives: the list must be non-empty
+    /// and all characters must be printable, except a few whitespace
+    /// characters which are commonly used in text. So, `[1, 2]` is a list,
+    /// `[104, 105]` is `"hi"`, and `[]` is just an empty list.
+    pub(crate) fn as_string(&self) -> Option<String> {
+        let items = self.as_list()?;
+        if items.is_empty() {
+            return None;
+        }
+        let mut result = String::new();
+        for item in items {
+            // Items of the list aren't evaluated, see `as_list`.
+            let code = match item.as_nat() {
+                Some(code) => code,
+                None => item.eval_briefly()?.as_nat()?,
+            };
+            let ch = char::from_u32(code.try_into().ok()?)?;
+            if ch.is_control() && !matches!(ch, '\n' | '\t' | '\r') {
+                return None;
+            }
+            result.push(ch);
+        }
+        Some(result)
+    }
+
+    /// Evaluate the value the same way as `eval` does, but give up
+    /// after `RECOGNIZE_STEPS` steps.
+    ///
+    /// It uses the reducer rather than `eval` because the reducer can stop.
+    /// `eval` can only be stopped by a stack overflow, and that's not something
+    /// we can recover from.
+    pub(crate) fn eval_briefly(&self) -> Option<Value> {
+        self.reduce(Strategy::CallByName, false, RECOGNIZE_STEPS)
+            .ok()
+    }
+
+    /// If the value is a pair, return both items of the pair.
+    ///
+    /// A pair is `λc c a b` where `a` and `b` are the items.
+    fn as_pair(&self) -> Option<(Value, Value)> {
+        let Value::Def { arg, value, .. } = self.unwrap_bound() else {
+            return None;
+        };
+        let Value::Call { target, arg: b, .. } = value.unwrap_bound() else {
+            return None;
+        };
+        let Value::Call { target, arg: a, .. } = target.unwrap_bound() else {
+            return None;
+        };
+        if target.local_name()? != arg {
+            return None;
+        }
+        Some((a.as_ref().clone(), b.as_ref().clone()))
+    }
+
+    /// Get the value that is bound to the name if the value is a bound name.
+    ///
+    /// Bound names don't change the meaning of the value, so we look right through them.
+    pub(crate) fn unwrap_bound(&self) -> &Value {
+        match self {
+            Value::BoundId { value, .. } => value.unwrap_bound(),
+            _ => self,
+        }
+    }
+
+    /// If the value is a name of a local variable, return the name.
+    ///
+    /// The name of an argument is an unbound `Id` when we look inside
+    /// of the function definition. However, if there is a global with the same name,
+    /// it might be bound to that global. It will be rebound to the argument
+    /// value when the function is called, so we treat it as a local name too.
+    fn local_name(&self) -> Option<&str> {
+        match self {
+            Value::Id { name, .. }
+            | Value::BoundId {
+                name, global: true, ..
+            } => Some(name),
+            _ => None,
+        }
+    }
+}
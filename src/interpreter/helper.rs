@@ -1,5 +1,7 @@
+use crate::parser::is_incomplete;
 use colored::Colorize;
 use rustyline::hint::Hint;
+use rustyline::validate::{ValidationContext, ValidationResult};
 use rustyline::Context;
 use std::borrow::Cow;
 use std::cell::RefCell;
@@ -36,9 +38,22 @@ impl Helper {
 //
 // The official rustyline example uses `derive` with these traits
 // but somehow it doesn't work for me.
-impl rustyline::validate::Validator for Helper {}
 impl rustyline::Helper for Helper {}
 
+// The validator decides what happens when the user presses Enter.
+// If the input is incomplete, rustyline inserts a newline and keeps reading,
+// so the user can type a multiline expression. Otherwise, the input
+// is submitted to the REPL.
+impl rustyline::validate::Validator for Helper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        if is_incomplete(ctx.input()) {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
 // Provides a very basic syntax highlighting for all user input in the REPL.
 impl rustyline::highlight::Highlighter for Helper {
     fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
@@ -57,15 +72,47 @@ impl rustyline::highlight::Highlighter for Helper {
         // For a real production-quality language, you should instead
         // actually try to `parse` the input, iterate over the resulting tokens,
         // and highlight them in the input text based on their positions.
-        // But for now, let's keep it simple.
-        let line = line.replace('λ', &lambda);
-        let line = line.replace('\\', &lambda);
-        let line = line.replace('=', &equal);
-        let line = line.replace('(', &left_br);
-        let line = line.replace(')', &right_br);
-        let line = line.replace('#', &hash);
-
-        Cow::Owned(line)
+        // But for now, let's keep it simple and only skip what the tokens
+        // can't be a part of: string and character literals, and comments.
+        let mut out = String::with_capacity(line.len());
+        let mut chars = line.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match ch {
+                'λ' => out.push_str(&lambda),
+                // A backslash is a lambda only if the argument name goes right after it.
+                // Before a newline, it's a line continuation.
+                '\\' if chars.peek().is_some_and(|next| !next.is_whitespace()) => {
+                    out.push_str(&lambda);
+                }
+                '=' => out.push_str(&equal),
+                '(' => out.push_str(&left_br),
+                ')' => out.push_str(&right_br),
+                '#' => {
+                    out.push_str(&hash);
+                    while let Some(next) = chars.next_if(|next| *next != '\n') {
+                        out.push(next);
+                    }
+                }
+                // Literals can't span multiple lines, and a backslash in them
+                // escapes the next char, which might be the quote.
+                '"' | '\'' => {
+                    out.push(ch);
+                    let mut escaped = false;
+                    while let Some(next) = chars.next_if(|next| *next != '\n') {
+                        out.push(next);
+                        match next {
+                            _ if escaped => escaped = false,
+                            '\\' => escaped = true,
+                            _ if next == ch => break,
+                            _ => {}
+                        }
+                    }
+                }
+                _ => out.push(ch),
+            }
+        }
+
+        Cow::Owned(out)
     }
 
     // We make this method to always return true,
@@ -85,20 +132,37 @@ impl rustyline::completion::Completer for Helper {
         pos: usize,
         _ctx: &Context<'_>,
     ) -> rustyline::Result<(usize, Vec<Self::Candidate>)> {
-        let mut res: Vec<CommandHint> = Vec::new();
         // Autocomplete only if the cursor is at the very end of the input string.
+        // The position is in bytes, not in characters, the same as `len`.
         if line.is_empty() || pos < line.len() {
-            return Ok((pos, res));
+            return Ok((pos, Vec::new()));
         }
+        Ok((pos, self.completions(line)))
+    }
+}
 
-        // Take the last word and try to find all known names starting with it.
-        let (_, word) = line.rsplit_once(' ').unwrap_or(("", line));
-        for hint in self.hints.borrow().iter() {
-            if hint.display().starts_with(word) {
-                res.push(hint.suffix(word.len()));
-            }
+impl Helper {
+    /// Find all known names that start with the last name in the line.
+    ///
+    /// Each candidate is the rest of the name that needs to be added to the line.
+    fn completions(&self, line: &str) -> Vec<CommandHint> {
+        // The name is everything after the last space, brace, or lambda.
+        // Names can have multi-byte characters, like `α`, so we look
+        // for the start of the name by characters and not by bytes.
+        let start = line
+            .char_indices()
+            .rev()
+            .find(|(_, ch)| ch.is_whitespace() || "()[],\"'\\λ".contains(*ch))
+            .map_or(0, |(pos, ch)| pos + ch.len_utf8());
+        let word = &line[start..];
+        if word.is_empty() {
+            return Vec::new();
         }
-        Ok((pos, res))
+        self.hints
+            .borrow()
+            .iter()
+            .filter_map(|hint| hint.suffix(word))
+            .collect()
     }
 }
 
@@ -113,8 +177,10 @@ impl rustyline::hint::Hinter for Helper {
 pub struct CommandHint(String);
 
 impl CommandHint {
-    fn suffix(&self, strip_chars: usize) -> CommandHint {
-        CommandHint(self.0[strip_chars..].to_string())
+    /// If the hint starts with the prefix, make a hint with the rest of it.
+    fn suffix(&self, prefix: &str) -> Option<CommandHint> {
+        let rest = self.0.strip_prefix(prefix)?;
+        Some(CommandHint(rest.to_string()))
     }
 }
 
@@ -137,3 +203,51 @@ impl rustyline::completion::Candidate for CommandHint {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use rustyline::highlight::Highlighter;
+
+    #[rstest]
+    #[case("", &[])]
+    #[case("a", &["nd"])]
+    #[case("f (a", &["nd"])]
+    #[case("λx α", &["", "β"])]
+    #[case("Ω", &["mega"])]
+    #[case("nat.a", &["dd"])]
+    #[case("λ", &[])]
+    #[case("ñ", &["andú"])]
+    fn completions(#[case] line: &str, #[case] exp: &[&str]) {
+        let helper = Helper::new();
+        for name in ["and", "α", "αβ", "Ωmega", "nat.add", "ñandú"] {
+            helper.add(name);
+        }
+        let hints: Vec<_> = helper.completions(line).into_iter().map(|h| h.0).collect();
+        assert_eq!(hints, exp);
+    }
+
+    #[rstest]
+    #[case("\\x x", "λx x")]
+    #[case("λx x", "λx x")]
+    #[case("id = \\x x", "id = λx x")]
+    #[case("f (\\x x)", "f (λx x)")]
+    #[case("f \"a\\nb\"", "f \"a\\nb\"")]
+    #[case("f '\\'' \\x x", "f '\\'' λx x")]
+    #[case("f \"(=)\" (a)", "f \"(=)\" (a)")]
+    #[case("f \"λ\"", "f \"λ\"")]
+    #[case("f \\\n  a", "f \\\n  a")]
+    #[case("a # (b) \\x\n\\y y", "a # (b) \\x\nλy y")]
+    fn highlight(#[case] line: &str, #[case] exp: &str) {
+        let output = Helper::new().highlight(line, 0);
+        // Put the colored tokens back to see where they were.
+        let output = output
+            .replace(&"λ".blue().to_string(), "λ")
+            .replace(&"=".blue().to_string(), "=")
+            .replace(&"(".magenta().to_string(), "(")
+            .replace(&")".magenta().to_string(), ")")
+            .replace(&"#".cyan().to_string(), "#");
+        assert_eq!(output, exp);
+    }
+}
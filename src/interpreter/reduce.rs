@@ -0,0 +1,708 @@
+//! Small-step reduction of values with a choice of strategy.
+//!
+//! `Value::eval` is a recursive evaluator with one hard-coded strategy:
+//! call-by-name, up to the first lambda. It's fast, but it's hard to see
+//! what it does, and it can't do anything else. The reducer here does one
+//! reduction at a time on the whole term, so the same term can be reduced
+//! with different strategies and each step can be looked at.
+//!
+//! The term is converted into de Bruijn notation (see `Term`) for reduction,
+//! so substitution never captures names. But unlike `Value::to_debruijn`,
+//! globals are kept as names and replaced by their values only when
+//! the strategy needs it. That's called δ-reduction (delta), and it's
+//! counted as a step too. So, `not true` stays readable until `not` is called.
+//!
+//! Strategies differ in which redex (a call of a lambda) they reduce first
+//! and when they stop:
+//!
+//! | Strategy          | Arguments            | Under lambdas | Stops at              |
+//! |-------------------|----------------------|---------------|-----------------------|
+//! | normal order      | as they are          | yes           | normal form           |
+//! | applicative order | reduced first        | yes           | normal form           |
+//! | call-by-name      | as they are          | no            | the first lambda      |
+//! | call-by-value     | reduced first        | no            | the first lambda      |
+//! | call-by-need      | shared, reduced once | no            | the first lambda      |
+//!
+//! For example, `K I (Y K)` passes a term without a normal form as an argument
+//! that is never used. Normal order, call-by-name, and call-by-need
+//! throw it away and stop. Applicative order and call-by-value try to reduce it
+//! first and never stop, so the reducer gives up after the maximum number of steps.
+//!
+//! Normal and applicative order can also do η-reduction (eta): replace
+//! `λx f x` with just `f` if `f` doesn't use `x`. Both do the same when called,
+//! so it doesn't change what the term means, but the normal form gets shorter.
+//! For example, `λx λy add x y` is the same as `add`. Normal order does η-reduction
+//! as soon as it sees it (outermost first), and applicative order does it
+//! after the body is reduced (innermost first).
+use crate::ast_nodes::Span;
+use crate::desugar;
+use crate::interpreter::Value;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// The order in which the reducer reduces calls, see the module docs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strategy {
+    /// Leftmost outermost redex first, including under lambdas.
+    /// Finds the normal form if there is one.
+    NormalOrder,
+    /// Leftmost innermost redex first, including under lambdas.
+    /// Arguments are reduced to the normal form before the call.
+    ApplicativeOrder,
+    /// Leftmost outermost redex first, but never under lambdas.
+    /// That's what `Value::eval` does.
+    #[default]
+    CallByName,
+    /// Arguments are reduced before the call, but never under lambdas.
+    CallByValue,
+    /// The same as call-by-name, but each argument is reduced at most once,
+    /// and all places it's passed to share the result.
+    CallByNeed,
+}
+
+impl Strategy {
+    /// All strategies, in the same order as in the docs.
+    pub const ALL: [Strategy; 5] = [
+        Strategy::NormalOrder,
+        Strategy::ApplicativeOrder,
+        Strategy::CallByName,
+        Strategy::CallByValue,
+        Strategy::CallByNeed,
+    ];
+
+    /// The name of the strategy, like `call-by-name`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::NormalOrder => "normal-order",
+            Strategy::ApplicativeOrder => "applicative-order",
+            Strategy::CallByName => "call-by-name",
+            Strategy::CallByValue => "call-by-value",
+            Strategy::CallByNeed => "call-by-need",
+        }
+    }
+
+    /// Find the strategy by its name, see `name`.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Strategy> {
+        Strategy::ALL.into_iter().find(|s| s.name() == name)
+    }
+}
+
+/// How many steps the reducer makes before giving up, unless told otherwise.
+pub const MAX_STEPS: usize = 100_000;
+
+impl Value {
+    /// Reduce the value with the given strategy.
+    ///
+    /// If `eta` is true, strategies that reduce under lambdas also do η-reduction,
+    /// see the module docs. Other strategies ignore it.
+    ///
+    /// The value should have globals bound, see `bind_global`. If the reduction
+    /// doesn't stop after `max_steps` steps, it's an error. Most probably,
+    /// the term doesn't have a normal form (or a lambda to stop at)
+    /// with this strategy.
+    pub fn reduce(&self, strategy: Strategy, eta: bool, max_steps: usize) -> anyhow::Result<Value> {
+        let mut reducer = Reducer::new(strategy, eta);
+        let mut node = to_node(self, &mut Vec::new(), &None);
+        for _ in 0..max_steps {
+            if !reducer.step(&mut node)? {
+                return Ok(to_value(&node, &reducer.heap, &mut Vec::new()));
+            }
+        }
+        Err(gave_up(strategy, max_steps))
+    }
+}
+
+/// The error for when the reduction doesn't stop after the maximum number of steps.
+pub(super) fn gave_up(strategy: Strategy, max_steps: usize) -> anyhow::Error {
+    anyhow::anyhow!(
+        "gave up after {max_steps} steps, the term might not terminate with {}",
+        strategy.name()
+    )
+}
+
+/// The global the node comes from, see `Node::App`.
+pub(super) type Origin = Option<Rc<str>>;
+
+/// A term being reduced.
+///
+/// It's the same as `Term` but knows about globals and sharing.
+#[derive(Debug, Clone)]
+pub(super) enum Node {
+    /// A de Bruijn index, 1 is the argument of the innermost lambda.
+    Var(usize),
+    /// A name that isn't bound to anything.
+    Free(String),
+    /// A global name with its value, replaced by the value when needed.
+    /// The origin is the same as for `App`.
+    Global(String, Box<Value>, Origin),
+    /// A Church numeral, unfolded one call at a time when needed, see `numeral_node`.
+    /// The origin is the same as for `App`.
+    ///
+    /// It's not a global named by the number because a global with that name
+    /// may be defined to be something else, like `1 = K`.
+    Numeral(u64, Origin),
+    /// An argument shared by all places it's passed to, the index in the heap.
+    /// Only call-by-need makes them.
+    Shared(usize),
+    /// A lambda with the name of its argument, to print it with.
+    Lam(String, Box<Node>),
+    /// A call, with the name of the global it comes from.
+    ///
+    /// It's `None` for calls in the term we started with, and the name
+    /// of the global for calls that came with its value. The reducer doesn't
+    /// care, but the tracer uses it to hide steps inside of globals.
+    App(Box<Node>, Box<Node>, Origin),
+}
+
+/// Convert the value into a node, knowing names of arguments of all lambdas around it.
+///
+/// It's the same as `Value::to_debruijn` except that globals aren't expanded.
+/// All calls in the value are marked as coming from the `origin`.
+pub(super) fn to_node(val: &Value, names: &mut Vec<String>, origin: &Origin) -> Node {
+    match val {
+        Value::Def { arg, value, .. } => {
+            names.push(arg.clone());
+            let body = to_node(value, names, origin);
+            names.pop();
+            Node::Lam(arg.clone(), body.into())
+        }
+        // An argument might be bound to a global with the same name,
+        // see `Value::bind_global`.
+        Value::Id { name, .. }
+        | Value::BoundId {
+            name, global: true, ..
+        } => {
+            if let Some(pos) = names.iter().rposition(|arg| arg == name) {
+                return Node::Var(names.len() - pos);
+            }
+            match val {
+                // A number written in the source is bound to its numeral.
+                Value::BoundId { value, .. } => match **value {
+                    Value::Numeral { n, .. } if desugar::as_numeral(name) == Some(n) => {
+                        Node::Numeral(n, origin.clone())
+                    }
+                    _ => Node::Global(name.clone(), value.clone(), origin.clone()),
+                },
+                _ => Node::Free(name.clone()),
+            }
+        }
+        // A local value comes from the outside of all lambdas around it.
+        Value::BoundId { value, .. } => to_node(value, &mut Vec::new(), origin),
+        Value::Call { target, arg, .. } => Node::App(
+            to_node(target, names, origin).into(),
+            to_node(arg, names, origin).into(),
+            origin.clone(),
+        ),
+        // A numeral is kept as a number, so it's unfolded
+        // only when the strategy gets to it.
+        Value::Numeral { n, .. } => Node::Numeral(*n, origin.clone()),
+    }
+}
+
+/// Unfold the Church numeral one call at a time, the same as `Value::call` does.
+///
+/// `n` is `λa λb a (n-1 a b)`, where `n-1` is kept as a number,
+/// so it's unfolded further only if the strategy gets to it.
+/// Strategies that stop at the first lambda never do, and so big numerals
+/// are as cheap for them as small ones.
+fn numeral_node(n: u64, origin: &Origin) -> Node {
+    let body = if n == 0 {
+        Node::Var(1)
+    } else {
+        let rest = Node::Numeral(n - 1, origin.clone());
+        let rest = Node::App(rest.into(), Node::Var(2).into(), origin.clone());
+        let rest = Node::App(rest.into(), Node::Var(1).into(), origin.clone());
+        Node::App(Node::Var(2).into(), rest.into(), origin.clone())
+    };
+    let body = Node::Lam("b".to_string(), body.into());
+    Node::Lam("a".to_string(), body.into())
+}
+
+/// Replace the variable pointing to the lambda `depth` levels up with the value.
+///
+/// Variables pointing further are decremented because the lambda is gone,
+/// and variables in the value are shifted because it's now under `depth - 1`
+/// more lambdas than it was.
+fn subst(node: Node, val: &Node, depth: usize) -> Node {
+    match node {
+        Node::Var(i) if i == depth => shift(val.clone(), depth - 1, 0),
+        Node::Var(i) if i > depth => Node::Var(i - 1),
+        Node::Lam(name, body) => Node::Lam(name, subst(*body, val, depth + 1).into()),
+        Node::App(target, arg, origin) => Node::App(
+            subst(*target, val, depth).into(),
+            subst(*arg, val, depth).into(),
+            origin,
+        ),
+        node => node,
+    }
+}
+
+/// Increment all variables in the node pointing outside of it by `by`.
+///
+/// `cutoff` is the number of lambdas inside of the node we're in,
+/// variables up to it point to them.
+fn shift(node: Node, by: usize, cutoff: usize) -> Node {
+    if by == 0 {
+        return node;
+    }
+    match node {
+        Node::Var(i) if i > cutoff => Node::Var(i + by),
+        Node::Lam(name, body) => Node::Lam(name, shift(*body, by, cutoff + 1).into()),
+        Node::App(target, arg, origin) => Node::App(
+            shift(*target, by, cutoff).into(),
+            shift(*arg, by, cutoff).into(),
+            origin,
+        ),
+        node => node,
+    }
+}
+
+/// How deep in the term the reducer can look for a redex.
+const MAX_DEPTH: usize = 10_000;
+
+/// Check if the node is `λx f x` where `f` doesn't use `x`.
+fn is_eta_redex(node: &Node) -> bool {
+    let Node::Lam(_, body) = node else {
+        return false;
+    };
+    let Node::App(target, arg, _) = body.as_ref() else {
+        return false;
+    };
+    matches!(**arg, Node::Var(1)) && !uses_var(target, 1)
+}
+
+/// Check if the node uses the variable pointing `idx` lambdas up.
+fn uses_var(node: &Node, idx: usize) -> bool {
+    match node {
+        Node::Var(i) => *i == idx,
+        Node::Lam(_, body) => uses_var(body, idx + 1),
+        Node::App(target, arg, _) => uses_var(target, idx) || uses_var(arg, idx),
+        // Globals, numerals, and shared arguments are closed terms.
+        Node::Free(_) | Node::Global(..) | Node::Numeral(..) | Node::Shared(_) => false,
+    }
+}
+
+/// Replace `λx f x` with `f`, see `is_eta_redex`.
+fn eta(node: &mut Node) -> bool {
+    let Node::Lam(_, body) = std::mem::replace(node, Node::Var(0)) else {
+        unreachable!("eta-reduction of not a lambda");
+    };
+    let Node::App(target, ..) = *body else {
+        unreachable!("eta-reduction of not a call");
+    };
+    // `f` is now outside of the lambda, so all variables pointing further
+    // than it are decremented. That's what substitution of the variable
+    // `f` doesn't use does.
+    *node = subst(*target, &Node::Var(0), 1);
+    true
+}
+
+/// Replace the global name with its value, or unfold the numeral.
+///
+/// Calls of the unfolded numeral come from the number, so that the tracer
+/// can hide them the same way as steps inside of globals.
+fn delta(node: &mut Node) -> bool {
+    *node = match node {
+        Node::Global(name, val, _) => {
+            let origin = Some(name.as_str().into());
+            match val.as_ref() {
+                Value::Numeral { n, .. } => numeral_node(*n, &origin),
+                val => to_node(val, &mut Vec::new(), &origin),
+            }
+        }
+        Node::Numeral(n, _) => numeral_node(*n, &Some(n.to_string().into())),
+        _ => unreachable!("delta-reduction of not a global"),
+    };
+    true
+}
+
+/// Where to go from a node to get to one of its children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Dir {
+    /// The body of a lambda.
+    Body,
+    /// The target of a call.
+    Target,
+    /// The argument of a call.
+    Arg,
+    /// The shared argument in the heap the node points to.
+    Shared,
+}
+
+/// A function looking for a redex in the node and reducing it.
+type StepFn = fn(&mut Reducer, &mut Node) -> anyhow::Result<bool>;
+
+pub(super) struct Reducer {
+    strategy: Strategy,
+    /// Do η-reduction, only for the strategies that reduce under lambdas.
+    eta: bool,
+    /// Shared arguments, for call-by-need.
+    ///
+    /// A call-by-need argument is stored here only once, and the body of
+    /// the lambda gets `Node::Shared` pointing to it. When one of them is reduced,
+    /// it's reduced in the heap, and so all others see the result.
+    /// All shared arguments are closed terms because call-by-need never
+    /// reduces under lambdas, so they don't need shifting.
+    pub(super) heap: Vec<Node>,
+    /// The path from the root of the term to the last reduced redex.
+    pub(super) path: Vec<Dir>,
+}
+
+impl Reducer {
+    pub(super) fn new(strategy: Strategy, eta: bool) -> Self {
+        Self {
+            strategy,
+            eta,
+            heap: Vec::new(),
+            path: Vec::new(),
+        }
+    }
+
+    /// Do one reduction step.
+    ///
+    /// Returns `false` if there is nothing to reduce with the strategy.
+    /// Otherwise, `path` points to where the redex was.
+    pub(super) fn step(&mut self, node: &mut Node) -> anyhow::Result<bool> {
+        self.path.clear();
+        match self.strategy {
+            Strategy::NormalOrder => self.step_normal(node),
+            Strategy::ApplicativeOrder => self.step_applicative(node),
+            Strategy::CallByName | Strategy::CallByNeed => self.step_lazy(node),
+            Strategy::CallByValue => self.step_value(node),
+        }
+    }
+
+    /// Look for a redex in the child of the current node and reduce it.
+    ///
+    /// The direction is added to `path` and stays there only if a redex is found.
+    ///
+    /// Some terms grow on each step, like `Y K` with call-by-value
+    /// becoming `K (K (K ...))`, and they would overflow the stack long before
+    /// running out of steps. So we give up when the redex is too deep.
+    fn step_into(&mut self, dir: Dir, child: &mut Node, step: StepFn) -> anyhow::Result<bool> {
+        if self.path.len() >= MAX_DEPTH {
+            anyhow::bail!(
+                "gave up on a term nested deeper than {MAX_DEPTH} levels, it might not terminate with {}",
+                self.strategy.name()
+            );
+        }
+        self.path.push(dir);
+        let reduced = step(self, child)?;
+        if !reduced {
+            self.path.pop();
+        }
+        Ok(reduced)
+    }
+
+    fn step_normal(&mut self, node: &mut Node) -> anyhow::Result<bool> {
+        match node {
+            Node::Global(..) | Node::Numeral(..) => Ok(delta(node)),
+            Node::Lam(..) if self.eta && is_eta_redex(node) => Ok(eta(node)),
+            Node::Lam(_, body) => self.step_into(Dir::Body, body, Self::step_normal),
+            Node::App(target, arg, _) => {
+                if matches!(**target, Node::Lam(..)) {
+                    return Ok(self.beta(node));
+                }
+                Ok(self.step_into(Dir::Target, target, Self::step_normal)?
+                    || self.step_into(Dir::Arg, arg, Self::step_normal)?)
+            }
+            Node::Var(_) | Node::Free(_) | Node::Shared(_) => Ok(false),
+        }
+    }
+
+    fn step_applicative(&mut self, node: &mut Node) -> anyhow::Result<bool> {
+        match node {
+            Node::Global(..) | Node::Numeral(..) => Ok(delta(node)),
+            Node::Lam(_, body) => {
+                if self.step_into(Dir::Body, body, Self::step_applicative)? {
+                    return Ok(true);
+                }
+                Ok(self.eta && is_eta_redex(node) && eta(node))
+            }
+            Node::App(target, arg, _) => {
+                if self.step_into(Dir::Target, target, Self::step_applicative)?
+                    || self.step_into(Dir::Arg, arg, Self::step_applicative)?
+                {
+                    return Ok(true);
+                }
+                Ok(matches!(**target, Node::Lam(..)) && self.beta(node))
+            }
+            Node::Var(_) | Node::Free(_) | Node::Shared(_) => Ok(false),
+        }
+    }
+
+    /// Call-by-name and call-by-need: reduce the target until it's a lambda,
+    /// and then call it.
+    fn step_lazy(&mut self, node: &mut Node) -> anyhow::Result<bool> {
+        match node {
+            Node::Global(..) | Node::Numeral(..) => Ok(delta(node)),
+            Node::Free(name) => anyhow::bail!("unbound variable `{name}`"),
+            Node::Shared(idx) => {
+                // Reduce the shared argument in the heap. When there is nothing
+                // left to reduce, it's replaced by a copy of the result,
+                // which isn't a step because nothing changes.
+                let idx = *idx;
+                let mut shared = std::mem::replace(&mut self.heap[idx], Node::Var(0));
+                let reduced = self.step_into(Dir::Shared, &mut shared, Self::step_lazy);
+                self.heap[idx] = shared;
+                if reduced? {
+                    return Ok(true);
+                }
+                *node = self.heap[idx].clone();
+                self.step_lazy(node)
+            }
+            Node::App(target, ..) => {
+                if self.step_into(Dir::Target, target, Self::step_lazy)? {
+                    return Ok(true);
+                }
+                Ok(matches!(**target, Node::Lam(..)) && self.beta(node))
+            }
+            Node::Lam(..) | Node::Var(_) => Ok(false),
+        }
+    }
+
+    fn step_value(&mut self, node: &mut Node) -> anyhow::Result<bool> {
+        match node {
+            Node::Global(..) | Node::Numeral(..) => Ok(delta(node)),
+            Node::Free(name) => anyhow::bail!("unbound variable `{name}`"),
+            Node::App(target, arg, _) => {
+                if self.step_into(Dir::Target, target, Self::step_value)? {
+                    return Ok(true);
+                }
+                // A global name is already a value, there is no need
+                // to replace it by its value before passing it.
+                if !matches!(**arg, Node::Global(..) | Node::Numeral(..))
+                    && self.step_into(Dir::Arg, arg, Self::step_value)?
+                {
+                    return Ok(true);
+                }
+                Ok(matches!(**target, Node::Lam(..)) && self.beta(node))
+            }
+            Node::Lam(..) | Node::Var(_) | Node::Shared(_) => Ok(false),
+        }
+    }
+
+    /// Call the lambda: replace `(λx body) arg` with the body
+    /// where `x` is replaced by `arg`.
+    fn beta(&mut self, node: &mut Node) -> bool {
+        let Node::App(target, arg, _) = std::mem::replace(node, Node::Var(0)) else {
+            unreachable!("beta-reduction of not a call");
+        };
+        let Node::Lam(_, body) = *target else {
+            unreachable!("beta-reduction of not a lambda");
+        };
+        let arg = match *arg {
+            // Lambdas and names are already as reduced as they can be,
+            // so there is nothing to share.
+            arg @ (Node::Lam(..)
+            | Node::Global(..)
+            | Node::Numeral(..)
+            | Node::Shared(_)
+            | Node::Var(_)) => arg,
+            arg if self.strategy == Strategy::CallByNeed => {
+                self.heap.push(arg);
+                Node::Shared(self.heap.len() - 1)
+            }
+            arg => arg,
+        };
+        *node = subst(*body, &arg, 1);
+        true
+    }
+}
+
+/// Convert the node back into a value.
+///
+/// Arguments keep their original names unless it would make the body
+/// refer to something else, then they get a number, the same as in `repr`.
+pub(super) fn to_value(node: &Node, heap: &[Node], names: &mut Vec<String>) -> Value {
+    let span = Span::default();
+    match node {
+        Node::Var(idx) => Value::Id {
+            name: names[names.len() - idx].clone(),
+            span,
+        },
+        Node::Free(name) => Value::Id {
+            name: name.clone(),
+            span,
+        },
+        Node::Global(name, val, _) => Value::BoundId {
+            name: name.clone(),
+            value: val.clone(),
+            global: true,
+            span,
+        },
+        Node::Numeral(n, _) => Value::Numeral { n: *n, span },
+        Node::Shared(idx) => to_value(&heap[*idx], heap, &mut Vec::new()),
+        Node::Lam(arg, body) => {
+            let name = arg_name(arg, body, heap, names);
+            names.push(name.clone());
+            let value = to_value(body, heap, names).into();
+            names.pop();
+            Value::Def {
+                arg: name,
+                value,
+                span,
+            }
+        }
+        Node::App(target, arg, _) => Value::Call {
+            target: to_value(target, heap, names).into(),
+            arg: to_value(arg, heap, names).into(),
+            span,
+        },
+    }
+}
+
+/// Pick a name for the argument of the lambda with the given body.
+///
+/// It's the original name unless the body uses something else with that name.
+pub(super) fn arg_name(arg: &str, body: &Node, heap: &[Node], names: &[String]) -> String {
+    let mut taken = HashSet::new();
+    used_names(body, heap, 1, names, &mut taken);
+    let mut name = arg.to_string();
+    let mut n = 0;
+    while taken.contains(&name) {
+        n += 1;
+        name = format!("{arg}{n}");
+    }
+    name
+}
+
+/// Collect all names the node refers to that aren't its own arguments.
+///
+/// The node is under `depth` lambdas that aren't in `names` yet.
+fn used_names(
+    node: &Node,
+    heap: &[Node],
+    depth: usize,
+    names: &[String],
+    out: &mut HashSet<String>,
+) {
+    match node {
+        Node::Var(idx) if *idx > depth => {
+            out.insert(names[names.len() - (idx - depth)].clone());
+        }
+        Node::Var(_) => {}
+        Node::Free(name) | Node::Global(name, ..) => {
+            out.insert(name.clone());
+        }
+        Node::Numeral(n, _) => {
+            out.insert(n.to_string());
+        }
+        Node::Shared(idx) => used_names(&heap[*idx], heap, 0, &[], out),
+        Node::Lam(_, body) => used_names(body, heap, depth + 1, names, out),
+        Node::App(target, arg, _) => {
+            used_names(target, heap, depth, names, out);
+            used_names(arg, heap, depth, names, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Session;
+    use rstest::rstest;
+
+    fn reduce(input: &str, strategy: Strategy) -> anyhow::Result<String> {
+        reduce_eta(input, strategy, false)
+    }
+
+    fn reduce_eta(input: &str, strategy: Strategy, eta: bool) -> anyhow::Result<String> {
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        session.eval_source("I = λx x", None)?;
+        session.set_strategy(strategy);
+        session.set_max_steps(1000);
+        session.set_eta(eta);
+        let val = session.eval_source(input, None)?.unwrap();
+        Ok(val.repr())
+    }
+
+    #[rstest]
+    #[case::normal(Strategy::NormalOrder, "λx x")]
+    #[case::applicative(Strategy::ApplicativeOrder, "")]
+    #[case::name(Strategy::CallByName, "λx x")]
+    #[case::value(Strategy::CallByValue, "")]
+    #[case::need(Strategy::CallByNeed, "λx x")]
+    fn termination(#[case] strategy: Strategy, #[case] exp: &str) {
+        for input in ["K I ((λx x x) (λx x x))", "K I (Y K)"] {
+            match reduce(input, strategy) {
+                Ok(res) => assert_eq!(res, exp, "{input}"),
+                Err(err) => {
+                    assert!(exp.is_empty(), "{input}: {err}");
+                    assert!(err.to_string().starts_with("gave up"));
+                }
+            }
+        }
+    }
+
+    #[rstest]
+    #[case::normal(Strategy::NormalOrder, "λf λx f (f (f (f (f x))))")]
+    #[case::applicative(Strategy::ApplicativeOrder, "λf λx f (f (f (f (f x))))")]
+    #[case::name(Strategy::CallByName, "λf λx 2 f (3 f x)")]
+    #[case::value(Strategy::CallByValue, "λf λx 2 f (3 f x)")]
+    #[case::need(Strategy::CallByNeed, "λf λx 2 f (3 f x)")]
+    fn strategies(#[case] strategy: Strategy, #[case] exp: &str) {
+        let res = reduce("(λm λn λf λx m f (n f x)) 2 3", strategy).unwrap();
+        assert_eq!(res, exp);
+    }
+
+    #[rstest]
+    #[case(r"(λa λb a) b", "λb1 b")]
+    #[case(r"(λf λx f x) (λy x)", "λx1 x")]
+    #[case(r"λx (λy λx y) x", "λx λx1 x")]
+    #[case(r"λx (λy λz y z) x", "λx λz x z")]
+    fn capture_avoiding(#[case] input: &str, #[case] exp: &str) {
+        let res = reduce(input, Strategy::NormalOrder).unwrap();
+        assert_eq!(res, exp);
+    }
+
+    #[rstest]
+    #[case::numeral("add 2 3", false, "λa λb a (a (a (a (a b))))")]
+    #[case::bool("and true (not false)", false, "λa λb a")]
+    #[case::list(
+        "tail [1, 2]",
+        false,
+        "λc c (λa λb b) (λc c (λa λb a (a b)) (λc c (λa λb a) (λa λb a)))"
+    )]
+    #[case::eta_simple(r"λx I x", true, "λx x")]
+    #[case::eta_inner(r"λa λb b (λx a x)", true, "λa λb b a")]
+    #[case::eta_nested(r"λa λb a (λx b x)", true, "λa a")]
+    #[case::eta_not_applied(r"λx x x", true, "λx x x")]
+    #[case::eta_uses_arg(r"λx (λy x y) x", true, "λx x x")]
+    #[case::eta_free(r"λx nope x", true, "nope")]
+    #[case::eta_closure(r"λa λx a x", true, "λa a")]
+    #[case::eta_off(r"λa λx a x", false, "λa λx a x")]
+    fn normal_form(#[case] input: &str, #[case] eta: bool, #[case] exp: &str) {
+        for strategy in [Strategy::NormalOrder, Strategy::ApplicativeOrder] {
+            let res = reduce_eta(input, strategy, eta).unwrap();
+            assert_eq!(res, exp, "{}", strategy.name());
+        }
+    }
+
+    #[test]
+    fn eta_only_normal_form() {
+        let res = reduce_eta(r"λx I x", Strategy::CallByValue, true).unwrap();
+        assert_eq!(res, "λx I x");
+    }
+
+    #[test]
+    fn unbound() {
+        let err = reduce("nope I", Strategy::CallByNeed).unwrap_err();
+        assert_eq!(err.to_string(), "unbound variable `nope`");
+        assert_eq!(
+            reduce("λx nope x", Strategy::NormalOrder).unwrap(),
+            "λx nope x"
+        );
+    }
+
+    #[test]
+    fn names() {
+        for strategy in Strategy::ALL {
+            assert_eq!(Strategy::from_name(strategy.name()), Some(strategy));
+        }
+        assert_eq!(Strategy::from_name("lazy"), None);
+    }
+}
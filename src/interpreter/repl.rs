@@ -1,16 +1,41 @@
-use crate::interpreter::{Helper, Session};
-use crate::parse;
+use crate::interpreter::{Helper, Session, SkipGlobals, Strategy, Sugar, UndefinedNames, Value};
+use crate::Dialect;
 use colored::Colorize;
 use rustyline::error::ReadlineError;
 use rustyline::history::FileHistory;
 use rustyline::Editor;
+use std::path::PathBuf;
+
+/// How many steps `:trace` shows, unless changed with `:limit`.
+///
+/// A trace of an infinite loop would otherwise go on until the reducer gives up.
+const TRACE_LIMIT: usize = 100;
+
+/// Settings for `:trace`, changed by `:skip` and `:limit`.
+struct Tracing {
+    skip: SkipGlobals,
+    limit: Option<usize>,
+}
 
 /// Run interactive rustyline-powered REPL.
 ///
 /// "REPL" means "Read, Eval, Print, Loop".
 /// It reads input, evaluates it, prints the evaluation result,
 /// and repeats the same until the user closes the REPL.
-pub fn run_repl() {
+///
+/// The directories in the search path are where `import` looks for files
+/// besides the current directory. The dialect is used for everything
+/// the user types in. Undefined names in assignments are reported
+/// according to `undefined`, see `UndefinedNames`. Expressions are reduced
+/// with the given strategy, with or without η-reduction, until the user
+/// changes it with `:strategy` or `:eta`. See `run_command` for all commands.
+pub fn run_repl(
+    search_path: &[PathBuf],
+    dialect: Dialect,
+    undefined: UndefinedNames,
+    strategy: Strategy,
+    eta: bool,
+) {
     let helper = Helper::new();
     let mut rl: Editor<&Helper, FileHistory> = Editor::new().unwrap();
     rl.set_helper(Some(&helper));
@@ -18,6 +43,17 @@ pub fn run_repl() {
         println!("{}", "No previous history.".yellow());
     }
     let mut session = Session::new(Some(&helper));
+    for dir in search_path {
+        session.add_search_path(dir);
+    }
+    session.set_dialect(dialect);
+    session.set_undefined_names(undefined);
+    session.set_strategy(strategy);
+    session.set_eta(eta);
+    let mut tracing = Tracing {
+        skip: SkipGlobals::Nothing,
+        limit: Some(TRACE_LIMIT),
+    };
     if let Err(err) = session.load_stdlib() {
         let msg = format!("{:?}", err.context("failed to load stdlib"));
         println!("{}", msg.red());
@@ -26,22 +62,39 @@ pub fn run_repl() {
         let readline = rl.readline(">>> ");
         match readline {
             Ok(input) => {
-                // We currently do not support multiline expressions in REPL,
-                // and a statement is a part of a statement, so let's explicitly
-                // allow and skip comments.
-                if input.starts_with('#') {
+                // Multiline expressions are handled by the `Validator`
+                // implementation of the `Helper`, so the input we get here
+                // is always complete.
+                rl.add_history_entry(&input).unwrap();
+                // Commands for the REPL itself start with a colon,
+                // which can't start a statement in the language.
+                if let Some(command) = input.trim().strip_prefix(':') {
+                    match run_command(&mut session, &mut tracing, command) {
+                        Ok(msg) => println!("{msg}"),
+                        Err(err) => println!("{}", format!("{err:?}").red()),
+                    }
                     continue;
                 }
-                rl.add_history_entry(&input).unwrap();
                 // Parse, eval, and print the input.
-                let res = match parse(&input) {
-                    Ok(module) => match session.eval_module(&module) {
-                        Ok(result) => result.repr().green(),
-                        Err(err) => format!("{err:?}").red(),
-                    },
-                    Err(err) => err.to_string().red(),
+                // If the input has only comments, there is nothing to print.
+                // The session parses the input because it knows
+                // about the operators declared before, and it also
+                // takes care of imports.
+                let res = match session
+                    .eval_source(&input, None)
+                    .map(Option::<&Value>::cloned)
+                {
+                    Ok(Some(result)) => Some(describe(&session, &result)),
+                    Ok(None) => None,
+                    Err(err) => Some(format!("{err:?}").red().to_string()),
                 };
-                println!("{res}");
+                // Warnings are about the input, so they go before the result.
+                for warning in session.take_warnings() {
+                    println!("{}", format!("warning: {warning}").yellow());
+                }
+                if let Some(res) = res {
+                    println!("{res}");
+                }
             }
             Err(ReadlineError::Interrupted) => {
                 println!("{}", "CTRL-C".yellow());
@@ -59,3 +112,115 @@ pub fn run_repl() {
     }
     rl.save_history("history.txt").unwrap();
 }
+
+/// Run a REPL command (without the colon) and return the colored message for the user.
+///
+/// Supported commands:
+///
+/// + `:strategy` shows the current reduction strategy and all available ones.
+/// + `:strategy <name>` switches to the strategy, like `:strategy normal-order`.
+/// + `:eta` shows if η-reduction is on.
+/// + `:eta on` and `:eta off` turn η-reduction on and off.
+/// + `:trace <expr>` prints each step of evaluating the expression.
+/// + `:skip` shows globals whose steps `:trace` hides.
+/// + `:skip all`, `:skip none`, or `:skip <name> <name>...` sets them.
+/// + `:limit` shows how many steps `:trace` shows at most.
+/// + `:limit <number>` or `:limit off` sets it.
+fn run_command(
+    session: &mut Session,
+    tracing: &mut Tracing,
+    command: &str,
+) -> anyhow::Result<String> {
+    let (name, args) = command
+        .split_once(char::is_whitespace)
+        .unwrap_or((command, ""));
+    let args: Vec<&str> = args.split_whitespace().collect();
+    match (name, &args[..]) {
+        ("eta" | "skip" | "limit", []) => {}
+        ("strategy", []) => {
+            let names: Vec<_> = Strategy::ALL.iter().map(|s| s.name()).collect();
+            let msg = format!(
+                "strategy: {} (available: {})",
+                session.strategy().name(),
+                names.join(", ")
+            );
+            return Ok(msg.yellow().to_string());
+        }
+        ("strategy", [name]) => {
+            let Some(strategy) = Strategy::from_name(name) else {
+                anyhow::bail!("unknown strategy `{name}`, type `:strategy` to see all");
+            };
+            session.set_strategy(strategy);
+            let msg = format!("strategy: {}", strategy.name());
+            return Ok(msg.yellow().to_string());
+        }
+        ("strategy", _) => anyhow::bail!("usage: `:strategy [name]`"),
+        ("eta", ["on"]) => session.set_eta(true),
+        ("eta", ["off"]) => session.set_eta(false),
+        ("eta", _) => anyhow::bail!("usage: `:eta [on|off]`"),
+        ("trace", []) => anyhow::bail!("usage: `:trace <expression>`"),
+        ("trace", _) => return trace(session, tracing, command["trace".len()..].trim()),
+        ("skip", ["all"]) => tracing.skip = SkipGlobals::All,
+        ("skip", ["none"]) => tracing.skip = SkipGlobals::Nothing,
+        ("skip", names) => {
+            tracing.skip = SkipGlobals::Only(names.iter().map(ToString::to_string).collect());
+        }
+        ("limit", ["off"]) => tracing.limit = None,
+        ("limit", [limit]) => {
+            let Ok(limit) = limit.parse() else {
+                anyhow::bail!("usage: `:limit [number|off]`");
+            };
+            tracing.limit = Some(limit);
+        }
+        ("limit", _) => anyhow::bail!("usage: `:limit [number|off]`"),
+        _ => anyhow::bail!("unknown command `:{command}`"),
+    }
+    // The command has changed a setting or is asking for it,
+    // so we show its current value.
+    let msg = match name {
+        "eta" if session.eta() => "eta-reduction: on".to_string(),
+        "eta" => "eta-reduction: off".to_string(),
+        "skip" => match &tracing.skip {
+            SkipGlobals::Nothing => "skip: none".to_string(),
+            SkipGlobals::All => "skip: all".to_string(),
+            SkipGlobals::Only(names) => {
+                let mut names: Vec<_> = names.iter().map(String::as_str).collect();
+                names.sort_unstable();
+                format!("skip: {}", names.join(" "))
+            }
+        },
+        _ => match tracing.limit {
+            Some(limit) => format!("limit: {limit}"),
+            None => "limit: off".to_string(),
+        },
+    };
+    Ok(msg.yellow().to_string())
+}
+
+/// Print each step of evaluating the input, see `Session::trace`.
+fn trace(session: &mut Session, tracing: &Tracing, input: &str) -> anyhow::Result<String> {
+    let result = session.trace(input, None, &tracing.skip, tracing.limit, &mut |line| {
+        println!("{}", line.to_colored_string());
+    })?;
+    let Some(result) = result else {
+        let limit = tracing.limit.unwrap_or(0);
+        let msg = format!("stopped after {limit} steps, see `:limit`");
+        return Ok(msg.yellow().to_string());
+    };
+    Ok(describe(session, &result))
+}
+
+/// Represent the result and, if there are globals that are the same
+/// up to names of arguments, add a comment with their names.
+///
+/// So, `not true` is shown as `λa λb b  # false`, and the user doesn't
+/// have to remember what `false` looks like.
+fn describe(session: &Session, result: &Value) -> String {
+    let repr = session.repr(result, Sugar::default()).green();
+    let names = session.names_of(result);
+    if names.is_empty() {
+        return repr.to_string();
+    }
+    let comment = format!("# {}", names.join(", "));
+    format!("{repr}  {}", comment.dimmed())
+}
@@ -1,34 +1,145 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::interpreter::Value;
 
+/// The name of the module for the code that doesn't come from an imported file:
+/// the REPL input, the stdin, or the file passed to `rlci eval`.
+pub const MAIN: &str = "main";
+
 /// The global scope holds all global names defined in the current session.
 ///
-/// This is a quite straightforward wrapper around a hash map.
+/// Each module has its own namespace, so that the names defined in one module
+/// don't clash with the names in another one. Besides, there is the prelude,
+/// the namespace for all exported names that is visible from all modules.
+///
+/// Everything is a quite straightforward wrapper around hash maps.
 /// The scope owns all values inside of it.
 #[derive(Debug)]
 pub struct GlobalScope {
-    values: HashMap<String, Value>,
+    /// Names defined in each module, by the module name.
+    modules: HashMap<String, HashMap<String, Value>>,
+    /// Names exported by modules.
+    prelude: HashMap<String, Value>,
+    /// The module that exported each name in the prelude.
+    exporters: HashMap<String, String>,
+    /// The module that is being evaluated right now.
+    /// All new names are defined in this module.
+    current: String,
 }
 
 impl GlobalScope {
     pub fn new() -> Self {
         Self {
-            values: HashMap::new(),
+            modules: HashMap::from([(MAIN.to_string(), HashMap::new())]),
+            prelude: HashMap::new(),
+            exporters: HashMap::new(),
+            current: MAIN.to_string(),
         }
     }
 
     /// Get the value of the given name from the scope if available.
+    ///
+    /// A qualified name, like `nat.add`, is looked up in the given module.
+    /// An unqualified name is looked up in the current module first
+    /// and then in the prelude.
     pub fn get(&self, name: &str) -> Option<&Value> {
-        self.values.get(name)
+        if let Some((module, name)) = name.split_once('.') {
+            return self.modules.get(module)?.get(name);
+        }
+        self.names().get(name).or_else(|| self.prelude.get(name))
     }
 
-    /// Save a value in the scope udner the given name.
+    /// Save a value in the current module under the given name.
     ///
     /// It takes the ownership of the value, so for the code to keep using the value
     /// the pointer to that value should be returned.
     pub fn set(&mut self, name: &str, val: Value) -> &Value {
-        self.values.insert(name.to_string(), val);
-        self.get(name).unwrap()
+        let names = self.modules.get_mut(&self.current).unwrap();
+        names.insert(name.to_string(), val);
+        names.get(name).unwrap()
+    }
+
+    /// Copy the value of the name from the current module into the prelude.
+    ///
+    /// Returns `None` if the current module has no such name.
+    pub fn export(&mut self, name: &str) -> Option<&Value> {
+        let val = self.names().get(name)?.clone();
+        self.prelude.insert(name.to_string(), val);
+        self.exporters
+            .insert(name.to_string(), self.current.clone());
+        self.prelude.get(name)
+    }
+
+    /// All names visible without the module name: the names defined
+    /// in the current module and the exported ones.
+    pub fn visible_names(&self) -> HashSet<String> {
+        self.names()
+            .keys()
+            .chain(self.prelude.keys())
+            .cloned()
+            .collect()
+    }
+
+    /// All names visible without the module name with the module each one comes from:
+    /// the current module or the module that exported the name.
+    pub fn visible_modules(&self) -> HashMap<String, String> {
+        let defined = self.names().keys().map(|name| (name, &self.current));
+        // The names defined in the current module go last to hide the exported ones.
+        self.exporters
+            .iter()
+            .chain(defined)
+            .map(|(name, module)| (name.clone(), module.clone()))
+            .collect()
+    }
+
+    /// All names visible without the module name with their values.
+    ///
+    /// A name defined in the current module hides the exported one.
+    pub fn visible(&self) -> impl Iterator<Item = (&str, &Value)> {
+        let names = self.names();
+        let exported = self
+            .prelude
+            .iter()
+            .filter(|(name, _)| !names.contains_key(*name));
+        names
+            .iter()
+            .chain(exported)
+            .map(|(name, val)| (name.as_str(), val))
+    }
+
+    /// The name of the module that is being evaluated right now.
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    /// Check if there is a module with the given name.
+    pub fn has_module(&self, module: &str) -> bool {
+        self.modules.contains_key(module)
+    }
+
+    /// Make a new module and make it the current one.
+    ///
+    /// Returns the name of the previous module, pass it to `leave`
+    /// when the new module is evaluated.
+    pub fn enter(&mut self, module: &str) -> String {
+        self.modules.entry(module.to_string()).or_default();
+        std::mem::replace(&mut self.current, module.to_string())
+    }
+
+    /// Go back to the previous module.
+    ///
+    /// If the module we leave has failed to evaluate, it's removed.
+    /// Otherwise, a half-evaluated module would stay forever,
+    /// and the user couldn't import it again after fixing it.
+    pub fn leave(&mut self, previous: String, failed: bool) {
+        let module = std::mem::replace(&mut self.current, previous);
+        if failed {
+            self.modules.remove(&module);
+        }
+    }
+
+    /// All names defined in the current module.
+    fn names(&self) -> &HashMap<String, Value> {
+        &self.modules[&self.current]
     }
 }
@@ -1,15 +1,94 @@
 use anyhow::Context;
 
-use crate::ast_nodes::{Expr, Module, Stmt};
-use crate::interpreter::{read_stdlib, GlobalScope, Helper, Value};
+use crate::ast_nodes::{Expr, Module, Span, Stmt};
+use crate::fixity::{Fixities, Fixity};
+use crate::interpreter::{
+    read_stdlib, GlobalScope, Helper, SkipGlobals, Strategy, Sugar, Term, TraceLine, TraceOptions,
+    Value, MAIN, MAX_STEPS,
+};
+use crate::lint::{lint, Diagnostic};
+use crate::parser::{is_word_char, parse_source, parse_source_recovering, Dialect, Rule};
+use pest::error::Error;
+use std::cell::OnceCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// All syntax errors found in the input.
+///
+/// It's returned (wrapped into `anyhow::Error`) by the methods that both parse
+/// and evaluate the input, so that the caller can tell syntax errors apart
+/// from errors at runtime using `downcast_ref`.
+#[derive(Debug)]
+pub struct SyntaxErrors {
+    errors: Vec<Error<Rule>>,
+}
+
+impl fmt::Display for SyntaxErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msgs: Vec<_> = self.errors.iter().map(ToString::to_string).collect();
+        write!(f, "{}", msgs.join("\n\n"))
+    }
+}
+
+impl std::error::Error for SyntaxErrors {}
+
+/// What to do when an assignment uses a name that isn't defined.
+///
+/// Names are bound when the assignment is evaluated (see `Value::bind_global`),
+/// so a name that isn't defined at that moment will never be defined for it.
+/// It fails only when (and if) the function is called, which might be
+/// much later and far away from the typo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UndefinedNames {
+    /// Fail the assignment, the name doesn't get defined.
+    Error,
+    /// Define the name but remember a warning, see `Session::take_warnings`.
+    #[default]
+    Warn,
+    /// Don't check anything, fail only when the undefined name is evaluated.
+    Ignore,
+}
 
 /// Session is a runtime of a program.
 ///
 /// The Session loads stdlib, holds the global state, and is responsible
 /// for evaluating modules and statements.
+///
+/// The state includes fixity declarations. They're needed to parse the input,
+/// so the input that uses operators declared in the session should be parsed
+/// with `Session::parse`.
+///
+/// The session also keeps track of imported files, so that each file
+/// is evaluated only once, and import cycles are reported instead of
+/// looping forever. Each imported file (and each stdlib module) is evaluated
+/// in its own namespace, named after the file, and only the names it exports
+/// are visible without the module name.
 pub struct Session<'a> {
     global: GlobalScope,
+    fixities: Fixities,
     helper: Option<&'a Helper>,
+    /// Directories where to look for imported files, besides the directory
+    /// of the file that imports them.
+    search_path: Vec<PathBuf>,
+    /// Canonical paths of all files that have been evaluated.
+    imported: HashSet<PathBuf>,
+    /// Canonical paths of the files that are being evaluated right now.
+    /// The last one is the innermost, the file that imports something
+    /// is always before the file it imports.
+    importing: Vec<PathBuf>,
+    /// The dialect for the inputs that don't have a dialect pragma.
+    dialect: Dialect,
+    /// What to do with undefined names in assignments.
+    undefined_names: UndefinedNames,
+    /// Warnings that haven't been shown to the user yet.
+    warnings: Vec<String>,
+    /// The reduction strategy for expressions.
+    strategy: Strategy,
+    /// How many steps the reducer makes before giving up.
+    max_steps: usize,
+    /// Do η-reduction when reducing to the normal form.
+    eta: bool,
 }
 
 impl<'a> Session<'a> {
@@ -22,46 +101,375 @@ impl<'a> Session<'a> {
     pub fn new(helper: Option<&'a Helper>) -> Self {
         Self {
             global: GlobalScope::new(),
+            fixities: Fixities::new(),
             helper,
+            search_path: Vec::new(),
+            imported: HashSet::new(),
+            importing: Vec::new(),
+            dialect: Dialect::Standard,
+            undefined_names: UndefinedNames::default(),
+            warnings: Vec::new(),
+            strategy: Strategy::default(),
+            max_steps: MAX_STEPS,
+            eta: false,
         }
     }
 
+    /// Add a directory to the list of directories where to look for imported files.
+    ///
+    /// Directories are checked in the order they were added,
+    /// after the directory of the file with the import.
+    pub fn add_search_path(&mut self, dir: &Path) {
+        self.search_path.push(dir.to_path_buf());
+    }
+
+    /// Set the dialect for all the next inputs, including imported files.
+    ///
+    /// An input can still choose another dialect with a pragma.
+    /// The stdlib is always in the standard dialect.
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.dialect = dialect;
+    }
+
+    /// Set what to do when an assignment uses a name that isn't defined.
+    pub fn set_undefined_names(&mut self, mode: UndefinedNames) {
+        self.undefined_names = mode;
+    }
+
+    /// Set the reduction strategy for all the next expressions.
+    ///
+    /// Assignments aren't evaluated, so the strategy matters only
+    /// when the value is used in an expression.
+    pub fn set_strategy(&mut self, strategy: Strategy) {
+        self.strategy = strategy;
+    }
+
+    /// The reduction strategy for expressions, see `set_strategy`.
+    #[must_use]
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    /// Set how many steps to make before giving up on an expression.
+    ///
+    /// It's used by all strategies except call-by-name,
+    /// which can only be stopped by a stack overflow.
+    pub fn set_max_steps(&mut self, max_steps: usize) {
+        self.max_steps = max_steps;
+    }
+
+    /// Turn on or off η-reduction: replacing `λx f x` with `f`.
+    ///
+    /// It's done only by the strategies that reduce to the normal form,
+    /// see `Strategy`.
+    pub fn set_eta(&mut self, eta: bool) {
+        self.eta = eta;
+    }
+
+    /// If η-reduction is on, see `set_eta`.
+    #[must_use]
+    pub fn eta(&self) -> bool {
+        self.eta
+    }
+
+    /// Get all warnings produced since the last call, and forget them.
+    ///
+    /// The session doesn't print anything on its own, so it's up to the caller
+    /// to show the warnings to the user after each evaluation.
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
     /// Read stdlib and evaluate it in the current session context.
     ///
-    /// Since there is no "import" statement, users cannot explicitly
-    /// import anything from the stdlib, so we implicitly import it
+    /// The `import` statement works only with files on the disk, and the stdlib
+    /// is embedded into the binary. So, we implicitly import it
     /// at the shell startup. It's called "prelude" in Haskell and Rust
     /// and "builtins" in Python.
+    ///
+    /// Each stdlib module gets its own namespace, just like imported files do.
     pub fn load_stdlib(&mut self) -> anyhow::Result<()> {
-        for module in read_stdlib()? {
-            self.eval_module(&module).context("failed to eval module")?;
+        for (name, module) in read_stdlib(&mut self.fixities)? {
+            self.in_module(&name, |session| session.eval_module(&module).map(drop))
+                .with_context(|| format!("failed to eval stdlib/{name} module"))?;
         }
         Ok(())
     }
 
+    /// Parse the input, taking into account the operators declared in the session.
+    ///
+    /// Fixity declarations from the input are added to the session right away,
+    /// even before the module is evaluated.
+    #[allow(clippy::result_large_err)]
+    pub fn parse(&mut self, input: &str) -> Result<Module, Error<Rule>> {
+        parse_source(input, None, &mut self.fixities, self.dialect)
+    }
+
+    /// Same as `parse` but recovers from syntax errors, see `parse_recovering`.
+    pub fn parse_recovering(&mut self, input: &str) -> (Module, Vec<Error<Rule>>) {
+        parse_source_recovering(input, None, &mut self.fixities, self.dialect)
+    }
+
+    /// Parse and evaluate the input and return the result of the last statement.
+    ///
+    /// It's the same as parsing the input with `parse_recovering` and then
+    /// evaluating it with `eval_module`, except that all imported files are loaded
+    /// before the module is evaluated. If any of them declare new operators,
+    /// the input is parsed again, so that the operators can be used in the input.
+    ///
+    /// The path, if given, is used in spans and error messages, and imports
+    /// in the input are resolved relative to it. If syntax errors are found,
+    /// they all are returned as `SyntaxErrors`.
+    pub fn eval_source(
+        &mut self,
+        input: &str,
+        path: Option<&str>,
+    ) -> anyhow::Result<Option<&Value>> {
+        let module = self.parse_with_imports(input, path)?;
+        self.eval_module(&module)
+    }
+
+    /// Read the file, parse it, and evaluate it.
+    ///
+    /// It's how imports are evaluated, and it's also the way to run the main file,
+    /// so that the imports in it are resolved relative to the file and
+    /// an import of the main file from another file is detected as a cycle.
+    /// Unlike `eval_source`, it returns a copy of the result rather than a reference
+    /// because we still need to update the import state after the evaluation.
+    pub fn eval_file(&mut self, path: &Path) -> anyhow::Result<Option<Value>> {
+        let key = path
+            .canonicalize()
+            .with_context(|| format!("cannot open {}", path.display()))?;
+        if let Some(pos) = self.importing.iter().position(|p| p == &key) {
+            let chain: Vec<_> = self.importing[pos..]
+                .iter()
+                .chain([&key])
+                .map(|p| p.display().to_string())
+                .collect();
+            anyhow::bail!("import cycle: {}", chain.join(" -> "));
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("cannot read {}", path.display()))?;
+        self.importing.push(key.clone());
+        let path = path.display().to_string();
+        let result = self
+            .eval_source(&content, Some(&path))
+            .map(Option::<&Value>::cloned);
+        self.importing.pop();
+        // If the file failed, it will be evaluated again next time
+        // it's imported. Perhaps, the user has fixed it in the meantime.
+        if result.is_ok() {
+            self.imported.insert(key);
+        }
+        result
+    }
+
     /// Evaluate all statements in the module and return the result of the last one.
-    pub fn eval_module(&mut self, module: &Module) -> anyhow::Result<&Value> {
-        for stmt in &module.stmts[..(module.stmts.len() - 1)] {
+    ///
+    /// A module may have no statements at all if it contains only comments.
+    /// Then there is nothing to evaluate and there is no result.
+    /// The same if the last statement is an import or a fixity declaration.
+    pub fn eval_module(&mut self, module: &Module) -> anyhow::Result<Option<&Value>> {
+        let Some((last, stmts)) = module.stmts.split_last() else {
+            return Ok(None);
+        };
+        for stmt in stmts {
             self.eval_stmt(stmt)?;
         }
-        let stmt = module.stmts.last().unwrap();
-        self.eval_stmt(stmt)
+        self.eval_stmt(last)
+    }
+
+    /// Read a term in de Bruijn notation and evaluate it.
+    ///
+    /// Free variables in the term are globals of the session, so `λ nat.inc 1`
+    /// works as expected. Numbers are indices there, so Church numerals
+    /// can't be referred by their names.
+    pub fn eval_debruijn(&mut self, input: &str) -> anyhow::Result<&Value> {
+        let term = Term::parse(input)?;
+        for name in term.free_names() {
+            if self.global.get(name).is_none() {
+                anyhow::bail!("variable `{name}` is not defined");
+            }
+        }
+        let val = term.to_value().bind_global(&self.global);
+        let val = self.eval_value(&val)?;
+        Ok(self.global.set("_", val))
+    }
+
+    /// Evaluate the value with the strategy of the session.
+    ///
+    /// Call-by-name is what `Value::eval` does, and it's much faster
+    /// than the reducer, so the reducer is used only for other strategies.
+    fn eval_value(&self, val: &Value) -> anyhow::Result<Value> {
+        match self.strategy {
+            Strategy::CallByName => val.eval(),
+            strategy => val.reduce(strategy, self.eta, self.max_steps),
+        }
+    }
+
+    /// Names of all globals visible without the module name that are the same
+    /// as the value, up to names of arguments (see `PartialEq` for `Value`).
+    ///
+    /// It tells the user that `λa λb a` they've got is `true`. The last result,
+    /// `_`, is not included, it's always the same.
+    #[must_use]
+    ///
+    /// Numerals are compared by their numbers first. Spelled out, `'🦀'` is
+    /// a term 129408 calls deep, so it's built only if there is a global
+    /// that isn't a numeral to compare it with.
+    pub fn names_of(&self, val: &Value) -> Vec<&str> {
+        let term = OnceCell::new();
+        let same = |global: &Value| match (val.unwrap_bound(), global.unwrap_bound()) {
+            (Value::Numeral { n, .. }, Value::Numeral { n: m, .. }) => n == m,
+            _ => global.to_debruijn() == *term.get_or_init(|| val.to_debruijn()),
+        };
+        let mut names: Vec<_> = self
+            .global
+            .visible()
+            .filter(|(name, global)| *name != "_" && same(global))
+            .map(|(name, _)| name)
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Represent the value with `Value::repr_with` so that the output means
+    /// the same value in the session, see `Value::repr_among`.
+    ///
+    /// So, if the session has `1 = K`, a numeral `1` inside of the value
+    /// is printed in full and not as the number.
+    #[must_use]
+    pub fn repr(&self, val: &Value, sugar: Sugar) -> String {
+        val.repr_among(sugar, &self.global.visible_names())
+    }
+
+    /// Parse the source and run the linter on it, see `crate::lint`.
+    ///
+    /// The source itself is not evaluated, but its imports are: they
+    /// may declare operators the source uses, and the names they export
+    /// are globals for the linter, the same as the names defined before.
+    ///
+    /// The names exported by the module the file would be evaluated in
+    /// are not globals for it. They are already in the prelude only because
+    /// the file is a stdlib module (`rlci lint src/stdlib/nat.rb`),
+    /// and the file defines them, not redefines.
+    pub fn lint(&mut self, input: &str, path: Option<&str>) -> anyhow::Result<Vec<Diagnostic>> {
+        // The parser doesn't accept an empty input, but there is nothing to check.
+        if input.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let module = self.parse_with_imports(input, path)?;
+        let mut globals = self.global.visible_modules();
+        if let Some(name) = path.and_then(|path| Path::new(path).file_stem()) {
+            let name = name.to_string_lossy();
+            globals.retain(|_, module| *module != name);
+        }
+        Ok(lint(&module, input, &globals))
+    }
+
+    /// Evaluate the input and trace the reduction of its last expression.
+    ///
+    /// All statements but the last are evaluated as usual, and the last one
+    /// should be an expression. It's reduced step by step with the strategy
+    /// of the session, and `on_line` is called for each line of the trace
+    /// (see `trace.rs`). Steps inside of globals can be hidden, and
+    /// if the limit is given, the trace stops after that many steps shown.
+    ///
+    /// Returns the result of the expression, or `None` if the trace was stopped.
+    pub fn trace(
+        &mut self,
+        input: &str,
+        path: Option<&str>,
+        skip: &SkipGlobals,
+        limit: Option<usize>,
+        on_line: &mut dyn FnMut(TraceLine),
+    ) -> anyhow::Result<Option<Value>> {
+        let mut module = self.parse_with_imports(input, path)?;
+        let Some(Stmt::Expr { expr, .. }) = module.stmts.pop() else {
+            anyhow::bail!("nothing to trace, the input should end with an expression");
+        };
+        self.eval_module(&module)?;
+        let val = Value::from_expr(&expr).bind_global(&self.global);
+        let options = TraceOptions {
+            strategy: self.strategy,
+            eta: self.eta,
+            max_steps: self.max_steps,
+            skip,
+            limit,
+        };
+        let Some(result) = val.trace(&options, on_line)? else {
+            return Ok(None);
+        };
+        Ok(Some(self.global.set("_", result).clone()))
     }
 
     /// Evaluate the statement and return its result.
     ///
-    /// In the current implementation, any statement can be evaluated
-    /// into a specific value. The only failure possible is when a name is undefined.
-    fn eval_stmt(&mut self, stmt: &Stmt) -> anyhow::Result<&Value> {
+    /// Assignments and expressions are evaluated into a specific value.
+    /// The only failure possible is when a name is undefined.
+    /// Imports, exports, and fixity declarations have no value.
+    fn eval_stmt(&mut self, stmt: &Stmt) -> anyhow::Result<Option<&Value>> {
         match stmt {
             // Assignment: store the value in the global scope.
-            Stmt::Assign { target, expr } => {
+            Stmt::Assign { target, expr, .. } => {
                 let val = Value::from_expr(expr);
                 let val = val.bind_global(&self.global);
+                self.check_undefined(target, &val)?;
+                // Names from other modules can be accessed only
+                // by the qualified name, unless they are exported.
                 if let Some(helper) = self.helper {
-                    helper.add(target);
+                    match self.global.current() {
+                        MAIN => helper.add(target),
+                        module => helper.add(&format!("{module}.{target}")),
+                    }
+                }
+                Ok(Some(self.global.set(target, val)))
+            }
+            // Import: evaluate the file if it wasn't evaluated yet.
+            //
+            // If the module was evaluated by `eval_source`, the file
+            // is already imported, and this is a no-op.
+            Stmt::Import { path, span } => {
+                self.import(path, span)?;
+                Ok(None)
+            }
+            // Export: make the names visible from all modules without the module name.
+            //
+            // The value is copied into the prelude as it is at the moment of export.
+            // If the module redefines the name later, it should export it again.
+            Stmt::Export { names, span } => {
+                for name in names {
+                    if self.global.export(name).is_none() {
+                        anyhow::bail!(
+                            "cannot export `{name}` at {span}: it's not defined in module `{}`",
+                            self.global.current()
+                        );
+                    }
+                    if let Some(helper) = self.helper {
+                        helper.add(name);
+                    }
+                }
+                Ok(None)
+            }
+            // Fixity declaration: remember it for parsing the next inputs.
+            //
+            // If the module was parsed by `Session::parse`, the parser has
+            // already done it. But the module might have been parsed
+            // without the session, and then it's our only chance to learn about it.
+            Stmt::Fixity {
+                assoc,
+                precedence,
+                operators,
+                ..
+            } => {
+                let fixity = Fixity {
+                    assoc: *assoc,
+                    precedence: *precedence,
+                };
+                for name in operators {
+                    self.fixities.declare(name, fixity);
                 }
-                Ok(self.global.set(target, val))
+                Ok(None)
             }
             // Variable name: show its value.
             //
@@ -72,28 +480,194 @@ impl<'a> Session<'a> {
             // Without this branch, when the user types `true`, they would simply get
             // `true` back. This branch unwraps the explicit name to the actual
             // representation of its value.
+            //
+            // Numbers don't need to be defined, and they don't have a global
+            // value we could point to. So, if the name is a number that isn't defined,
+            // we make a new Church numeral and store it as the last result.
             Stmt::Expr {
-                expr: Expr::Id { name },
-            } => match self.global.get(name) {
-                Some(val) => Ok(val),
-                None => anyhow::bail!("variable `{name}` is not defined"),
-            },
+                expr: Expr::Id { name, span },
+                ..
+            } => {
+                if self.global.get(name).is_none() {
+                    let Some(val) = Value::numeral(name, span) else {
+                        anyhow::bail!("variable `{name}` is not defined at {span}")
+                    };
+                    return Ok(Some(self.global.set("_", val)));
+                }
+                Ok(self.global.get(name))
+            }
             // An arbitrary expression: eagerly evaluate.
-            Stmt::Expr { expr } => {
+            Stmt::Expr { expr, .. } => {
                 let val = Value::from_expr(expr);
                 let val = val.bind_global(&self.global);
-                let val = val.eval()?;
-                Ok(self.global.set("_", val))
+                let val = self.eval_value(&val)?;
+                Ok(Some(self.global.set("_", val)))
             }
         }
     }
+
+    /// Report names used in the assignment that aren't defined.
+    ///
+    /// Depending on the session setting, it's either an error or a warning.
+    fn check_undefined(&mut self, target: &str, val: &Value) -> anyhow::Result<()> {
+        if self.undefined_names == UndefinedNames::Ignore {
+            return Ok(());
+        }
+        let msgs: Vec<_> = val
+            .unbound_names()
+            .iter()
+            .map(|(name, span)| format!("undefined name `{name}` in `{target}` at {span}"))
+            .collect();
+        if msgs.is_empty() {
+            return Ok(());
+        }
+        if self.undefined_names == UndefinedNames::Error {
+            anyhow::bail!("{}", msgs.join("\n"));
+        }
+        self.warnings.extend(msgs);
+        Ok(())
+    }
+
+    /// Parse the input and evaluate the files it imports.
+    ///
+    /// If any of them declare new operators, the input is parsed again,
+    /// so that the operators can be used in the input.
+    fn parse_with_imports(&mut self, input: &str, path: Option<&str>) -> anyhow::Result<Module> {
+        let fixities = self.fixities.clone();
+        let mut module = self.parse_checked(input, path)?;
+        for stmt in &module.stmts {
+            if let Stmt::Import { path, span } = stmt {
+                self.import(path, span)?;
+            }
+        }
+        if self.fixities != fixities {
+            module = self.parse_checked(input, path)?;
+        }
+        Ok(module)
+    }
+
+    /// Parse the input, turning syntax errors into `SyntaxErrors`.
+    fn parse_checked(&mut self, input: &str, path: Option<&str>) -> anyhow::Result<Module> {
+        let (module, errors) =
+            parse_source_recovering(input, path, &mut self.fixities, self.dialect);
+        if !errors.is_empty() {
+            return Err(SyntaxErrors { errors }.into());
+        }
+        Ok(module)
+    }
+
+    /// Evaluate the imported file unless it's already evaluated.
+    ///
+    /// The file is evaluated in a new module named after the file.
+    /// So, all names defined in `lib/ops.rb` are available as `ops.name`.
+    fn import(&mut self, target: &str, span: &Span) -> anyhow::Result<()> {
+        let path = self.resolve(target, span)?;
+        if self.imported.contains(&path) {
+            return Ok(());
+        }
+        let emsg = format!("failed to import `{target}` at {span}");
+        // The file is being evaluated right now, it's an import cycle,
+        // and `eval_file` will report it. We shouldn't enter the module
+        // of that file again, or we'll remove it on failure while it's still
+        // being evaluated.
+        if self.importing.contains(&path) {
+            return self.eval_file(&path).map(drop).context(emsg);
+        }
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+        if name.is_empty() || !name.chars().all(is_word_char) {
+            anyhow::bail!(
+                "cannot import `{target}` at {span}: `{name}` is not a valid module name"
+            );
+        }
+        if self.global.has_module(&name) {
+            anyhow::bail!("cannot import `{target}` at {span}: module `{name}` already exists");
+        }
+        self.in_module(&name, |session| session.eval_file(&path).map(drop))
+            .context(emsg)
+    }
+
+    /// Call the function with the given module being the current one.
+    ///
+    /// If the function fails, the module is removed, so that it can be
+    /// imported again when the user fixes the error.
+    fn in_module<F>(&mut self, module: &str, f: F) -> anyhow::Result<()>
+    where
+        F: FnOnce(&mut Self) -> anyhow::Result<()>,
+    {
+        let previous = self.global.enter(module);
+        let result = f(self);
+        self.global.leave(previous, result.is_err());
+        result
+    }
+
+    /// Find the imported file and return its canonical path.
+    ///
+    /// The path is relative to the directory of the file with the import.
+    /// If the import is not in a file (for example, in REPL), the path
+    /// is relative to the current directory. If the file is not there,
+    /// we look for it in the search path.
+    ///
+    /// The canonical path is absolute and has all symlinks resolved. So, the same file
+    /// imported from different places with different relative paths
+    /// still has the same canonical path, and we know it's the same file.
+    fn resolve(&self, target: &str, span: &Span) -> anyhow::Result<PathBuf> {
+        // An empty path joined with a relative path is the same relative path,
+        // which is relative to the current directory.
+        let base = span
+            .file
+            .as_deref()
+            .and_then(|file| Path::new(file).parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        for dir in std::iter::once(&base).chain(&self.search_path) {
+            let path = dir.join(target);
+            if path.is_file() {
+                return Ok(path.canonicalize()?);
+            }
+        }
+        anyhow::bail!("cannot find `{target}` imported at {span}")
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lint::LintKind;
     use crate::parse;
     use rstest::rstest;
+    use std::fs;
+
+    /// A temporary directory, removed with everything inside when dropped.
+    ///
+    /// It's removed even if the test fails, so that running tests
+    /// doesn't leave anything behind.
+    struct TempDir(PathBuf);
+
+    impl std::ops::Deref for TempDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Create a fresh directory with the given files to test imports.
+    fn make_dir(name: &str, files: &[(&str, &str)]) -> TempDir {
+        let dir = std::env::temp_dir().join(format!("rlci-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        TempDir(dir)
+    }
 
     // These are the most important tests of the runtime.
     // Perhaps, there should be more of these.
@@ -112,6 +686,16 @@ mod tests {
     #[case(r"(\a (\a a) (\x a)) A", "λx A")]
     #[case(r"(\a (\a a) (\x a)) A B", "λa a")]
     #[case(r"(\a \a a) A", "λa a")]
+    #[case::nat(r"0", "λa λb b")]
+    #[case::nat(r"3", "λa λb a (a (a b))")]
+    #[case::nat(r"\a 3", "λa 3")]
+    #[case::nat(r"\3 3", "λ3 3")]
+    #[case::nat(r"(\3 3) A", "λa a")]
+    #[case::nat(r"id 2", "λa λb a (a b)")]
+    #[case::nat(r"2 A", "λb A (1 A b)")]
+    #[case::let_in(r"let x = A in x B", "λb b")]
+    #[case::let_in(r"let x = A, y = x in y", "λa a")]
+    #[case::let_in(r"let x = B in let x = A in x", "λa a")]
     fn eval_module(#[case] input: &str, #[case] exp: &str) {
         let mut session = Session::new(None);
         session.eval_module(&parse("id = λx x").unwrap()).unwrap();
@@ -119,8 +703,583 @@ mod tests {
         session.eval_module(&parse("B = λb b").unwrap()).unwrap();
 
         let module = parse(input).unwrap();
-        let val = session.eval_module(&module).unwrap();
+        let val = session.eval_module(&module).unwrap().unwrap();
         println!("Input: {input}");
         assert_eq!(val.repr(), exp);
     }
+
+    #[rstest]
+    #[case(r"[]", "[]")]
+    #[case(r"[A]", "[A]")]
+    #[case(r"[A, B]", "[A, B]")]
+    #[case(r"[[], [A]]", "[[], [A]]")]
+    #[case(r"[id A, \x x]", "[id A, λx x]")]
+    #[case(r"(\a \b [b, a]) A B", "[B, A]")]
+    #[case(r"(\xs xs (\a \b b) (\a \b b)) [A, B]", "[B]")]
+    #[case::not_a_list(r"\c c A B", "λc c A B")]
+    #[case::not_a_list(r"\c c (\a \b b) A", "λc c (λa λb b) A")]
+    #[case::loop_item(r"[(\x x x) (\x x x)]", "[(λx x x) (λx x x)]")]
+    #[case::loop_tail(
+        r"\c c (\a \b b) ((\x x x) (\x x x))",
+        "λc c (λa λb b) ((λx x x) (λx x x))"
+    )]
+    fn repr_sugar_lists(#[case] input: &str, #[case] exp: &str) {
+        let mut session = Session::new(None);
+        session.eval_module(&parse("id = λx x").unwrap()).unwrap();
+        session.eval_module(&parse("A = λa a").unwrap()).unwrap();
+        session.eval_module(&parse("B = λb b").unwrap()).unwrap();
+        let module = parse(input).unwrap();
+        let val = session.eval_module(&module).unwrap().unwrap();
+        let sugar = Sugar {
+            lists: true,
+            ..Sugar::default()
+        };
+        assert_eq!(val.repr_with(sugar), exp);
+    }
+
+    #[rstest]
+    #[case(r#""hi""#, r#""hi""#)]
+    #[case(r#""a\"b\\c\n""#, r#""a\"b\\c\n""#)]
+    #[case(r#""é\u{3bb}""#, r#""éλ""#)]
+    #[case(r#""🦀 \u{1F980}""#, r#""🦀 🦀""#)]
+    #[case(r"['🦀']", r#""🦀""#)]
+    #[case(r"[(\n \a \b a (n a b)) 103]", r#""h""#)]
+    #[case(r"[104, 105]", r#""hi""#)]
+    #[case(r"[(\x x) 42]", r#""*""#)]
+    #[case::not_a_string(r#""""#, r"[]")]
+    #[case::not_a_string(r"[1, 2]", r"[1, 2]")]
+    #[case::not_a_string(r"[A]", r"[A]")]
+    fn repr_sugar_strings(#[case] input: &str, #[case] exp: &str) {
+        let mut session = Session::new(None);
+        session.eval_module(&parse("A = λa a").unwrap()).unwrap();
+        let module = parse(input).unwrap();
+        let val = session.eval_module(&module).unwrap().unwrap();
+        let sugar = Sugar {
+            lists: true,
+            strings: true,
+            ..Sugar::default()
+        };
+        assert_eq!(val.repr_with(sugar), exp);
+    }
+
+    /// Check that the value is the same as the code, up to names of arguments.
+    fn assert_same(val: &Value, exp: &str) {
+        let module = parse(exp).unwrap();
+        let Stmt::Expr { expr, .. } = &module.stmts[0] else {
+            panic!("bad statement")
+        };
+        let exp = Value::from_expr(expr);
+        assert!(val == &exp, "{} != {}", val.repr(), exp.repr());
+    }
+
+    #[test]
+    fn operators() {
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        let module = session.parse("infixl 6 +\ninfixl 7 *").unwrap();
+        assert!(session.eval_module(&module).unwrap().is_none());
+        let module = session.parse("+ = add\n* = mul").unwrap();
+        session.eval_module(&module).unwrap();
+        // The declarations from the previous inputs are still in effect.
+        let module = session.parse("eq (1 + 2 * 3) 7").unwrap();
+        let val = session.eval_module(&module).unwrap().unwrap();
+        assert_same(val, "λt λf t");
+        // And even if the module was parsed without the session,
+        // evaluating it teaches the session about the operators.
+        let module = parse("infixr 5 ++\n++ = add").unwrap();
+        session.eval_module(&module).unwrap();
+        let module = session.parse("eq (1 ++ 2 * 2) 5").unwrap();
+        let val = session.eval_module(&module).unwrap().unwrap();
+        assert_same(val, "λt λf t");
+    }
+
+    // The repr of a value with operators can be parsed back into the same value.
+    #[rstest]
+    #[case(r"(\x \y y x) (+)", "λy y (+)")]
+    #[case(r"\x + x", "λx + x")]
+    #[case(r"\x x + x", "λx + x x")]
+    #[case(r"\+ \x x (+)", "λ+ λx x (+)")]
+    #[case(r"[(+), A]", "[+, A]")]
+    fn repr_operators(#[case] input: &str, #[case] exp: &str) {
+        let mut session = Session::new(None);
+        session
+            .eval_source("infixl 6 +\n+ = λa λb a\nA = λa a", None)
+            .unwrap();
+        let val = session.eval_source(input, None).unwrap().unwrap().clone();
+        let sugar = Sugar {
+            lists: true,
+            ..Sugar::default()
+        };
+        let repr = val.repr_with(sugar);
+        assert_eq!(repr, exp);
+        let again = session.eval_source(&repr, None).unwrap().unwrap();
+        assert_eq!(again.repr_with(sugar), exp);
+    }
+
+    // A word with a dot right after it is a module name, not an argument,
+    // so `λa list.head a` is read back the same.
+    #[rstest]
+    #[case(r"\a (list.head a)", false, "λa list.head a")]
+    #[case(r"\a (list.head a)", true, "λa list.head a")]
+    #[case(r"\a \f (f list.head)", false, "λa λf f list.head")]
+    #[case(r"\a \f (f list.head)", true, "λa f. f list.head")]
+    #[case(r"\a (a list.head)", false, "λa a list.head")]
+    #[case(r"\a (a (list.head a))", false, "λa a (list.head a)")]
+    fn repr_qualified(#[case] input: &str, #[case] lambdas: bool, #[case] exp: &str) {
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        let val = session.eval_source(input, None).unwrap().unwrap().clone();
+        let sugar = Sugar {
+            lambdas,
+            ..Sugar::default()
+        };
+        let repr = val.repr_with(sugar);
+        assert_eq!(repr, exp);
+        let again = session.eval_source(&repr, None).unwrap().unwrap();
+        assert_eq!(again.repr_with(sugar), exp);
+    }
+
+    // With `1 = K`, the number `1` in the output would mean `K`,
+    // so the numeral is printed in full.
+    #[rstest]
+    #[case(Strategy::CallByName)]
+    #[case(Strategy::CallByNeed)]
+    fn repr_shadowed_numeral(#[case] strategy: Strategy) {
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        session.eval_source("1 = K", None).unwrap();
+        session.set_strategy(strategy);
+        let val = session
+            .eval_source("add 2 0", None)
+            .unwrap()
+            .unwrap()
+            .clone();
+        let repr = session.repr(&val, Sugar::default());
+        assert_eq!(repr, "λa λb a ((λa λb a b) inc 0 a b)");
+        assert_eq!(val.repr(), "λa λb a (1 inc 0 a b)");
+        let val = session.eval_source(&format!("eq ({repr}) 2"), None);
+        assert_same(val.unwrap().unwrap(), "λt λf t");
+    }
+
+    // Numerals are unfolded only as far as the evaluation needs,
+    // so big numbers are as cheap as small ones, see `Value::Numeral`.
+    #[rstest]
+    #[case("is_zero 99999999999", "λt λf f")]
+    #[case("is_zero (dec 50000)", "λt λf f")]
+    fn big_numerals(#[case] input: &str, #[case] exp: &str) {
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        let val = session.eval_source(input, None).unwrap().unwrap();
+        assert_same(val, exp);
+    }
+
+    #[test]
+    fn big_numeral_repr() {
+        let mut session = Session::new(None);
+        let val = session.eval_source("50000", None).unwrap().unwrap();
+        assert_eq!(val.as_nat(), Some(50000));
+        let repr = val.repr();
+        assert!(repr.starts_with("λa λb a (a ("));
+        assert_eq!(repr.matches("a (").count(), 49999);
+    }
+
+    #[test]
+    fn import() {
+        let dir = make_dir(
+            "import",
+            &[
+                (
+                    "main.rb",
+                    "import \"lib/ops.rb\"\nimport \"lib/ops.rb\"\neq (2 + 3) 5",
+                ),
+                (
+                    "lib/ops.rb",
+                    "import \"base.rb\"\ninfixl 6 +\n+ = plus\nexport +",
+                ),
+                ("lib/base.rb", "plus = add\nexport plus"),
+            ],
+        );
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        let val = session.eval_file(&dir.join("main.rb")).unwrap().unwrap();
+        assert_same(&val, "λt λf t");
+        // The names and the operators from the imported files are now in the session.
+        let val = session.eval_source("eq (plus 1 1 + 1) 3", None).unwrap();
+        assert_same(val.unwrap(), "λt λf t");
+    }
+
+    #[test]
+    fn import_once() {
+        let dir = make_dir("import_once", &[("x.rb", "x = y\nexport x")]);
+        let main = dir.join("main.rb").display().to_string();
+        let mut session = Session::new(None);
+        session
+            .eval_source("A = λa λb a\nB = λa λb b\nC = λc c\nD = λd d", None)
+            .unwrap();
+        session.eval_source("y = A\nexport y", None).unwrap();
+        session.eval_source("import \"x.rb\"", Some(&main)).unwrap();
+        session.eval_source("y = B\nexport y", None).unwrap();
+        // If `x.rb` were evaluated again, `x` would be `B`.
+        session
+            .eval_source("import \"./x.rb\"", Some(&main))
+            .unwrap();
+        let val = session.eval_source("x C D", None).unwrap().unwrap();
+        assert_eq!(val.repr(), "λc c");
+    }
+
+    #[test]
+    fn import_search_path() {
+        let dir = make_dir("import_search_path", &[("lib/a.rb", "A = λa a")]);
+        let mut session = Session::new(None);
+        let res = session.eval_source("import \"a.rb\"", None);
+        assert!(res.unwrap_err().to_string().contains("cannot find `a.rb`"));
+        session.add_search_path(&dir.join("lib"));
+        session.eval_source("import \"a.rb\"", None).unwrap();
+        let val = session.eval_source("a.A", None).unwrap().unwrap();
+        assert_eq!(val.repr(), "λa a");
+    }
+
+    #[test]
+    fn import_cycle() {
+        let dir = make_dir(
+            "import_cycle",
+            &[
+                ("a.rb", "import \"b.rb\"\nA = λa a"),
+                ("b.rb", "import \"a.rb\"\nB = λb b"),
+            ],
+        );
+        let mut session = Session::new(None);
+        let err = session.eval_file(&dir.join("a.rb")).unwrap_err();
+        let msg = format!("{err:?}");
+        assert!(msg.contains("import cycle: "), "{msg}");
+        assert!(msg.contains("a.rb -> "), "{msg}");
+    }
+
+    #[test]
+    fn import_syntax_errors() {
+        let dir = make_dir("import_syntax_errors", &[("a.rb", "A = \nB = ]")]);
+        let mut session = Session::new(None);
+        let main = dir.join("main.rb").display().to_string();
+        let err = session
+            .eval_source("import \"a.rb\"", Some(&main))
+            .unwrap_err();
+        let errors = err.downcast_ref::<SyntaxErrors>().unwrap();
+        assert_eq!(errors.errors.len(), 2);
+        assert!(errors.to_string().contains("a.rb:2:5"));
+    }
+
+    #[test]
+    fn namespaces() {
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        let val = session.eval_source("bool.not bool.true", None).unwrap();
+        assert_same(val.unwrap(), "λt λf f");
+        // Redefining an exported name doesn't affect the module that defines it.
+        session.eval_source("true = λx x", None).unwrap();
+        let val = session.eval_source("true", None).unwrap();
+        assert_eq!(val.unwrap().repr(), "λx x");
+        let val = session.eval_source("bool.true", None).unwrap();
+        assert_same(val.unwrap(), "λt λf t");
+        let val = session
+            .eval_source("not false (λa a) (λb b)", None)
+            .unwrap();
+        assert_eq!(val.unwrap().repr(), "λa a");
+        let val = session.eval_source("main.true", None).unwrap();
+        assert_eq!(val.unwrap().repr(), "λx x");
+    }
+
+    #[test]
+    fn import_namespace() {
+        let dir = make_dir(
+            "import_namespace",
+            &[("lib.rb", "A = λa a\nB = λb b\nexport A")],
+        );
+        let main = dir.join("main.rb").display().to_string();
+        let mut session = Session::new(None);
+        session
+            .eval_source("import \"lib.rb\"", Some(&main))
+            .unwrap();
+        let val = session.eval_source("A", None).unwrap().unwrap();
+        assert_eq!(val.repr(), "λa a");
+        let val = session.eval_source("lib.B", None).unwrap().unwrap();
+        assert_eq!(val.repr(), "λb b");
+        let err = session.eval_source("B", None).unwrap_err();
+        assert!(err.to_string().contains("variable `B` is not defined"));
+        let err = session.eval_source("export C", None).unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("cannot export `C`"), "{msg}");
+        assert!(msg.contains("module `main`"), "{msg}");
+    }
+
+    #[test]
+    fn import_namespace_collision() {
+        let dir = make_dir(
+            "import_namespace_collision",
+            &[("nat.rb", "x = λx x"), ("bad.rb", "x = λx x\ny")],
+        );
+        let main = dir.join("main.rb").display().to_string();
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        let err = session
+            .eval_source("import \"nat.rb\"", Some(&main))
+            .unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("module `nat` already exists"), "{msg}");
+        // A module that failed to import can be imported again once it's fixed.
+        let res = session.eval_source("import \"bad.rb\"", Some(&main));
+        assert!(res.is_err());
+        fs::write(dir.join("bad.rb"), "x = λx x").unwrap();
+        session
+            .eval_source("import \"bad.rb\"", Some(&main))
+            .unwrap();
+        let val = session.eval_source("bad.x", None).unwrap().unwrap();
+        assert_eq!(val.repr(), "λx x");
+    }
+
+    #[test]
+    fn dialect() {
+        let dir = make_dir(
+            "dialect",
+            &[("k.rb", "# dialect: standard\nK = \\a \\b a\nexport K")],
+        );
+        let main = dir.join("main.rb").display().to_string();
+        let mut session = Session::new(None);
+        session.set_dialect(Dialect::Dot);
+        session.eval_source("import \"k.rb\"", Some(&main)).unwrap();
+        let val = session.eval_source("K(λa.a)(λab.b)", None).unwrap();
+        assert_eq!(val.unwrap().repr(), "λa a");
+        let err = session.eval_source("λa a", None).unwrap_err();
+        assert!(err.to_string().contains("expected `.`"));
+    }
+
+    #[test]
+    fn nat_can_be_redefined() {
+        let mut session = Session::new(None);
+        session.eval_module(&parse("3 = λa a").unwrap()).unwrap();
+        let module = parse("3").unwrap();
+        let val = session.eval_module(&module).unwrap().unwrap();
+        assert_eq!(val.repr(), "λa a");
+        let module = parse("(\\x x) 3").unwrap();
+        let val = session.eval_module(&module).unwrap().unwrap();
+        assert_eq!(val.repr(), "λa a");
+    }
+
+    #[test]
+    fn let_in_is_local() {
+        let mut session = Session::new(None);
+        session.eval_module(&parse("id = λx x").unwrap()).unwrap();
+        let module = parse("let x = id in x").unwrap();
+        session.eval_module(&module).unwrap();
+        let module = parse("x").unwrap();
+        assert!(session.eval_module(&module).is_err());
+    }
+
+    #[rstest]
+    #[case("λ λ 2", "λa λb a")]
+    #[case("(λ λ 2) λ 1", "λb λa a")]
+    #[case("not true", "λa λb b")]
+    #[case("λ nat.inc 1", "λa nat.inc a")]
+    fn eval_debruijn(#[case] input: &str, #[case] exp: &str) {
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        let val = session.eval_debruijn(input).unwrap();
+        assert_eq!(val.repr(), exp);
+    }
+
+    #[rstest]
+    #[case("λ 2", "index 2 doesn't point to any lambda")]
+    #[case("λ nope 1", "variable `nope` is not defined")]
+    fn eval_debruijn_err(#[case] input: &str, #[case] exp: &str) {
+        let mut session = Session::new(None);
+        let err = session.eval_debruijn(input).unwrap_err();
+        assert!(err.to_string().contains(exp), "{err}");
+    }
+
+    #[rstest]
+    #[case(r"foo = \x bar x", &["undefined name `bar` in `foo` at 1:10"])]
+    #[case(r"foo = \x \y x y", &[])]
+    #[case(r"foo = let a = b in a", &["undefined name `b` in `foo` at 1:15"])]
+    #[case(r"foo = a (\a a) a", &["undefined name `a` in `foo` at 1:7"])]
+    #[case(r"foo = nat.nope (x 2)", &[
+        "undefined name `nat.nope` in `foo` at 1:7",
+        "undefined name `x` in `foo` at 1:17",
+    ])]
+    #[case(r"foo = \x not x", &[])]
+    #[case(r"foo = foo", &["undefined name `foo` in `foo` at 1:7"])]
+    fn undefined_names(#[case] input: &str, #[case] exp: &[&str]) {
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        session.eval_source(input, None).unwrap();
+        assert_eq!(session.take_warnings(), exp);
+        assert!(session.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn undefined_names_mode() {
+        let mut session = Session::new(None);
+        session.set_undefined_names(UndefinedNames::Error);
+        // The stdlib itself has no undefined names.
+        session.load_stdlib().unwrap();
+        let err = session
+            .eval_source(r"foo = \x bar (baz x)", None)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "undefined name `bar` in `foo` at 1:10\nundefined name `baz` in `foo` at 1:15"
+        );
+        assert!(session.eval_source("foo", None).is_err());
+        assert!(session.take_warnings().is_empty());
+        // A qualified name right after an argument is a name, not more arguments.
+        let err = session
+            .eval_source(r"foo = \x nta.add x 1", None)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "undefined name `nta.add` in `foo` at 1:10");
+        session.eval_source(r"foo = \x nat.add x 1", None).unwrap();
+        let val = session.eval_source("eq (foo 2) 3", None).unwrap().unwrap();
+        assert_same(val, "λt λf t");
+
+        session.set_undefined_names(UndefinedNames::Ignore);
+        session.eval_source(r"foo = \x bar x", None).unwrap();
+        assert!(session.take_warnings().is_empty());
+        let err = session.eval_source("foo 1", None).unwrap_err();
+        assert_eq!(
+            err.root_cause().to_string(),
+            "unbound variable `bar` at 1:10"
+        );
+    }
+
+    #[test]
+    fn lint() {
+        let dir = make_dir(
+            "lint",
+            &[("ops.rb", "infixl 6 <+>\n<+> = \\a \\b a\nexport <+>")],
+        );
+        let path = dir.join("main.rb").display().to_string();
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        let input = "import \"ops.rb\"\nf = \\true \\x true <+> x\nf";
+        let diags = session.lint(input, Some(&path)).unwrap();
+        let diags: Vec<_> = diags.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            diags,
+            [format!("{path}:2:6: shadowed-global: argument `true` shadows the global with the same name")]
+        );
+        // The source itself is not evaluated.
+        assert!(session.eval_source("f", None).is_err());
+        assert!(session.lint("", None).unwrap().is_empty());
+        let err = session.lint("f = ", None).unwrap_err();
+        assert!(err.downcast_ref::<SyntaxErrors>().is_some());
+    }
+
+    // Only the names visible in the module are redefined, and the names
+    // a stdlib module exports itself are not visible to it when linted.
+    #[test]
+    fn lint_redefined() {
+        let dir = make_dir("lint_redefined", &[("ops.rb", "plus = add\nexport plus")]);
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        let path = dir.join("main.rb").display().to_string();
+        let input = "import \"ops.rb\"\nplus = add\ntrue = K";
+        let diags = session.lint(input, Some(&path)).unwrap();
+        let diags: Vec<_> = diags.iter().map(|diag| diag.message.clone()).collect();
+        assert_eq!(
+            diags,
+            [
+                "`plus` is already defined in module `ops`, the code defined before keeps using the old definition",
+                "`true` is already defined in module `bool`, the code defined before keeps using the old definition",
+            ]
+        );
+
+        let source = include_str!("../stdlib/nat.rb");
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        let diags = session.lint(source, Some("src/stdlib/nat.rb")).unwrap();
+        assert!(diags
+            .iter()
+            .all(|diag| diag.kind != LintKind::RedefinedGlobal));
+        // The same code in a module with another name does redefine them.
+        let diags = session.lint(source, Some("numbers.rb")).unwrap();
+        assert!(diags
+            .iter()
+            .any(|diag| diag.kind == LintKind::RedefinedGlobal));
+    }
+
+    // The global `b` in the result must not be captured by the argument `b`.
+    #[rstest]
+    #[case(r"(\a \b a) b", "λb1 b", "λ λ 1")]
+    #[case(r"(\a \b a b) b", "λb1 b b1", "λ (λ 1) 1")]
+    #[case(r"(\a \b \b1 a b) b", "λb1 λb11 b b1", "λ λ (λ 1) 2")]
+    #[case(r"(\a \b \c a) b", "λb1 λc b", "λ λ λ 1")]
+    #[case(r"(\a \b b a) (\b b)", "λb b (λb b)", "λ 1 (λ 1)")]
+    #[case(r"(\a \b a) c", "λb c", "λ c")]
+    fn capture_avoiding(#[case] input: &str, #[case] exp: &str, #[case] exp_debruijn: &str) {
+        let mut session = Session::new(None);
+        session.eval_source("b = λx x", None).unwrap();
+        let val = session.eval_source(input, None).unwrap().unwrap().clone();
+        assert_eq!(val.repr(), exp);
+        assert_eq!(val.to_debruijn().to_string(), exp_debruijn);
+        // The output means the same as the value.
+        let again = session.eval_source(exp, None).unwrap().unwrap();
+        assert_eq!(again.repr(), exp);
+        assert!(again == &val);
+    }
+
+    #[test]
+    fn names_of() {
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        let val = session
+            .eval_source("not true", None)
+            .unwrap()
+            .unwrap()
+            .clone();
+        assert_eq!(session.names_of(&val), ["false"]);
+        session.eval_source("no = λx λy y", None).unwrap();
+        assert_eq!(session.names_of(&val), ["false", "no"]);
+        // The name in the current module hides the exported one.
+        session.eval_source("false = λx x", None).unwrap();
+        assert_eq!(session.names_of(&val), ["no"]);
+        let val = session
+            .eval_source("λx x x", None)
+            .unwrap()
+            .unwrap()
+            .clone();
+        assert!(session.names_of(&val).is_empty());
+    }
+
+    // That's what the REPL does after each result, so it must not choke
+    // on big literals, see `names_of`.
+    #[test]
+    fn names_of_big_numeral() {
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        session.eval_source("crab = '🦀'", None).unwrap();
+        let val = session.eval_source("'🦀'", None).unwrap().unwrap().clone();
+        assert_eq!(session.names_of(&val), ["crab"]);
+        let val = session
+            .eval_source("200000", None)
+            .unwrap()
+            .unwrap()
+            .clone();
+        assert!(session.names_of(&val).is_empty());
+    }
+
+    #[test]
+    fn eval_comments_only() {
+        let mut session = Session::new(None);
+        let module = parse("# nothing to see here").unwrap();
+        assert!(session.eval_module(&module).unwrap().is_none());
+    }
+
+    #[rstest]
+    #[case(r"x", "variable `x` is not defined at 1:1")]
+    #[case(r"id x", "unbound variable `x` at 1:4")]
+    #[case("id\n(\\a a) y", "unbound variable `y` at 2:8")]
+    fn eval_error_location(#[case] input: &str, #[case] exp: &str) {
+        let mut session = Session::new(None);
+        session.eval_module(&parse("id = λx x").unwrap()).unwrap();
+        let module = parse(input).unwrap();
+        let err = session.eval_module(&module).unwrap_err();
+        assert_eq!(err.root_cause().to_string(), exp);
+    }
 }
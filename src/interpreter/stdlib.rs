@@ -1,5 +1,6 @@
 use crate::ast_nodes::Module;
-use crate::parse;
+use crate::fixity::Fixities;
+use crate::parser::{parse_source, Dialect};
 use anyhow::Context;
 use include_dir::{include_dir, Dir};
 
@@ -9,30 +10,43 @@ use include_dir::{include_dir, Dir};
 static STDLIB_DIR: Dir = include_dir!("./src/stdlib");
 
 /// Read and parse all stdlib modules.
-pub fn read_stdlib() -> anyhow::Result<Vec<Module>> {
-    let mut modules: Vec<Module> = Vec::new();
+///
+/// Each module is returned together with its name, which is the file name
+/// without the extension. So, the names from `nat.rb` live in the `nat` namespace.
+///
+/// Fixity declarations from the stdlib are added to the given table,
+/// so that the operators can be used in the user code.
+pub fn read_stdlib(fixities: &mut Fixities) -> anyhow::Result<Vec<(String, Module)>> {
+    let mut modules: Vec<(String, Module)> = Vec::new();
     for file in STDLIB_DIR.files() {
         let content = file.contents_utf8().unwrap();
-        let emsg = format!("failed to parse {:?} module", file.path());
-        let module = parse(content).context(emsg)?;
-        modules.push(module);
+        // The path is relative to the stdlib directory, so we prefix it
+        // to make it clear in error messages where the file comes from.
+        let path = format!("stdlib/{}", file.path().display());
+        let emsg = format!("failed to parse {path} module");
+        let module =
+            parse_source(content, Some(&path), fixities, Dialect::Standard).context(emsg)?;
+        let name = file.path().file_stem().unwrap().to_string_lossy();
+        modules.push((name.to_string(), module));
     }
     Ok(modules)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::interpreter::Session;
+    use crate::interpreter::{Session, Strategy};
     use crate::parse;
     use rstest::rstest;
 
-    const T: &str = "λa λb a";
-    const F: &str = "λa λb b";
-    const ONE: &str = "λa a";
-    const TWO: &str = "λa λb a (a b)";
+    // Results are compared up to names of arguments,
+    // so the names don't have to match the ones in the stdlib.
+    const T: &str = "λt λf t";
+    const F: &str = "λt λf f";
+    const ONE: &str = "λf λx f x";
+    const TWO: &str = "λf λx f (f x)";
 
     #[rstest]
-    #[case::id(r"id", "λa a")]
+    #[case::id(r"id", "λx x")]
     // bool
     #[case::true_repr(r"true", T)]
     #[case::false_repr(r"false", F)]
@@ -54,22 +68,6 @@ mod tests {
     #[case::xnor(r"xnor false true", F)]
     #[case::xnor(r"xnor true false", F)]
     #[case::xnor(r"xnor true true", T)]
-    // nat operations
-    #[case::inc(r"eq (inc 0) 1", T)]
-    #[case::inc(r"eq (inc 2) 3", T)]
-    #[case::add(r"eq (add 2 3) 5", T)]
-    #[case::mul(r"eq (mul 2 3) 6", T)]
-    #[case::pow(r"eq (pow 3 2) 9", T)]
-    #[case::dec(r"eq (dec 3) 2", T)]
-    #[case::dec(r"eq (dec 0) 0", T)]
-    #[case::sub(r"eq (sub 5 3) 2", T)]
-    #[case::sub(r"eq (sub 3 5) 0", T)]
-    #[case::diff(r"eq (diff 5 3) 2", T)]
-    #[case::diff(r"eq (diff 3 5) 2", T)]
-    #[case::min(r"eq (min 3 5) 3", T)]
-    #[case::min(r"eq (min 5 3) 3", T)]
-    #[case::max(r"eq (max 3 5) 5", T)]
-    #[case::max(r"eq (max 5 3) 5", T)]
     // nat comparison
     #[case::is_zero(r"is_zero 0", T)]
     #[case::is_zero(r"is_zero 1", F)]
@@ -88,15 +86,8 @@ mod tests {
     #[case::gte(r"gte 2 5", F)]
     #[case::gte(r"gte 5 2", T)]
     #[case::gte(r"gte 2 2", T)]
-    // rec
-    #[case::fac(r"eq (fac 2) 2", T)]
-    #[case::fac(r"eq (fac 3) 6", T)]
-    // #[case::fac(r#"eq (fac 4) (mul 6 4)"#, T)]
-    #[case::fib(r"eq (fib 1) 1", T)]
-    #[case::fib(r"eq (fib 2) 1", T)]
-    #[case::fib(r"eq (fib 3) 2", T)]
-    #[case::fib(r"eq (fib 4) 3", T)]
-    #[case::fib(r"eq (fib 5) 5", T)]
+    // nat literals
+    #[case::nat(r"lt 14 15", T)]
     // pair
     #[case::car(r"car (cons 1 2)", ONE)]
     #[case::cdr(r"cdr (cons 1 2)", TWO)]
@@ -106,12 +97,71 @@ mod tests {
     #[case::head(r"head (prepend empty_list 2)", TWO)]
     #[case::tail(r"is_empty (tail (prepend empty_list 2))", T)]
     #[case(r"head (prepend (prepend empty_list 3) 2)", TWO)]
+    // list literals
+    #[case::literal(r"is_empty []", T)]
+    #[case::literal(r"is_empty [1]", F)]
+    #[case::literal(r"head [2, 1]", TWO)]
+    #[case::literal(r"head (tail [1, 2])", TWO)]
+    #[case::literal(r"is_empty (tail [1])", T)]
+    #[case::literal(r"head (prepend [1] 2)", TWO)]
+    // string and char literals
+    #[case::string(r#"is_empty """#, T)]
+    #[case::string(r#"head "\u{2}\u{1}""#, TWO)]
+    #[case::string(r#"head (tail "\u{1}\u{2}")"#, TWO)]
+    #[case::char(r"'\u{2}'", TWO)]
+    #[case::char(r"is_zero '\0'", T)]
+    #[case::char(r"is_zero ' '", F)]
     fn stdlib(#[case] input: &str, #[case] exp: &str) {
         let mut session = Session::new(None);
         session.load_stdlib().unwrap();
         let module = parse(input).unwrap();
-        let val = session.eval_module(&module).unwrap();
-        println!("Input: {input}");
-        assert_eq!(val.repr(), exp);
+        let val = session.eval_module(&module).unwrap().unwrap().clone();
+        let exp = session.eval_source(exp, None).unwrap().unwrap();
+        assert!(&val == exp, "{input}: {} != {}", val.repr(), exp.repr());
+    }
+
+    // With normal order, results are reduced to the normal form,
+    // so they can be compared directly, without `eq`.
+    #[rstest]
+    // nat operations
+    #[case::inc(r"inc 0", "1")]
+    #[case::inc(r"inc 2", "3")]
+    #[case::add(r"add 2 3", "5")]
+    #[case::mul(r"mul 2 3", "6")]
+    #[case::pow(r"pow 3 2", "9")]
+    #[case::dec(r"dec 3", "2")]
+    #[case::dec(r"dec 0", "0")]
+    #[case::sub(r"sub 5 3", "2")]
+    #[case::sub(r"sub 3 5", "0")]
+    #[case::diff(r"diff 5 3", "2")]
+    #[case::diff(r"diff 3 5", "2")]
+    #[case::min(r"min 3 5", "3")]
+    #[case::min(r"min 5 3", "3")]
+    #[case::max(r"max 3 5", "5")]
+    #[case::max(r"max 5 3", "5")]
+    // rec
+    #[case::fac(r"fac 2", "2")]
+    #[case::fac(r"fac 3", "6")]
+    #[case::fac(r"fac 4", "24")]
+    #[case::fib(r"fib 1", "1")]
+    #[case::fib(r"fib 2", "1")]
+    #[case::fib(r"fib 3", "2")]
+    #[case::fib(r"fib 4", "3")]
+    #[case::fib(r"fib 5", "5")]
+    // nat literals
+    #[case::nat(r"add 10 3", "13")]
+    #[case::nat(r"mul 4 3", "12")]
+    // pair and list
+    #[case::cdr(r"cdr (cons 1 2)", "2")]
+    #[case::tail(r"tail [1, 2, 3]", "[2, 3]")]
+    #[case::prepend(r"prepend [2] 1", "[1, 2]")]
+    #[case::string(r#"tail "abc""#, r#""bc""#)]
+    fn normal_form(#[case] input: &str, #[case] exp: &str) {
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        session.set_strategy(Strategy::NormalOrder);
+        let val = session.eval_source(input, None).unwrap().unwrap().clone();
+        let exp = session.eval_source(exp, None).unwrap().unwrap();
+        assert!(&val == exp, "{input}: {} != {}", val.repr(), exp.repr());
     }
 }
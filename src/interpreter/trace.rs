@@ -0,0 +1,557 @@
+//! Step-by-step reduction, to see how exactly a term is evaluated.
+//!
+//! The tracer runs the same reducer as `Value::reduce` (see `reduce.rs`),
+//! but after each step it prints the term as it was before the step,
+//! with the redex that was reduced highlighted. So, `not true` is traced as:
+//!
+//! ```text
+//!    not true
+//! →δ (λa a false true) true
+//! →β true false true
+//! →δ (λa λb a) false true
+//! →β (λb false) true
+//! →β false
+//! →δ λa λb b
+//! ```
+//!
+//! Each line starts with the kind of the step that led to it:
+//! β (beta) is a call of a lambda, δ (delta) is replacing a global name
+//! with its value, and η (eta) is replacing `λx f x` with `f`.
+//!
+//! Most of the steps are usually inside of the stdlib functions, and they aren't
+//! very interesting. These steps can be hidden, see `SkipGlobals`.
+//! Hidden steps are still done, but the next line starts with `↠`
+//! and says how many steps it took to get there.
+use crate::interpreter::reduce::{arg_name, gave_up, to_node, to_value, Dir, Node, Reducer};
+use crate::interpreter::value::is_operator;
+use crate::interpreter::{Strategy, Value};
+use colored::Colorize;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+
+/// Which steps the tracer should hide.
+///
+/// A step is inside of a global if the redex came from the value
+/// of the global, not from the traced expression itself.
+/// For example, if `not` is skipped, `not true` is traced as:
+///
+/// ```text
+///    not true
+/// →δ (λa a false true) true
+/// →β true false true
+/// →δ (λa λb a) false true
+/// ↠  λa λb b  # 3 steps
+/// ```
+///
+/// Here, `true` is expanded because it's written in the expression,
+/// but the calls it's passed to come from `not`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SkipGlobals {
+    /// Show all steps.
+    #[default]
+    Nothing,
+    /// Hide steps inside of all globals.
+    All,
+    /// Hide steps inside of the globals with these names.
+    Only(HashSet<String>),
+}
+
+impl SkipGlobals {
+    /// Check if the steps inside of the global with the given name are hidden.
+    fn skips(&self, name: &str) -> bool {
+        match self {
+            SkipGlobals::Nothing => false,
+            SkipGlobals::All => true,
+            SkipGlobals::Only(names) => names.contains(name),
+        }
+    }
+}
+
+/// What kind of reduction a step does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepKind {
+    /// Calling a lambda: `(λx body) arg`.
+    Beta,
+    /// Replacing a global name with its value.
+    Delta,
+    /// Replacing `λx f x` with `f`.
+    Eta,
+}
+
+impl StepKind {
+    /// The Greek letter for the step.
+    #[must_use]
+    pub fn letter(self) -> char {
+        match self {
+            StepKind::Beta => 'β',
+            StepKind::Delta => 'δ',
+            StepKind::Eta => 'η',
+        }
+    }
+}
+
+/// How the term on a line of the trace was reached from the term on the line before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reached {
+    /// It's the term we started with, there is no line before.
+    Start,
+    /// One step of the given kind.
+    Step(StepKind),
+    /// Several hidden steps.
+    Steps(usize),
+}
+
+/// One line of the trace: the term between two steps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceLine {
+    /// How this term was reached from the term on the line before.
+    pub reached: Reached,
+    /// The term, without any syntactic sugar.
+    pub term: String,
+    /// Where in the `term` is the redex that is reduced next, as byte offsets.
+    /// It's `None` for the last line and for lines before a hidden step.
+    pub redex: Option<Range<usize>>,
+}
+
+impl TraceLine {
+    /// The line with the redex highlighted and colors, for the terminal.
+    #[must_use]
+    pub fn to_colored_string(&self) -> String {
+        let term = match &self.redex {
+            Some(redex) => format!(
+                "{}{}{}",
+                &self.term[..redex.start],
+                self.term[redex.clone()].yellow().underline(),
+                &self.term[redex.end..]
+            ),
+            None => self.term.clone(),
+        };
+        match self.reached {
+            Reached::Steps(n) => format!("↠  {term}  {}", format!("# {n} steps").dimmed()),
+            _ => format!("{} {term}", self.arrow()),
+        }
+    }
+
+    /// The arrow at the start of the line, always 2 characters wide.
+    fn arrow(&self) -> String {
+        match self.reached {
+            Reached::Start => "  ".to_string(),
+            Reached::Step(kind) => format!("→{}", kind.letter()),
+            Reached::Steps(_) => "↠ ".to_string(),
+        }
+    }
+}
+
+/// Without colors, the redex is wrapped into `[[` and `]]`.
+impl fmt::Display for TraceLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ", self.arrow())?;
+        match &self.redex {
+            Some(redex) => write!(
+                f,
+                "{}[[{}]]{}",
+                &self.term[..redex.start],
+                &self.term[redex.clone()],
+                &self.term[redex.end..]
+            )?,
+            None => write!(f, "{}", self.term)?,
+        }
+        if let Reached::Steps(n) = self.reached {
+            write!(f, "  # {n} steps")?;
+        }
+        Ok(())
+    }
+}
+
+/// Everything `Value::trace` needs to know besides the value.
+pub(crate) struct TraceOptions<'a> {
+    pub strategy: Strategy,
+    /// Do η-reduction, see `Value::reduce`.
+    pub eta: bool,
+    /// How many steps to do, including hidden ones, before giving up.
+    pub max_steps: usize,
+    pub skip: &'a SkipGlobals,
+    /// How many steps to show before stopping.
+    pub limit: Option<usize>,
+}
+
+impl Value {
+    /// Reduce the value step by step, calling `on_line` for each line of the trace.
+    ///
+    /// Returns the result of the reduction, or `None` if it was stopped
+    /// because there were more steps to show than the limit.
+    pub(crate) fn trace(
+        &self,
+        options: &TraceOptions,
+        on_line: &mut dyn FnMut(TraceLine),
+    ) -> anyhow::Result<Option<Value>> {
+        let mut reducer = Reducer::new(options.strategy, options.eta);
+        let mut node = to_node(self, &mut Vec::new(), &None);
+        // How many steps were shown (their redex highlighted).
+        let mut shown = 0;
+        // How many steps were done since the last line.
+        let mut steps = 0;
+        let mut last_kind = StepKind::Beta;
+        // If the last step was shown, the result of it is shown as well.
+        let mut last_shown = true;
+        for _ in 0..options.max_steps {
+            // The line should show the term before the step, and we know
+            // what the redex is only after the step. So we keep a copy.
+            let before = node.clone();
+            let heap = reducer.heap.clone();
+            let reached = match steps {
+                0 => Reached::Start,
+                1 => Reached::Step(last_kind),
+                n => Reached::Steps(n),
+            };
+            if !reducer.step(&mut node)? {
+                let (term, _) = render(&node, &reducer.heap, None);
+                on_line(TraceLine {
+                    reached,
+                    term,
+                    redex: None,
+                });
+                return Ok(Some(to_value(&node, &reducer.heap, &mut Vec::new())));
+            }
+            let (kind, origin) = find_redex(&before, &heap, &reducer.path);
+            let hidden = origin.is_some_and(|name| options.skip.skips(name));
+            // The term is shown if the redex is, or if it's the result of a shown step.
+            // So, the term we started with is always shown.
+            if !hidden || last_shown {
+                let path = if hidden {
+                    None
+                } else {
+                    Some(&reducer.path[..])
+                };
+                let (term, redex) = render(&before, &heap, path);
+                on_line(TraceLine {
+                    reached,
+                    term,
+                    redex,
+                });
+                steps = 0;
+                if !hidden {
+                    if options.limit.is_some_and(|limit| shown >= limit) {
+                        return Ok(None);
+                    }
+                    shown += 1;
+                }
+            }
+            steps += 1;
+            last_kind = kind;
+            last_shown = !hidden;
+        }
+        Err(gave_up(options.strategy, options.max_steps))
+    }
+}
+
+/// Find the redex by its path, and tell what kind of step reduces it
+/// and what global it comes from.
+///
+/// For β and δ, the redex itself knows where it comes from. For η, it's the origin
+/// of the innermost call that the lambda is part of.
+fn find_redex<'a>(
+    mut node: &'a Node,
+    heap: &'a [Node],
+    path: &[Dir],
+) -> (StepKind, Option<&'a str>) {
+    let mut origin = None;
+    for dir in path {
+        if let Node::App(_, _, from) = node {
+            origin = from.as_deref();
+        }
+        node = match (node, dir) {
+            (Node::Lam(_, body), Dir::Body) => body,
+            (Node::App(target, ..), Dir::Target) => target,
+            (Node::App(_, arg, _), Dir::Arg) => arg,
+            (Node::Shared(idx), Dir::Shared) => &heap[*idx],
+            _ => unreachable!("the path doesn't match the term"),
+        };
+    }
+    match node {
+        Node::App(_, _, from) => (StepKind::Beta, from.as_deref()),
+        Node::Global(_, _, from) | Node::Numeral(_, from) => (StepKind::Delta, from.as_deref()),
+        Node::Lam(..) => (StepKind::Eta, origin),
+        _ => unreachable!("the path doesn't point to a redex"),
+    }
+}
+
+/// Print the term, and tell where in the output is the node at the end of the path.
+fn render(node: &Node, heap: &[Node], path: Option<&[Dir]>) -> (String, Option<Range<usize>>) {
+    let mut printer = Printer {
+        heap,
+        out: String::new(),
+        redex: None,
+    };
+    printer.print(node, &mut Vec::new(), path);
+    (printer.out, printer.redex)
+}
+
+/// Prints nodes the same way as `repr` prints values, but without any sugar.
+struct Printer<'a> {
+    heap: &'a [Node],
+    out: String,
+    /// Where in the output is the node at the end of the path.
+    redex: Option<Range<usize>>,
+}
+
+impl Printer<'_> {
+    /// Print the node with names of arguments of all lambdas around it.
+    ///
+    /// The path, if given, is the path from this node to the redex.
+    fn print(&mut self, node: &Node, names: &mut Vec<String>, path: Option<&[Dir]>) {
+        let start = self.out.len();
+        match node {
+            Node::Var(idx) => self.out.push_str(&names[names.len() - idx]),
+            Node::Free(name) | Node::Global(name, ..) => self.out.push_str(name),
+            Node::Numeral(n, _) => self.out.push_str(&n.to_string()),
+            // Shared arguments are closed, the names around don't matter.
+            Node::Shared(idx) => {
+                self.print(&self.heap[*idx], &mut Vec::new(), child(path, Dir::Shared));
+            }
+            Node::Lam(arg, body) => {
+                let name = arg_name(arg, body, self.heap, names);
+                self.out.push('λ');
+                self.out.push_str(&name);
+                self.out.push(' ');
+                names.push(name);
+                self.print(body, names, child(path, Dir::Body));
+                names.pop();
+            }
+            Node::App(target, arg, _) => {
+                // Calls are left-associative, so only a lambda needs parenthesis
+                // as a target, but both a lambda and a call need them as an argument.
+                let target_braces = matches!(self.resolve(target), Node::Lam(..));
+                self.print_wrapped(target, names, child(path, Dir::Target), target_braces);
+                self.out.push(' ');
+                // An operator needs them as an argument too, the same as in `repr`.
+                let arg_braces = match self.resolve(arg) {
+                    Node::Lam(..) | Node::App(..) => true,
+                    Node::Var(idx) => is_operator(&names[names.len() - idx]),
+                    Node::Free(name) | Node::Global(name, ..) => is_operator(name),
+                    Node::Numeral(..) | Node::Shared(_) => false,
+                };
+                self.print_wrapped(arg, names, child(path, Dir::Arg), arg_braces);
+            }
+        }
+        if path.is_some_and(<[Dir]>::is_empty) {
+            self.redex = Some(start..self.out.len());
+        }
+    }
+
+    fn print_wrapped(
+        &mut self,
+        node: &Node,
+        names: &mut Vec<String>,
+        path: Option<&[Dir]>,
+        braces: bool,
+    ) {
+        if braces {
+            self.out.push('(');
+        }
+        self.print(node, names, path);
+        if braces {
+            self.out.push(')');
+        }
+    }
+
+    /// Follow the shared argument, if the node is one, to see what it looks like.
+    fn resolve<'n>(&'n self, mut node: &'n Node) -> &'n Node {
+        while let Node::Shared(idx) = node {
+            node = &self.heap[*idx];
+        }
+        node
+    }
+}
+
+/// The rest of the path if it goes in the given direction.
+fn child(path: Option<&[Dir]>, dir: Dir) -> Option<&[Dir]> {
+    match path? {
+        [first, rest @ ..] if *first == dir => Some(rest),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Session;
+    use rstest::rstest;
+
+    fn trace(
+        input: &str,
+        strategy: Strategy,
+        skip: &SkipGlobals,
+        limit: Option<usize>,
+    ) -> (Vec<String>, bool) {
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        session.set_strategy(strategy);
+        let mut lines = Vec::new();
+        let res = session
+            .trace(input, None, skip, limit, &mut |line| {
+                lines.push(line.to_string());
+            })
+            .unwrap();
+        (lines, res.is_some())
+    }
+
+    #[test]
+    fn not_true() {
+        let (lines, done) = trace(
+            "not true",
+            Strategy::CallByName,
+            &SkipGlobals::Nothing,
+            None,
+        );
+        assert!(done);
+        assert_eq!(
+            lines,
+            vec![
+                "   [[not]] true",
+                "→δ [[(λa a false true) true]]",
+                "→β [[true]] false true",
+                "→δ [[(λa λb a) false]] true",
+                "→β [[(λb false) true]]",
+                "→β [[false]]",
+                "→δ λa λb b",
+            ]
+        );
+    }
+
+    #[test]
+    fn skip_globals() {
+        let skip = SkipGlobals::Only(HashSet::from(["not".to_string()]));
+        let (lines, _) = trace("not true", Strategy::CallByName, &skip, None);
+        assert_eq!(
+            lines,
+            vec![
+                "   [[not]] true",
+                "→δ [[(λa a false true) true]]",
+                "→β [[true]] false true",
+                "→δ (λa λb a) false true",
+                "↠  λa λb b  # 3 steps",
+            ]
+        );
+        // Calls written in the expression are shown even inside of arguments.
+        let (lines, _) = trace(
+            "I (not true)",
+            Strategy::CallByName,
+            &SkipGlobals::All,
+            None,
+        );
+        assert_eq!(
+            lines,
+            vec![
+                "   [[I]] (not true)",
+                "→δ [[(λa a) (not true)]]",
+                "→β [[not]] true",
+                "→δ [[(λa a false true) true]]",
+                "→β [[true]] false true",
+                "→δ (λa λb a) false true",
+                "↠  λa λb b  # 3 steps",
+            ]
+        );
+    }
+
+    #[test]
+    fn hidden_steps() {
+        let mut session = Session::new(None);
+        session.load_stdlib().unwrap();
+        session.eval_source("two_ids = I I", None).unwrap();
+        let mut lines = Vec::new();
+        session
+            .trace("two_ids", None, &SkipGlobals::All, None, &mut |line| {
+                lines.push(line.to_string());
+            })
+            .unwrap();
+        assert_eq!(
+            lines,
+            vec!["   [[two_ids]]", "→δ I I", "↠  λa a  # 3 steps"]
+        );
+    }
+
+    #[test]
+    fn limit() {
+        let (lines, done) = trace(
+            "not true",
+            Strategy::CallByName,
+            &SkipGlobals::Nothing,
+            Some(2),
+        );
+        assert!(!done);
+        assert_eq!(
+            lines,
+            vec![
+                "   [[not]] true",
+                "→δ [[(λa a false true) true]]",
+                "→β [[true]] false true",
+            ]
+        );
+        let (lines, done) = trace(
+            "not true",
+            Strategy::CallByName,
+            &SkipGlobals::Nothing,
+            Some(0),
+        );
+        assert!(!done);
+        assert_eq!(lines, vec!["   [[not]] true"]);
+    }
+
+    // `list.head` is a qualified name, not an argument and a dot.
+    #[test]
+    fn qualified_body() {
+        let (lines, _) = trace(
+            r"λa (λx x) list.head a",
+            Strategy::NormalOrder,
+            &SkipGlobals::Nothing,
+            Some(1),
+        );
+        assert_eq!(
+            lines,
+            vec!["   λa [[(λx x) list.head]] a", "→β λa [[list.head]] a"]
+        );
+    }
+
+    #[rstest]
+    #[case::normal(Strategy::NormalOrder, &[
+        "   [[(λx λy y x) ((λz z) (λa a))]]",
+        "→β λy y ([[(λz z) (λa a)]])",
+        "→β λy y (λa a)",
+    ])]
+    #[case::applicative(Strategy::ApplicativeOrder, &[
+        "   (λx λy y x) ([[(λz z) (λa a)]])",
+        "→β [[(λx λy y x) (λa a)]]",
+        "→β λy y (λa a)",
+    ])]
+    #[case::name(Strategy::CallByName, &[
+        "   [[(λx λy y x) ((λz z) (λa a))]]",
+        "→β λy y ((λz z) (λa a))",
+    ])]
+    #[case::value(Strategy::CallByValue, &[
+        "   (λx λy y x) ([[(λz z) (λa a)]])",
+        "→β [[(λx λy y x) (λa a)]]",
+        "→β λy y (λa a)",
+    ])]
+    fn strategies(#[case] strategy: Strategy, #[case] exp: &[&str]) {
+        let input = r"(λx λy y x) ((λz z) (λa a))";
+        let (lines, _) = trace(input, strategy, &SkipGlobals::Nothing, None);
+        assert_eq!(lines, exp);
+    }
+
+    #[test]
+    fn call_by_need() {
+        let input = r"(λx x x) ((λz z) (λa a))";
+        let (lines, _) = trace(input, Strategy::CallByNeed, &SkipGlobals::Nothing, None);
+        assert_eq!(
+            lines,
+            vec![
+                "   [[(λx x x) ((λz z) (λa a))]]",
+                "→β [[(λz z) (λa a)]] ((λz z) (λa a))",
+                "→β [[(λa a) (λa a)]]",
+                "→β λa a",
+            ]
+        );
+    }
+}
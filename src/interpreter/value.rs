@@ -1,6 +1,26 @@
 use super::GlobalScope;
-use crate::ast_nodes::Expr;
+use crate::ast_nodes::{Expr, Span};
+use crate::desugar;
+use crate::parser::is_word_char;
 use anyhow::Context;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+/// Syntactic sugar that `Value::repr_with` may use to make the output shorter.
+///
+/// Without any sugar, the output uses only the core syntax of lambda calculus.
+/// It's verbose but honest, and that's what `Value::repr` does.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Sugar {
+    /// Represent nested definitions as one multi-argument definition:
+    /// `λa b c. a` instead of `λa λb λc a`.
+    pub lambdas: bool,
+    /// Represent values that look like lists as list literals: `[a, b, c]`.
+    pub lists: bool,
+    /// Represent values that look like strings as string literals: `"abc"`.
+    /// Takes priority over `lists` because every string is also a list.
+    pub strings: bool,
+}
 
 /// Different types of runtime values.
 ///
@@ -10,28 +30,42 @@ use anyhow::Context;
 /// In a bigger language, perhaps it's a good idea to use traits and let each
 /// type to be defined separately. However, for small languages (up to JSON)
 /// enums work quite well.
+///
+/// Each value remembers the span of the AST node it was created from.
+/// Values are produced from other values during evaluation, and the new values
+/// inherit the span from the old ones. So, when something goes wrong,
+/// the error points to the place in the source code where the problematic
+/// identifier or call was originally written.
 #[derive(Debug, Clone)]
 pub enum Value {
     /// A lambda function definition
     Def {
         arg: String,
         value: Box<Value>,
+        span: Span,
     },
     /// An unbound identifier.
-    Id {
-        name: String,
-    },
+    Id { name: String, span: Span },
     /// An identifier bound to a global or local Value.
     BoundId {
         name: String,
         value: Box<Value>,
         global: bool,
+        span: Span,
     },
     // A function application.
     Call {
         target: Box<Value>,
         arg: Box<Value>,
+        span: Span,
     },
+    /// A Church numeral, see `desugar::numeral`.
+    ///
+    /// Numerals can be big, and a numeral made of definitions and calls
+    /// has a call for each unit. A tree that deep takes a lot of memory,
+    /// and walking it recursively overflows the stack. So, the numeral
+    /// is kept as a number and unfolded one call at a time, see `call`.
+    Numeral { n: u64, span: Span },
 }
 
 impl Value {
@@ -43,67 +77,282 @@ impl Value {
     /// so `Value` additionally has `BoundId`.
     pub fn from_expr(expr: &Expr) -> Self {
         match expr {
-            Expr::Def { arg, expr } => Value::Def {
-                arg: arg.to_string(),
+            Expr::Def { arg, expr, span } => Value::Def {
+                arg: arg.clone(),
                 // The `into` method is a convenient way to convert `Value`
                 // into `Box<Value>`.
                 value: Value::from_expr(expr).into(),
+                span: span.clone(),
             },
-            Expr::Call { target, arg } => Value::Call {
+            Expr::Call { target, arg, span } => Value::Call {
                 target: Value::from_expr(target).into(),
                 arg: Value::from_expr(arg).into(),
+                span: span.clone(),
+            },
+            Expr::Id { name, span } => Value::Id {
+                name: name.clone(),
+                span: span.clone(),
             },
-            Expr::Id { name } => Value::Id {
-                name: name.to_string(),
+            Expr::Numeral { n, span } => Value::Numeral {
+                n: *n,
+                span: span.clone(),
             },
         }
     }
 
+    /// If the name is a natural number literal, make a Church numeral for it.
+    pub fn numeral(name: &str, span: &Span) -> Option<Self> {
+        let n = desugar::as_numeral(name)?;
+        Some(Value::Numeral {
+            n,
+            span: span.clone(),
+        })
+    }
+
+    /// The position in the source code of the AST node the value was created from.
+    pub fn span(&self) -> &Span {
+        use Value::*;
+        match self {
+            Def { span, .. }
+            | Id { span, .. }
+            | BoundId { span, .. }
+            | Call { span, .. }
+            | Numeral { span, .. } => span,
+        }
+    }
+
     /// Represent the value as a valid human-readable expression.
     ///
     /// The name and the idea comes from Python's `__repr__` magic method.
     /// The main idea is that you can copy-paste the result of this function into REPL
     /// and (assuming all globals are the same) get the same evaluation result.
+    /// That's why an argument that would hide a name from the outside
+    /// is printed with a fresh name, like `b1` (see `repr_in`).
     ///
     /// The function is primarily used by the REPL to nicely format execution result.
     pub fn repr(&self) -> String {
+        self.repr_with(Sugar::default())
+    }
+
+    /// Same as `repr` but may use syntactic sugar to make the output shorter.
+    pub fn repr_with(&self, sugar: Sugar) -> String {
+        self.repr_among(sugar, &HashSet::new())
+    }
+
+    /// Same as `repr_with` but for the output to be read where the given
+    /// global names are defined.
+    ///
+    /// Numerals inside of the value are printed as numbers, and a number
+    /// is a name that can be defined to be something else, like `1 = K`.
+    /// Then the number would mean the global, and so a numeral that has
+    /// the name of one of the globals is printed in full instead.
+    pub fn repr_among(&self, sugar: Sugar, globals: &HashSet<String>) -> String {
+        // A numeral inside of the value is printed as a number (see `repr_in`),
+        // but if the numeral is the whole value, the number would tell nothing new.
+        if let Value::Numeral { n, .. } = self {
+            return numeral_repr(*n, sugar);
+        }
+        self.repr_in(sugar, globals, &mut Vec::new())
+    }
+
+    /// Same as `repr_with` but for a value inside of lambdas.
+    ///
+    /// The scope has the arguments of all lambdas around the value,
+    /// the innermost is the last. For each argument, it's the name of the argument
+    /// and the name it's printed with. Usually, they're the same. But if the body
+    /// uses a name from the outside that is the same as the argument, like `b` in
+    /// the result of `(λa λb a) b`, the argument gets a fresh name: `λb1 b`.
+    /// Otherwise, `b` in the output would refer to the argument, and the output
+    /// would mean something else than the value. In other words, the output
+    /// must be "capture-avoiding". For the same reason, numerals hidden by `globals`
+    /// are printed in full, see `repr_among`.
+    fn repr_in(
+        &self,
+        sugar: Sugar,
+        globals: &HashSet<String>,
+        scope: &mut Vec<(String, String)>,
+    ) -> String {
         // This is a convenient way to more briefly referrer to the items
         // of the current enum. So, instead of `Value::Def` we can write just `Def`.
         use Value::*;
+        if sugar.strings {
+            if let Some(text) = self.as_string() {
+                // Rust escapes strings the same way as our string literals do.
+                return format!("{text:?}");
+            }
+        }
+        if sugar.lists {
+            if let Some(items) = self.as_list() {
+                let items: Vec<_> = items
+                    .iter()
+                    .map(|item| item.repr_item(sugar, globals, scope))
+                    .collect();
+                return format!("[{}]", items.join(", "));
+            }
+        }
         match self {
-            Def { arg, value } => {
-                format!("λ{arg} {}", value.repr())
+            Def { .. } => {
+                // Collect arguments of all directly nested definitions,
+                // or only of this one if we don't sugar lambdas.
+                let mut names = Vec::new();
+                let mut body = self;
+                while let Def { arg, value, .. } = body {
+                    let name = fresh_name(arg, value, globals, scope);
+                    scope.push((arg.clone(), name.clone()));
+                    names.push(name);
+                    body = value;
+                    if !sugar.lambdas {
+                        break;
+                    }
+                }
+                let body_repr = body.repr_in(sugar, globals, scope);
+                scope.truncate(scope.len() - names.len());
+                if names.len() > 1 {
+                    return format!("λ{}. {body_repr}", names.join(" "));
+                }
+                format!("λ{} {body_repr}", names[0])
             }
             // Global IDs are better to be referred in repr by their name.
+            // Unless it's an argument that has the same name as a global,
+            // see `bind_global`. Then it's printed with the name of the argument.
             BoundId {
                 name, global: true, ..
             }
-            | Id { name } => name.to_string(),
+            | Id { name, .. } => match scope.iter().rfind(|(arg, _)| arg == name) {
+                Some((_, printed)) => printed.clone(),
+                None => match self.hidden_numeral(globals) {
+                    Some(n) => numeral_repr(n, sugar),
+                    None => name.clone(),
+                },
+            },
             // Local bound IDs should be represented by their values.
             // If you repr them by name, the result of `(λa λb a) true` will be
             // represented as `λb a` and it won't be clear what is `a` in this case.
             // However, if you repr them by value, the repr of the result
             // will be `λb true`.
-            BoundId { value, .. } => value.repr(),
-            Call { target, arg } => {
-                let mut tr = target.repr();
-                let mut ar = arg.repr();
-                // Only `Def` and `Call` may contain spaces.
+            //
+            // The value comes from the outside of all lambdas around it,
+            // so it can't refer to their arguments, and it's printed without the scope.
+            BoundId { value, .. } => value.repr_in(sugar, globals, &mut Vec::new()),
+            Call { target, arg, .. } => {
+                let mut tr = target.repr_in(sugar, globals, scope);
+                let mut ar = arg.repr_in(sugar, globals, scope);
                 // `Def` needs to be wrapped into braces because
                 // `(λa a) b` and `λa a b` are different expressions.
                 // `Call` does not need to be wrapped when on the left
                 // because `(a b) c` and `a b c` is the same.
-                if tr.contains('λ') {
+                // Literals are never wrapped, `("ab")` is not a valid syntax.
+                if needs_braces(target, &tr, false) {
                     tr = format!("({tr})");
                 }
-                if ar.contains(' ') {
+                if needs_braces(arg, &ar, true) {
                     ar = format!("({ar})");
                 }
                 format!("{tr} {ar}")
             }
+            // A numeral inside of a value is printed as a number, the same way
+            // as numbers written in the source are printed. It's much shorter,
+            // and printing a big numeral in full might take forever.
+            // An argument with the same name gets a fresh name, see `printed_names`,
+            // but a global can't be renamed, see `repr_among`.
+            Numeral { n, .. } => match self.hidden_numeral(globals) {
+                Some(n) => numeral_repr(n, sugar),
+                None => n.to_string(),
+            },
         }
     }
 
+    /// Call the function for each name that `repr_in` would print for a name
+    /// not defined inside of the value itself.
+    ///
+    /// The names of arguments of lambdas inside of the value are in `inner`.
+    fn printed_names<'v, F>(
+        &'v self,
+        globals: &HashSet<String>,
+        scope: &'v [(String, String)],
+        inner: &mut Vec<&'v str>,
+        f: &mut F,
+    ) where
+        F: FnMut(&str),
+    {
+        use Value::*;
+        match self {
+            Def { arg, value, .. } => {
+                inner.push(arg);
+                value.printed_names(globals, scope, inner, f);
+                inner.pop();
+            }
+            BoundId {
+                name, global: true, ..
+            }
+            | Id { name, .. } => {
+                if inner.contains(&name.as_str()) {
+                    return;
+                }
+                match scope.iter().rfind(|(arg, _)| arg == name) {
+                    Some((_, printed)) => f(printed),
+                    // A numeral printed in full doesn't refer to anything.
+                    None if self.hidden_numeral(globals).is_some() => {}
+                    None => f(name),
+                }
+            }
+            BoundId { value, .. } => value.printed_names(globals, &[], &mut Vec::new(), f),
+            Call { target, arg, .. } => {
+                target.printed_names(globals, scope, inner, f);
+                arg.printed_names(globals, scope, inner, f);
+            }
+            Numeral { .. } if self.hidden_numeral(globals).is_some() => {}
+            Numeral { n, .. } => f(&n.to_string()),
+        }
+    }
+
+    /// Represent an item of a list.
+    ///
+    /// Items of a list are not evaluated. That's fine for most of the items
+    /// but nested lists need to be evaluated to be recognized as lists.
+    /// So, if the item is a function call, we try to evaluate it
+    /// and check if the result is a list. Printing shouldn't hang,
+    /// so the evaluation gives up quickly, see `eval_briefly`.
+    fn repr_item(
+        &self,
+        sugar: Sugar,
+        globals: &HashSet<String>,
+        scope: &mut Vec<(String, String)>,
+    ) -> String {
+        if let Value::Call { .. } = self.unwrap_bound() {
+            if let Some(value) = self.eval_briefly() {
+                if value.as_list().is_some() {
+                    return value.repr_in(sugar, globals, scope);
+                }
+            }
+        }
+        self.repr_in(sugar, globals, scope)
+    }
+
+    /// If the value is a numeral that `repr_in` can't print as a number
+    /// because there is a global with that name (see `repr_among`), get the number.
+    ///
+    /// A number written in the source is bound to its numeral under the name
+    /// it's written with (see `bind_global`), so it's hidden the same way.
+    fn hidden_numeral(&self, globals: &HashSet<String>) -> Option<u64> {
+        let (name, n) = match self {
+            Value::Numeral { n, .. } => (n.to_string(), *n),
+            Value::BoundId {
+                name,
+                value,
+                global: true,
+                ..
+            } => match **value {
+                Value::Numeral { n, .. } if desugar::as_numeral(name) == Some(n) => {
+                    (name.clone(), n)
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+        globals.contains(&name).then_some(n)
+    }
+
     /// Bind all unbound variables in the Value to the global names.
     ///
     /// We call it as soon as a new function or expression is defined.
@@ -115,26 +364,34 @@ impl Value {
     /// `true` and then `and` that uses `true`. If the user redefines the `true`
     /// name, it will change the behavior of `and`. That's bad.
     ///
-    /// Python keeps a separate scope for all modules and binds all definitions
-    /// to the scope in which they are defined. This is better in some cases
-    /// but might get pretty messy.
+    /// Like in Python, each module has a separate scope, and the definitions
+    /// are bound to the scope in which they are defined. A name from another
+    /// module can be used either by the qualified name, like `nat.add`,
+    /// or without the module name if the module has exported it.
     ///
     /// The disadvantage of binding global names as soon as they are defined
     /// is that you cannot use the names that aren't defined yet. In particular,
     /// it makes recursion impossible without using Y-combinator.
     /// Maybe, this is for better. Otherwise, `a = a` would explode.
+    ///
+    /// Natural numbers are bound here as well. If a name is a number
+    /// and there is no global with such name, it gets bound to the Church numeral.
+    /// It's still a global `BoundId`, so it's shown in `repr` by its name
+    /// and can be shadowed by a local variable just like any other global.
     pub fn bind_global(&self, global: &GlobalScope) -> Value {
         use Value::*;
         match self {
-            Def { arg, value } => Def {
-                arg: arg.to_string(),
+            Def { arg, value, span } => Def {
+                arg: arg.clone(),
                 value: value.bind_global(global).into(),
+                span: span.clone(),
             },
-            Id { name } => match global.get(name) {
+            Id { name, span } => match global.get(name) {
                 Some(val) => BoundId {
-                    name: name.to_string(),
+                    name: name.clone(),
                     value: val.clone().into(),
                     global: true,
+                    span: span.clone(),
                 },
                 // You'll see me doing `clone` a lot in this module.
                 // Perhaps, I could avoid it with some smart pointer
@@ -151,16 +408,61 @@ impl Value {
                 //    nasty bugs.
                 //
                 // So, for a PoC (and RLCI is PoC), keep it simple.
-                None => self.clone(),
+                None => match Value::numeral(name, span) {
+                    Some(val) => BoundId {
+                        name: name.clone(),
+                        value: val.into(),
+                        global: true,
+                        span: span.clone(),
+                    },
+                    None => self.clone(),
+                },
             },
-            BoundId { .. } => self.clone(),
-            Call { target, arg } => Call {
+            BoundId { .. } | Numeral { .. } => self.clone(),
+            Call { target, arg, span } => Call {
                 target: target.bind_global(global).into(),
                 arg: arg.bind_global(global).into(),
+                span: span.clone(),
             },
         }
     }
 
+    /// Find all names in the value that aren't bound to anything.
+    ///
+    /// After `bind_global`, a name that is still an unbound `Id` is either
+    /// an argument of a function around it or a name that isn't defined at all.
+    /// We want the latter. Each name is reported once, with the position
+    /// where it's used first.
+    pub(crate) fn unbound_names(&self) -> Vec<(&str, &Span)> {
+        let mut found = Vec::new();
+        self.find_unbound(&mut Vec::new(), &mut found);
+        found
+    }
+
+    fn find_unbound<'v>(&'v self, args: &mut Vec<&'v str>, found: &mut Vec<(&'v str, &'v Span)>) {
+        use Value::*;
+        match self {
+            Def { arg, value, .. } => {
+                args.push(arg);
+                value.find_unbound(args, found);
+                args.pop();
+            }
+            Id { name, span } => {
+                let is_arg = args.contains(&name.as_str());
+                if !is_arg && !found.iter().any(|(n, _)| n == name) {
+                    found.push((name, span));
+                }
+            }
+            // Bound names are bound, and their values were checked
+            // when they were defined.
+            BoundId { .. } | Numeral { .. } => {}
+            Call { target, arg, .. } => {
+                target.find_unbound(args, found);
+                arg.find_unbound(args, found);
+            }
+        }
+    }
+
     /// Evaluate the value.
     ///
     /// "Evaluation" is not quite correct word for it, but I wanted to keep
@@ -178,18 +480,24 @@ impl Value {
     pub fn eval(&self) -> anyhow::Result<Value> {
         use Value::*;
         Ok(match self {
-            Def { arg: _, value: _ } => self.clone(),
-            Id { name } => anyhow::bail!("unbound variable `{name}`"),
-            BoundId { name, value, .. } => {
+            Def { .. } | Numeral { .. } => self.clone(),
+            Id { name, span } => anyhow::bail!("unbound variable `{name}` at {span}"),
+            BoundId {
+                name, value, span, ..
+            } => {
                 // Adding context to every failure is very important
                 // during the evaluation. This is how we build a traceback
                 // that get shown to the user in case of an undefined variable.
-                value.eval().context(format!("failure executing {name}"))?
+                value
+                    .eval()
+                    .context(format!("failure executing {name} at {span}"))?
             }
-            Call { target, arg } => {
+            Call { target, arg, span } => {
                 // This is the star of the show. The main point of the evaluation
                 // is to call all functions.
-                target.call(arg).context("failure calling a function")?
+                target
+                    .call(arg)
+                    .context(format!("failure calling a function at {span}"))?
             }
         })
     }
@@ -205,16 +513,53 @@ impl Value {
             Def {
                 arg: arg_name,
                 value: expr,
+                ..
             } => {
                 let expr = expr.bind_local(arg_name, arg_value);
                 expr.eval()
             }
-            Id { name } => anyhow::bail!("unbound variable `{name}`"),
+            Id { name, span } => anyhow::bail!("unbound variable `{name}` at {span}"),
             BoundId { value, .. } => value.call(arg_value),
-            Call { target, arg } => {
+            Call { target, arg, .. } => {
                 let value = target.call(arg)?;
                 value.call(arg_value)
             }
+            // The numeral `n` called with `a` is `λb a (a (a b))` with `n` calls of `a`.
+            // Instead of making all of them at once, we make only the outer one
+            // and leave the rest to the numeral `n - 1`: `λb a (n-1 a b)`.
+            // When the function is called, the smaller numeral is unfolded the same way.
+            // So, `is_zero 99999999999` is as fast as `is_zero 1`.
+            Numeral { n, span } => {
+                let id = |name: &str| Id {
+                    name: name.to_string(),
+                    span: span.clone(),
+                };
+                let call = |target: Value, arg: Value| Call {
+                    target: target.into(),
+                    arg: arg.into(),
+                    span: span.clone(),
+                };
+                let body = if *n == 0 {
+                    id("b")
+                } else {
+                    let a = BoundId {
+                        name: "a".to_string(),
+                        value: arg_value.clone().into(),
+                        global: false,
+                        span: span.clone(),
+                    };
+                    let rest = Numeral {
+                        n: n - 1,
+                        span: span.clone(),
+                    };
+                    call(a.clone(), call(call(rest, a), id("b")))
+                };
+                Ok(Def {
+                    arg: "b".to_string(),
+                    value: body.into(),
+                    span: span.clone(),
+                })
+            }
         }
     }
 
@@ -222,10 +567,17 @@ impl Value {
     ///
     /// It's called `bind_local` because it is called for function application
     /// during evaluation (β-reduction).
+    ///
+    /// The substitution is capture-avoiding without renaming anything.
+    /// The value isn't pasted into the body as text, it's wrapped into a `BoundId`,
+    /// and we never look inside of a `BoundId` here. So, if the value has
+    /// a free `b` and it ends up inside of `λb ...`, calling that function
+    /// won't bind the `b` from the value. Only `repr` has to pick fresh names
+    /// for such arguments, see `repr_in`.
     fn bind_local(&self, lname: &str, lvalue: &Value) -> Value {
         use Value::*;
         match self {
-            Def { arg, value } => {
+            Def { arg, value, span } => {
                 // Do not bind the local variable to functions that will shadow it anyway.
                 // This is not just a performance improvement. If we bind a variable
                 // that is meant to be rebind later, we may get a wrong repr.
@@ -236,8 +588,9 @@ impl Value {
                     self.clone()
                 } else {
                     Def {
-                        arg: arg.to_string(),
+                        arg: arg.clone(),
                         value: value.bind_local(lname, lvalue).into(),
+                        span: span.clone(),
                     }
                 }
             }
@@ -245,26 +598,133 @@ impl Value {
             //
             // We allow rebinding already bound IDs. It allows for shadowing
             // global names with local ones.
-            Id { name } | BoundId { name, .. } => {
+            Id { name, span } | BoundId { name, span, .. } => {
                 if name == lname {
                     BoundId {
-                        name: name.to_string(),
+                        name: name.clone(),
                         value: lvalue.clone().into(),
                         global: false,
+                        span: span.clone(),
                     }
                 } else {
                     self.clone()
                 }
             }
-            Call { target, arg } => {
+            Call { target, arg, span } => {
                 let target = target.bind_local(lname, lvalue);
                 let arg = arg.bind_local(lname, lvalue);
                 Call {
                     target: target.into(),
                     arg: arg.into(),
+                    span: span.clone(),
                 }
             }
+            Numeral { .. } => self.clone(),
+        }
+    }
+}
+
+/// Pick the name to print the argument of a lambda with.
+///
+/// It's the name of the argument itself unless the body prints the same name
+/// for something else. Then it's the name with the smallest number after it
+/// that the body doesn't print: `b1`, `b2`, and so on.
+fn fresh_name(
+    arg: &str,
+    body: &Value,
+    globals: &HashSet<String>,
+    scope: &[(String, String)],
+) -> String {
+    let mut taken = HashSet::new();
+    body.printed_names(globals, scope, &mut vec![arg], &mut |name| {
+        taken.insert(name.to_string());
+    });
+    if !taken.contains(arg) {
+        return arg.to_string();
+    }
+    for n in 1.. {
+        let name = format!("{arg}{n}");
+        if !taken.contains(name.as_str()) {
+            return name;
+        }
+    }
+    unreachable!()
+}
+
+/// Represent the Church numeral in full, the same way as `repr_in` represents definitions.
+///
+/// The body is built in a loop rather than by unfolding the numeral
+/// and printing the result because `repr_in` is recursive, and the numeral
+/// might be too big for it. The numeral doesn't use anything from the outside,
+/// so its arguments never need fresh names.
+fn numeral_repr(n: u64, sugar: Sugar) -> String {
+    let mut repr = String::from(if sugar.lambdas { "λa b. " } else { "λa λb " });
+    if n == 0 {
+        repr.push('b');
+        return repr;
+    }
+    for _ in 1..n {
+        repr.push_str("a (");
+    }
+    repr.push_str("a b");
+    for _ in 1..n {
+        repr.push(')');
+    }
+    repr
+}
+
+/// Check if the value needs braces around it when it's called
+/// or passed as an argument, given how it's printed.
+fn needs_braces(val: &Value, repr: &str, is_arg: bool) -> bool {
+    if repr.starts_with(['[', '"']) {
+        return false;
+    }
+    // Local values and numbers written in the source are printed
+    // the same way as the values they're bound to.
+    let mut val = val;
+    while let Value::BoundId { value, global, .. } = val {
+        if *global && !matches!(**value, Value::Numeral { .. }) {
+            break;
         }
+        val = value;
+    }
+    match val {
+        Value::Def { .. } => true,
+        Value::Numeral { .. } => repr.starts_with('λ'),
+        Value::Call { .. } => is_arg,
+        // An operator can be passed as an argument only in braces: `f (+)`.
+        // Otherwise, it would be parsed as an infix operator.
+        Value::Id { .. } | Value::BoundId { .. } => is_arg && is_operator(repr),
+    }
+}
+
+/// Check if the name is made of symbols, like `+`.
+///
+/// Qualified names, like `nat.+`, are never infix operators, so they don't count.
+pub(super) fn is_operator(name: &str) -> bool {
+    !name.starts_with(is_word_char)
+}
+
+/// Values are equal if they are alpha-equivalent: the same up to names of arguments.
+///
+/// So, `λa a` is equal to `λb b`, but not to `λa b`. Values are compared
+/// in de Bruijn notation (see `Value::to_debruijn`), with globals replaced
+/// by their values. So, `true` is equal to `λa λb a` no matter what
+/// names the stdlib uses. The values are compared as they are, without
+/// evaluating anything: `not false` is not equal to `true` until it's evaluated.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_debruijn() == other.to_debruijn()
+    }
+}
+
+impl Eq for Value {}
+
+// The hash must be the same for equal values, so it's calculated
+// on the same de Bruijn term that `eq` compares.
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_debruijn().hash(state);
     }
 }
 
@@ -295,9 +755,94 @@ mod tests {
         let module = parse(input).unwrap();
         assert_eq!(module.stmts.len(), 1);
         let stmt = &module.stmts[0];
-        match stmt {
-            Stmt::Expr { expr } => assert_eq!(Value::from_expr(expr).repr(), exp),
-            Stmt::Assign { .. } => panic!("bad statement"),
+        let Stmt::Expr { expr, .. } = stmt else {
+            panic!("bad statement")
+        };
+        assert_eq!(Value::from_expr(expr).repr(), exp);
+    }
+
+    #[rstest]
+    #[case(r"\x x", "λx x")]
+    #[case(r"\a \b a", "λa b. a")]
+    #[case(r"\a b c. a c (b c)", "λa b c. a c (b c)")]
+    #[case(r"\a \b (\c \d c) b", "λa b. (λc d. c) b")]
+    #[case(r"\a (\b b) (\c \d c)", "λa (λb b) (λc d. c)")]
+    fn repr_sugar_lambdas(#[case] input: &str, #[case] exp: &str) {
+        let module = parse(input).unwrap();
+        let Stmt::Expr { expr, .. } = &module.stmts[0] else {
+            panic!("bad statement")
+        };
+        let sugar = Sugar {
+            lambdas: true,
+            ..Sugar::default()
+        };
+        assert_eq!(Value::from_expr(expr).repr_with(sugar), exp);
+    }
+
+    #[test]
+    fn repr_numeral() {
+        let span = Span::default();
+        let numeral = |n| Value::Numeral {
+            n,
+            span: span.clone(),
+        };
+        let a = Value::Id {
+            name: "A".to_string(),
+            span: span.clone(),
+        };
+        assert_eq!(numeral(0).repr(), "λa λb b");
+        assert_eq!(numeral(3).repr(), "λa λb a (a (a b))");
+        // A numeral called with a function is unfolded only once,
+        // and the rest is printed as a number.
+        let val = numeral(2).call(&a).unwrap();
+        assert_eq!(val.repr(), "λb A (1 A b)");
+        // An argument can't hide the number.
+        let val = Value::Def {
+            arg: "1".to_string(),
+            value: Value::BoundId {
+                name: "x".to_string(),
+                value: val.into(),
+                global: false,
+                span: span.clone(),
+            }
+            .into(),
+            span,
+        };
+        assert_eq!(val.repr(), "λ11 λb A (1 A b)");
+        // Nor can a global, but it can't be renamed.
+        let globals = HashSet::from(["1".to_string()]);
+        assert_eq!(
+            val.repr_among(Sugar::default(), &globals),
+            "λ1 λb A ((λa λb a b) A b)"
+        );
+    }
+
+    #[rstest]
+    #[case(r"\x x", r"\y y", true)]
+    #[case(r"\a \b a", r"\b \a b", true)]
+    #[case(r"\a \b a", r"\a \b b", false)]
+    #[case(r"\a (\b b) a", r"\x (\x x) x", true)]
+    #[case(r"\a b", r"\c b", true)]
+    #[case(r"\a b", r"\b b", false)]
+    #[case(r"\a b", r"\a c", false)]
+    #[case(r"f x", r"f x", true)]
+    fn alpha_eq(#[case] left: &str, #[case] right: &str, #[case] exp: bool) {
+        let val = |input| {
+            let module = parse(input).unwrap();
+            let Stmt::Expr { expr, .. } = &module.stmts[0] else {
+                panic!("bad statement")
+            };
+            Value::from_expr(expr)
+        };
+        let (left, right) = (val(left), val(right));
+        assert_eq!(left == right, exp);
+        let hash = |val: &Value| {
+            let mut hasher = std::hash::DefaultHasher::new();
+            val.hash(&mut hasher);
+            hasher.finish()
+        };
+        if exp {
+            assert_eq!(hash(&left), hash(&right));
         }
     }
 }
@@ -15,23 +15,41 @@ extern crate pest;
 extern crate pest_derive;
 
 // The parser, converts the plain text input into AST nodes.
+mod ast_format;
 mod ast_nodes;
+mod desugar;
+mod fixity;
+mod formatter;
+mod lint;
 mod parser;
-pub use parser::{parse, LangParser};
+pub use formatter::{format_source, LambdaStyle};
+pub use lint::{lint, Diagnostic, LintKind};
+pub use parser::{parse, parse_file, parse_file_recovering, parse_recovering, Dialect, LangParser};
 
 /// The interpreter, evaluates values at runtime.
 pub mod interpreter {
+    mod blc;
+    mod debruijn;
+    mod decode;
     mod helper;
+    mod reduce;
     mod repl;
     mod scope;
     mod session;
     mod stdlib;
+    mod trace;
     mod value;
 
+    pub use blc::{pack_bits, run_blc, IoMode};
+    pub use debruijn::Term;
     pub(crate) use helper::Helper;
+    pub use reduce::{Strategy, MAX_STEPS};
     pub use repl::run_repl;
-    pub(crate) use scope::GlobalScope;
-    pub use session::Session;
+    pub(crate) use scope::{GlobalScope, MAIN};
+    pub use session::{Session, SyntaxErrors, UndefinedNames};
     pub(crate) use stdlib::read_stdlib;
+    pub(crate) use trace::TraceOptions;
+    pub use trace::{Reached, SkipGlobals, StepKind, TraceLine};
+    pub use value::Sugar;
     pub(crate) use value::Value;
 }
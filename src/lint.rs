@@ -0,0 +1,604 @@
+//! The linter, finds suspicious code that is valid but most probably wrong.
+//!
+//! It works on the AST, without evaluating anything, so it's safe to run
+//! on any code, even the code that would never terminate. The catch is that
+//! the parser desugars literals, `let`, and multi-argument definitions, and it
+//! drops parentheses. So, the same as the formatter, we look at the source code
+//! of nodes (thanks to spans) to tell what was written by the user and what was
+//! generated by the parser. We don't want to tell the user that the argument
+//! of a list literal they have never seen is unused.
+//!
+//! Checks:
+//!
+//! + `unused-argument`: an argument (or a `let` binding) that is never used
+//!   in the body. If it's on purpose, start the name with `_`.
+//! + `shadowed-global`: an argument with the same name as a global, like
+//!   `\true ...`. Inside of the body, the name means the argument
+//!   and not the global, which is easy to misread.
+//! + `redefined-global`: an assignment to a name that is already defined,
+//!   in stdlib, in an imported module, or earlier in the same module.
+//!   The names a module exports itself don't count. That's the hazard described
+//!   in `Value::bind_global`: everything defined before keeps using
+//!   the old value, so `and` from stdlib won't use the new `true`.
+//! + `redundant-parens`: parentheses that can be removed without changing
+//!   what the code means.
+//! + `unreachable-definition`: a definition that nothing can ever use.
+//!   It's either redefined before anything uses it, or, in a script that ends
+//!   with an expression, neither the expressions nor the exports depend on it.
+use crate::ast_format::{json_str, span_json};
+use crate::ast_nodes::{Expr, Module, Span, Stmt};
+use crate::parser::is_word_char;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// The kind of problem the linter has found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LintKind {
+    /// The argument is never used in the body of the definition.
+    UnusedArgument,
+    /// The argument has the same name as a global.
+    ShadowedGlobal,
+    /// The assignment redefines a name that is already defined.
+    RedefinedGlobal,
+    /// The parentheses can be removed.
+    RedundantParens,
+    /// Nothing can ever use the definition.
+    UnreachableDefinition,
+}
+
+impl LintKind {
+    /// The short name of the check, the same as in the docs and in JSON output.
+    #[must_use]
+    pub fn code(self) -> &'static str {
+        match self {
+            LintKind::UnusedArgument => "unused-argument",
+            LintKind::ShadowedGlobal => "shadowed-global",
+            LintKind::RedefinedGlobal => "redefined-global",
+            LintKind::RedundantParens => "redundant-parens",
+            LintKind::UnreachableDefinition => "unreachable-definition",
+        }
+    }
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// A single problem found by the linter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// What kind of problem it is.
+    pub kind: LintKind,
+    /// The human-readable description of the problem.
+    pub message: String,
+    /// Where the problem is in the source code.
+    pub span: Span,
+}
+
+impl Diagnostic {
+    /// Represent the diagnostic as a JSON object.
+    ///
+    /// ```text
+    /// {"kind": "unused-argument", "message": "...", "span": SPAN}
+    /// ```
+    ///
+    /// The span is in the same format as in `rlci parse --format json`.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"kind\": {}, \"message\": {}, \"span\": {}}}",
+            json_str(self.kind.code()),
+            json_str(&self.message),
+            span_json(&self.span),
+        )
+    }
+}
+
+// The same format as rustc uses for warnings, so that editors
+// can jump to the location.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.span, self.kind, self.message)
+    }
+}
+
+/// Check the module and return all problems found, ordered by position.
+///
+/// The source is the code the module was parsed from, it's needed to see
+/// the syntactic sugar and parentheses. The globals are the names visible
+/// in the module before it's evaluated, usually everything stdlib exports,
+/// each with the name of the module it comes from.
+#[must_use]
+#[allow(clippy::implicit_hasher)]
+pub fn lint(module: &Module, source: &str, globals: &HashMap<String, String>) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        source,
+        globals,
+        defined: HashMap::new(),
+        diagnostics: Vec::new(),
+    };
+    for stmt in &module.stmts {
+        linter.stmt(stmt);
+    }
+    linter.unreachable(module);
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.sort_by_key(|diag| (diag.span.start, diag.kind));
+    diagnostics.dedup();
+    diagnostics
+}
+
+/// Where in the outer expression the expression is.
+///
+/// That's what decides if parentheses around it are redundant.
+#[derive(Clone, Copy)]
+enum Pos {
+    /// The whole statement or the value in `let`.
+    Top,
+    /// The target of a call.
+    Head,
+    /// The argument of a call.
+    Arg,
+    /// An operand of an infix operator.
+    Operand,
+    /// Generated by the parser, there can't be parentheses around it.
+    Sugar,
+}
+
+struct Linter<'a> {
+    source: &'a str,
+    /// Names visible before the module, with the module each one comes from.
+    globals: &'a HashMap<String, String>,
+    /// Names assigned so far in the module, with where they were assigned.
+    defined: HashMap<&'a str, &'a Span>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Linter<'a> {
+    fn stmt(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::Assign { target, expr, span } => {
+                // The value goes first: arguments are bound at definition time,
+                // so the target itself isn't a global for them yet.
+                self.expr(expr, Pos::Top);
+                if let Some(prev) = self.defined.get(target.as_str()) {
+                    let msg = format!("`{target}` is already defined at {prev}, the code between keeps using the old definition");
+                    self.report(LintKind::RedefinedGlobal, msg, span.clone());
+                } else if let Some(module) = self.globals.get(target) {
+                    let msg = format!("`{target}` is already defined in module `{module}`, the code defined before keeps using the old definition");
+                    self.report(LintKind::RedefinedGlobal, msg, span.clone());
+                }
+                self.defined.insert(target, span);
+            }
+            Stmt::Expr { expr, .. } => self.expr(expr, Pos::Top),
+            Stmt::Import { .. } | Stmt::Export { .. } | Stmt::Fixity { .. } => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &'a Expr, pos: Pos) {
+        self.parens(expr, pos);
+        match expr {
+            Expr::Def {
+                arg,
+                expr: body,
+                span,
+            } => {
+                if let Some(arg_span) = self.arg_span(arg, span) {
+                    self.arg(arg, arg_span, body);
+                }
+                self.expr(body, child_pos(expr, body, Pos::Top));
+            }
+            // `a + b` is `(+ a) b`, and the call with the operator
+            // can't have parentheses around it on its own.
+            Expr::Call { target, arg, .. } if is_infix(target) => {
+                let Expr::Call { arg: lhs, .. } = target.as_ref() else {
+                    unreachable!();
+                };
+                self.expr(lhs, child_pos(expr, lhs, Pos::Operand));
+                self.expr(arg, child_pos(expr, arg, Pos::Operand));
+            }
+            Expr::Call { target, arg, .. } => {
+                let pos = if self.is_let(expr) {
+                    Pos::Top
+                } else {
+                    Pos::Arg
+                };
+                self.expr(target, child_pos(expr, target, Pos::Head));
+                self.expr(arg, child_pos(expr, arg, pos));
+            }
+            Expr::Id { .. } | Expr::Numeral { .. } => {}
+        }
+    }
+
+    /// Check the argument of a definition written by the user.
+    fn arg(&mut self, arg: &str, span: Span, body: &Expr) {
+        if !arg.starts_with('_') && !uses(body, arg) {
+            let what = if self.is_binding(&span) {
+                "binding"
+            } else {
+                "argument"
+            };
+            let msg =
+                format!("{what} `{arg}` is never used, rename it to `_{arg}` if that's intended");
+            self.report(LintKind::UnusedArgument, msg, span.clone());
+        }
+        if self.globals.contains_key(arg) || self.defined.contains_key(arg) {
+            let msg = format!("argument `{arg}` shadows the global with the same name");
+            self.report(LintKind::ShadowedGlobal, msg, span);
+        }
+    }
+
+    /// Find where the argument of the definition is written.
+    ///
+    /// Returns `None` for definitions generated by the parser for literals.
+    /// A definition written by the user starts either with the lambda
+    /// (`\x y. x`) or with the argument itself (the `y. x` part of it,
+    /// or a `let` binding).
+    fn arg_span(&self, arg: &str, span: &Span) -> Option<Span> {
+        let text = &self.source[span.start..span.end];
+        let offset = match text.chars().next()? {
+            ch @ ('\\' | 'λ') => ch.len_utf8(),
+            _ => 0,
+        };
+        let rest = text[offset..].strip_prefix(arg)?;
+        if rest.starts_with(is_word_char) {
+            return None;
+        }
+        Some(self.span_at(span, span.start + offset, span.start + offset + arg.len()))
+    }
+
+    /// Check if the argument is a `let` binding, `x = value`.
+    fn is_binding(&self, span: &Span) -> bool {
+        let rest = self.source[span.end..].trim_start_matches([' ', '\t']);
+        rest.strip_prefix('=')
+            .is_some_and(|rest| !rest.starts_with(|ch: char| ch.is_ascii_punctuation()))
+    }
+
+    /// Check if the call is `let`, which is a call of the definition
+    /// of the body with the value of the binding.
+    fn is_let(&self, expr: &Expr) -> bool {
+        let Expr::Call { target, span, .. } = expr else {
+            return false;
+        };
+        let text = &self.source[span.start..span.end];
+        let is_keyword = text
+            .strip_prefix("let")
+            .is_some_and(|rest| !rest.starts_with(is_word_char));
+        is_keyword
+            && matches!(target.as_ref(), Expr::Def { .. })
+            && target.span().start > span.start
+    }
+
+    /// Report parentheses around the expression if they aren't needed.
+    ///
+    /// Parentheses are needed to pass anything but a name as an argument,
+    /// to call a definition, and around operators. We don't know
+    /// precedences of operators, so we report only regular calls as operands.
+    fn parens(&mut self, expr: &Expr, pos: Pos) {
+        let redundant = match pos {
+            Pos::Top => !is_operator(expr),
+            Pos::Head | Pos::Operand => is_prefix_call(expr),
+            Pos::Arg | Pos::Sugar => false,
+        };
+        if !redundant {
+            return;
+        }
+        if let Some((start, end)) = self.wrapping_parens(expr.span()) {
+            let span = self.span_at(expr.span(), start, end);
+            let msg = "redundant parentheses".to_string();
+            self.report(LintKind::RedundantParens, msg, span);
+        }
+    }
+
+    /// Find parentheses right around the span, returns the byte range
+    /// from the opening one to the closing one inclusive.
+    ///
+    /// The span must be balanced by itself, otherwise in `(f a) + (g b)`
+    /// the operands would look like they're wrapped into one pair.
+    fn wrapping_parens(&self, span: &Span) -> Option<(usize, usize)> {
+        if !is_balanced(&self.source[span.start..span.end]) {
+            return None;
+        }
+        let before = self.source[..span.start].trim_end();
+        let after = self.source[span.end..].trim_start();
+        if !before.ends_with('(') || !after.starts_with(')') {
+            return None;
+        }
+        let start = before.len() - 1;
+        let end = self.source.len() - after.len() + 1;
+        Some((start, end))
+    }
+
+    /// Make a span for the given byte range inside of the source.
+    fn span_at(&self, base: &Span, start: usize, end: usize) -> Span {
+        let before = &self.source[..start];
+        let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+        Span {
+            file: base.file.clone(),
+            start,
+            end,
+            line: before.matches('\n').count() + 1,
+            col: before[line_start..].chars().count() + 1,
+        }
+    }
+
+    fn report(&mut self, kind: LintKind, message: String, span: Span) {
+        self.diagnostics.push(Diagnostic {
+            kind,
+            message,
+            span,
+        });
+    }
+
+    /// Report definitions that nothing can use.
+    ///
+    /// Each assignment is a node of the dependency graph. A name used in
+    /// an assignment refers to the latest assignment of that name before it,
+    /// that's how globals are bound. The roots are expression statements,
+    /// exports, and, if the module is a library (doesn't end with an expression),
+    /// the latest assignment of each name because other modules can use it.
+    fn unreachable(&mut self, module: &'a Module) {
+        // For each assignment, the name, the span, and the assignments it uses.
+        let mut defs: Vec<(&str, &Span, Vec<usize>)> = Vec::new();
+        let mut latest: HashMap<&str, usize> = HashMap::new();
+        let mut roots: Vec<usize> = Vec::new();
+        for stmt in &module.stmts {
+            match stmt {
+                Stmt::Assign { target, expr, span } => {
+                    let edges = free_names(expr)
+                        .into_iter()
+                        .filter_map(|name| latest.get(name).copied())
+                        .collect();
+                    latest.insert(target, defs.len());
+                    defs.push((target, span, edges));
+                }
+                Stmt::Expr { expr, .. } => {
+                    let names = free_names(expr);
+                    roots.extend(names.into_iter().filter_map(|name| latest.get(name)));
+                }
+                Stmt::Export { names, .. } => {
+                    roots.extend(names.iter().filter_map(|name| latest.get(name.as_str())));
+                }
+                Stmt::Import { .. } | Stmt::Fixity { .. } => {}
+            }
+        }
+        let is_script = matches!(module.stmts.last(), Some(Stmt::Expr { .. }));
+        if !is_script {
+            roots.extend(latest.values());
+        }
+        let mut reachable = vec![false; defs.len()];
+        while let Some(idx) = roots.pop() {
+            if !reachable[idx] {
+                reachable[idx] = true;
+                roots.extend(&defs[idx].2);
+            }
+        }
+        for (idx, (name, span, _)) in defs.iter().enumerate() {
+            if reachable[idx] {
+                continue;
+            }
+            let msg = if latest[name] == idx {
+                format!("`{name}` is never used")
+            } else {
+                format!("`{name}` is redefined before anything uses it")
+            };
+            self.report(LintKind::UnreachableDefinition, msg, (*span).clone());
+        }
+    }
+}
+
+/// The position of a child node, or `Sugar` if the node is generated by the parser.
+///
+/// The parser gives all nodes it generates for a literal
+/// the span of the whole literal. So, if the child has the same span
+/// as its parent, it's not something the user wrote.
+fn child_pos(parent: &Expr, child: &Expr, pos: Pos) -> Pos {
+    let (parent, child) = (parent.span(), child.span());
+    if parent.start == child.start && parent.end == child.end {
+        return Pos::Sugar;
+    }
+    pos
+}
+
+/// Check if the expression is a regular call, like `f a b`.
+///
+/// Infix operators and `let` are calls too, but they start
+/// with an operand or a binding rather than with the function.
+fn is_prefix_call(expr: &Expr) -> bool {
+    let Expr::Call { span, .. } = expr else {
+        return false;
+    };
+    let mut head = expr;
+    while let Expr::Call { target, .. } = head {
+        head = target;
+    }
+    head.span().start == span.start
+}
+
+/// Check if the expression is an infix operator with the left operand, `(+ a)`
+/// made by the parser from `a + b`.
+fn is_infix(expr: &Expr) -> bool {
+    let Expr::Call { target, arg, .. } = expr else {
+        return false;
+    };
+    is_operator(target) && target.span().start > arg.span().start
+}
+
+/// Check if the expression is an operator, like `+` or `nat.+`.
+fn is_operator(expr: &Expr) -> bool {
+    let Expr::Id { name, .. } = expr else {
+        return false;
+    };
+    let name = name
+        .rsplit_once('.')
+        .map_or(name.as_str(), |(_, name)| name);
+    !name.starts_with(is_word_char)
+}
+
+/// Check if the name is used in the expression as a free variable.
+fn uses(expr: &Expr, name: &str) -> bool {
+    match expr {
+        Expr::Def { arg, expr, .. } => arg != name && uses(expr, name),
+        Expr::Call { target, arg, .. } => uses(target, name) || uses(arg, name),
+        Expr::Id { name: id, .. } => id == name,
+        Expr::Numeral { .. } => false,
+    }
+}
+
+/// All names used in the expression that aren't its own arguments.
+fn free_names(expr: &Expr) -> HashSet<&str> {
+    fn walk<'a>(expr: &'a Expr, args: &mut Vec<&'a str>, names: &mut HashSet<&'a str>) {
+        match expr {
+            Expr::Def { arg, expr, .. } => {
+                args.push(arg);
+                walk(expr, args, names);
+                args.pop();
+            }
+            Expr::Call { target, arg, .. } => {
+                walk(target, args, names);
+                walk(arg, args, names);
+            }
+            Expr::Id { name, .. } => {
+                if !args.contains(&name.as_str()) {
+                    names.insert(name);
+                }
+            }
+            Expr::Numeral { .. } => {}
+        }
+    }
+    let mut names = HashSet::new();
+    walk(expr, &mut Vec::new(), &mut names);
+    names
+}
+
+/// Check that every parenthesis in the code is closed and there are
+/// no closing parentheses without an opening one.
+///
+/// Parentheses inside of string and character literals and comments don't count.
+fn is_balanced(text: &str) -> bool {
+    let mut depth: usize = 0;
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '(' => depth += 1,
+            ')' => match depth.checked_sub(1) {
+                Some(new) => depth = new,
+                None => return false,
+            },
+            '"' | '\'' => {
+                while let Some(next) = chars.next() {
+                    match next {
+                        '\\' => drop(chars.next()),
+                        next if next == ch => break,
+                        _ => {}
+                    }
+                }
+            }
+            '#' => {
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    depth == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use rstest::rstest;
+
+    fn check(input: &str) -> Vec<String> {
+        let module = parse(input).unwrap();
+        let globals = HashMap::from([
+            ("true".to_string(), "bool".to_string()),
+            ("false".to_string(), "bool".to_string()),
+        ]);
+        lint(&module, input, &globals)
+            .iter()
+            .map(|diag| format!("{}:{}: {}", diag.span.line, diag.span.col, diag.kind))
+            .collect()
+    }
+
+    #[rstest]
+    #[case::used("id = \\x x", &[])]
+    #[case::unused("const = \\x \\y x", &["1:13: unused-argument"])]
+    #[case::unused_lambda("const = λx λy x", &["1:13: unused-argument"])]
+    #[case::unused_dot("const = \\x y. x", &["1:12: unused-argument"])]
+    #[case::underscore("const = \\x \\_ x", &[])]
+    #[case::shadowed_by_inner("f = \\x \\x x", &["1:6: unused-argument"])]
+    #[case::let_binding("a = let x = b in c", &["1:9: unused-argument"])]
+    #[case::let_used("a = let x = b, y = x in y", &[])]
+    #[case::literals("a = [b, \"c\", 'd', 12]", &[])]
+    #[case::list_item("a = [\\x b]", &["1:7: unused-argument"])]
+    #[case::shadow_stdlib("a = \\true true", &["1:6: shadowed-global"])]
+    #[case::shadow_module("b = c\na = \\b b", &["2:6: shadowed-global"])]
+    #[case::shadow_later("a = \\b b\nb = c", &[])]
+    #[case::redefine_stdlib("true = \\a \\_b a", &["1:1: redefined-global"])]
+    #[case::redefine_module("a = b\nc = a\na = c", &["3:1: redefined-global"])]
+    fn check_defs(#[case] input: &str, #[case] expected: &[&str]) {
+        assert_eq!(check(input), expected);
+    }
+
+    #[rstest]
+    #[case::none("a = f (g x)", &[])]
+    #[case::stmt("a = (f x)", &["1:5: redundant-parens"])]
+    #[case::expr("(f x)", &["1:1: redundant-parens"])]
+    #[case::body("a = \\x (f x)", &["1:8: redundant-parens"])]
+    #[case::body_qualified("x = \\a (b c.d a)", &["1:8: redundant-parens"])]
+    #[case::body_starts_qualified("x = \\a (list.head a)", &["1:8: redundant-parens"])]
+    #[case::body_qualified_arg("x = \\a (f (nat.inc a) b)", &["1:8: redundant-parens"])]
+    #[case::body_qualified_dot("x = \\a. (b c.d a)", &["1:9: redundant-parens"])]
+    #[case::head_call("a = (f x) y", &["1:5: redundant-parens"])]
+    #[case::head_def("a = (\\x x) y", &[])]
+    #[case::arg_def("a = f (\\x x)", &[])]
+    #[case::operator("a = (+)", &[])]
+    #[case::operator_arg("a = f (+) x", &[])]
+    #[case::operands("infixl 6 +\na = (f b) + (g c)", &["2:5: redundant-parens", "2:13: redundant-parens"])]
+    #[case::operands_infix("infixl 6 +\na = (b + c) + d", &[])]
+    #[case::infix_head("infixl 6 +\na = (b + c) d", &[])]
+    #[case::infix_arg("infixl 6 +\na = f (b + c)", &[])]
+    #[case::let_value("a = let x = (f b) in x", &["1:13: redundant-parens"])]
+    #[case::let_body("a = let x = b in (f x)", &["1:18: redundant-parens"])]
+    #[case::let_arg("a = f (let x = b in x)", &[])]
+    #[case::spaces("a = ( f x )", &["1:5: redundant-parens"])]
+    #[case::multiline("a = (\n  f x\n)", &["1:5: redundant-parens"])]
+    #[case::parens_in_string("a = (f \")(\")", &["1:5: redundant-parens"])]
+    fn check_parens(#[case] input: &str, #[case] expected: &[&str]) {
+        assert_eq!(check(input), expected);
+    }
+
+    #[rstest]
+    #[case::library("a = b\nc = a", &[])]
+    #[case::script("a = b\nc = d\nc", &["1:1: unreachable-definition"])]
+    #[case::script_chain("a = b\nc = a\nc", &[])]
+    #[case::exported("a = b\nc = d\nexport a\nc", &[])]
+    #[case::redefined("a = b\na = c", &["1:1: unreachable-definition", "2:1: redefined-global"])]
+    #[case::self_reference("a = b\na = \\x a x", &["2:1: redefined-global"])]
+    #[case::arg_not_global("a = b\nc = \\a a\nc", &["1:1: unreachable-definition", "2:6: shadowed-global"])]
+    fn check_unreachable(#[case] input: &str, #[case] expected: &[&str]) {
+        assert_eq!(check(input), expected);
+    }
+
+    #[test]
+    fn diagnostic_format() {
+        let input = "const = \\x \\y x";
+        let module = parse(input).unwrap();
+        let diags = lint(&module, input, &HashMap::new());
+        assert_eq!(diags.len(), 1);
+        assert_eq!(
+            diags[0].to_string(),
+            "1:13: unused-argument: argument `y` is never used, rename it to `_y` if that's intended"
+        );
+        assert_eq!(
+            diags[0].to_json(),
+            r#"{"kind": "unused-argument", "message": "argument `y` is never used, rename it to `_y` if that's intended", "span": {"file": null, "start": 12, "end": 13, "line": 1, "col": 13}}"#
+        );
+    }
+}
@@ -1,12 +1,17 @@
 #![warn(clippy::all, clippy::pedantic)]
 
-use clap::{Parser, Subcommand};
+use anyhow::Context;
+use clap::{Parser, Subcommand, ValueEnum};
 use colored::Colorize;
 use rlci::interpreter::run_repl;
-use rlci::interpreter::Session;
-use rlci::parse;
+use rlci::interpreter::{
+    pack_bits, run_blc, IoMode, Session, SkipGlobals, Strategy, Sugar, SyntaxErrors, Term,
+    UndefinedNames, MAX_STEPS,
+};
+use rlci::{format_source, Diagnostic, Dialect, LambdaStyle};
 
-use std::io::{stdin, BufRead};
+use std::io::{stdin, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
 
 // Clap is a Rust library for making nice CLI tools.
 // It has a few methods for describing the interface you want.
@@ -25,19 +30,398 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Parse a module and print its AST.
-    Parse,
+    Parse {
+        /// The format in which to print the AST.
+        #[arg(long, value_enum, default_value_t = AstFormat::Debug)]
+        format: AstFormat,
+        /// The syntax for lambdas, unless the module has a pragma.
+        #[arg(long, value_enum, default_value_t = DialectKind::Standard)]
+        dialect: DialectKind,
+    },
     /// Eval a module and print the last expression result.
-    Eval,
+    Eval {
+        /// The file to evaluate. If not specified, the module is read from stdin.
+        file: Option<PathBuf>,
+        /// Syntactic sugar to use when printing the result.
+        #[arg(long, value_delimiter = ',')]
+        sugar: Vec<SugarKind>,
+        /// A directory where to look for imported files. Can be repeated.
+        #[arg(short = 'I', long = "include")]
+        include: Vec<PathBuf>,
+        /// The syntax for lambdas, unless the module has a pragma.
+        #[arg(long, value_enum, default_value_t = DialectKind::Standard)]
+        dialect: DialectKind,
+        /// The notation in which the input is written.
+        #[arg(long, value_enum, default_value_t = Notation::Lambda)]
+        input_format: Notation,
+        /// The notation in which to print the result. Sugar is ignored for `debruijn`.
+        #[arg(long, value_enum, default_value_t = Notation::Lambda)]
+        format: Notation,
+        /// What to do when an assignment uses a name that isn't defined.
+        #[arg(long, value_enum, default_value_t = UndefinedKind::Warn)]
+        undefined: UndefinedKind,
+        /// The order in which to reduce expressions.
+        #[arg(long, value_enum, default_value_t = StrategyKind::CallByName)]
+        strategy: StrategyKind,
+        /// Replace `λx f x` with `f` when reducing to the normal form.
+        /// Only for normal and applicative order.
+        #[arg(long)]
+        eta: bool,
+        /// How many reduction steps to make before giving up.
+        /// Call-by-name doesn't count steps.
+        #[arg(long, default_value_t = MAX_STEPS)]
+        max_steps: usize,
+    },
+    /// Show each step of evaluating the last expression of a module.
+    ///
+    /// Each line is the term between two steps, with the next redex highlighted.
+    Trace {
+        /// The file to evaluate. If not specified, the module is read from stdin.
+        file: Option<PathBuf>,
+        /// A directory where to look for imported files. Can be repeated.
+        #[arg(short = 'I', long = "include")]
+        include: Vec<PathBuf>,
+        /// The syntax for lambdas, unless the module has a pragma.
+        #[arg(long, value_enum, default_value_t = DialectKind::Standard)]
+        dialect: DialectKind,
+        /// The order in which to reduce the expression.
+        #[arg(long, value_enum, default_value_t = StrategyKind::CallByName)]
+        strategy: StrategyKind,
+        /// Replace `λx f x` with `f` when reducing to the normal form.
+        #[arg(long)]
+        eta: bool,
+        /// Hide steps inside of the global with this name. Can be repeated.
+        #[arg(long, value_delimiter = ',')]
+        skip: Vec<String>,
+        /// Hide steps inside of all globals.
+        #[arg(long, conflicts_with = "skip")]
+        skip_globals: bool,
+        /// Stop after showing this many steps.
+        #[arg(long)]
+        limit: Option<usize>,
+        /// How many steps to make, including hidden ones, before giving up.
+        #[arg(long, default_value_t = MAX_STEPS)]
+        max_steps: usize,
+    },
+    /// Format the source code.
+    ///
+    /// Files are rewritten in place. If no files are given,
+    /// the module is read from stdin and the result is printed to stdout.
+    Fmt {
+        /// The files to format.
+        files: Vec<PathBuf>,
+        /// Don't change anything, only report files that aren't formatted.
+        #[arg(long)]
+        check: bool,
+        /// The symbol to use for lambdas.
+        #[arg(long, value_enum, default_value_t = StyleKind::Backslash)]
+        style: StyleKind,
+        /// The syntax for lambdas, unless the module has a pragma.
+        #[arg(long, value_enum, default_value_t = DialectKind::Standard)]
+        dialect: DialectKind,
+    },
+    /// Find suspicious code: unused arguments, redefined globals, and so on.
+    ///
+    /// The code is not evaluated, only the files it imports are.
+    /// If no files are given, the module is read from stdin.
+    Lint {
+        /// The files to check.
+        files: Vec<PathBuf>,
+        /// How to print the problems found.
+        #[arg(long, value_enum, default_value_t = LintFormat::Human)]
+        format: LintFormat,
+        /// A directory where to look for imported files. Can be repeated.
+        #[arg(short = 'I', long = "include")]
+        include: Vec<PathBuf>,
+        /// The syntax for lambdas, unless the module has a pragma.
+        #[arg(long, value_enum, default_value_t = DialectKind::Standard)]
+        dialect: DialectKind,
+    },
+    /// Encode, decode, and run Binary Lambda Calculus programs.
+    Blc {
+        #[command(subcommand)]
+        command: BlcCommands,
+    },
     /// Run interactive REPL.
-    Repl,
+    Repl {
+        /// A directory where to look for imported files. Can be repeated.
+        #[arg(short = 'I', long = "include")]
+        include: Vec<PathBuf>,
+        /// The syntax for lambdas, unless the input has a pragma.
+        #[arg(long, value_enum, default_value_t = DialectKind::Standard)]
+        dialect: DialectKind,
+        /// What to do when an assignment uses a name that isn't defined.
+        #[arg(long, value_enum, default_value_t = UndefinedKind::Warn)]
+        undefined: UndefinedKind,
+        /// The order in which to reduce expressions. Can be changed with `:strategy`.
+        #[arg(long, value_enum, default_value_t = StrategyKind::CallByName)]
+        strategy: StrategyKind,
+        /// Replace `λx f x` with `f` when reducing to the normal form.
+        /// Can be changed with `:eta`.
+        #[arg(long)]
+        eta: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum BlcCommands {
+    /// Eval a module and print the last expression result as a BLC program.
+    ///
+    /// The result is evaluated only until it's a function. To encode the program
+    /// exactly as written, assign it to a name and put the name on the last line.
+    Encode {
+        /// The file to evaluate. If not specified, the module is read from stdin.
+        file: Option<PathBuf>,
+        /// Write the program packed into bytes instead of `0` and `1` characters.
+        #[arg(long)]
+        packed: bool,
+        /// A directory where to look for imported files. Can be repeated.
+        #[arg(short = 'I', long = "include")]
+        include: Vec<PathBuf>,
+    },
+    /// Read a BLC program and print it as a lambda.
+    ///
+    /// The program can be either a text of `0` and `1` characters or packed into bytes.
+    Decode {
+        /// The file with the program. If not specified, it's read from stdin.
+        file: Option<PathBuf>,
+        /// The notation in which to print the program.
+        #[arg(long, value_enum, default_value_t = Notation::Lambda)]
+        format: Notation,
+    },
+    /// Run a BLC program with stdin as the input and stdout as the output.
+    Run {
+        /// The file with the program, as text or packed into bytes.
+        program: PathBuf,
+        /// How the program sees the input and output.
+        #[arg(long, value_enum, default_value_t = IoModeKind::Bytes)]
+        mode: IoModeKind,
+    },
+}
+
+/// All kinds of syntactic sugar supported by `Sugar`.
+///
+/// The `ValueEnum` derive makes clap accept the variant names as values,
+/// like `--sugar lambdas`.
+#[derive(Clone, Copy, ValueEnum)]
+enum SugarKind {
+    /// Multi-argument definitions: `λa b. a`.
+    Lambdas,
+    /// List literals: `[a, b, c]`.
+    Lists,
+    /// String literals: `"abc"`.
+    Strings,
+}
+
+/// All formats in which `rlci parse` can print the AST.
+#[derive(Clone, Copy, ValueEnum)]
+enum AstFormat {
+    /// Rust debug representation, for debugging the parser. It's not stable.
+    Debug,
+    /// Single-line JSON, for other tools.
+    Json,
+    /// S-expression, to see the structure of the tree.
+    Sexpr,
+    /// The structure of each statement, without names.
+    Short,
+}
+
+/// All formats in which `rlci lint` can print the problems found.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LintFormat {
+    /// One line per problem, `file:line:col: kind: message`.
+    Human,
+    /// A JSON array of all problems, see `Diagnostic::to_json`.
+    Json,
+}
+
+/// Notations in which `rlci eval` can read the input and print the result.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Notation {
+    /// The language itself: `λa λb a`.
+    Lambda,
+    /// A single term with de Bruijn indices: `λ λ 2`.
+    Debruijn,
+}
+
+/// All modes supported by `IoMode`.
+#[derive(Clone, Copy, ValueEnum)]
+enum IoModeKind {
+    /// Lists of bytes (BLC8).
+    Bytes,
+    /// Lists of bits, the output is printed as `0` and `1` characters.
+    Bits,
+}
+
+/// All modes supported by `UndefinedNames`.
+#[derive(Clone, Copy, ValueEnum)]
+enum UndefinedKind {
+    /// Fail the assignment.
+    Error,
+    /// Print a warning.
+    Warn,
+    /// Fail only when the name is evaluated.
+    Ignore,
+}
+
+impl From<UndefinedKind> for UndefinedNames {
+    fn from(kind: UndefinedKind) -> Self {
+        match kind {
+            UndefinedKind::Error => UndefinedNames::Error,
+            UndefinedKind::Warn => UndefinedNames::Warn,
+            UndefinedKind::Ignore => UndefinedNames::Ignore,
+        }
+    }
+}
+
+/// All strategies supported by `Strategy`.
+#[derive(Clone, Copy, ValueEnum)]
+enum StrategyKind {
+    /// Leftmost outermost first, until the normal form.
+    NormalOrder,
+    /// Leftmost innermost first, until the normal form.
+    ApplicativeOrder,
+    /// Arguments aren't evaluated before the call, until the first lambda.
+    CallByName,
+    /// Arguments are evaluated before the call, until the first lambda.
+    CallByValue,
+    /// The same as call-by-name, but each argument is evaluated at most once.
+    CallByNeed,
+}
+
+impl From<StrategyKind> for Strategy {
+    fn from(kind: StrategyKind) -> Self {
+        match kind {
+            StrategyKind::NormalOrder => Strategy::NormalOrder,
+            StrategyKind::ApplicativeOrder => Strategy::ApplicativeOrder,
+            StrategyKind::CallByName => Strategy::CallByName,
+            StrategyKind::CallByValue => Strategy::CallByValue,
+            StrategyKind::CallByNeed => Strategy::CallByNeed,
+        }
+    }
+}
+
+/// All styles supported by `LambdaStyle`.
+#[derive(Clone, Copy, ValueEnum)]
+enum StyleKind {
+    /// `\x x`
+    Backslash,
+    /// `λx x`
+    Lambda,
+}
+
+/// All dialects supported by `Dialect`.
+#[derive(Clone, Copy, ValueEnum)]
+enum DialectKind {
+    /// `λx y. x y`
+    Standard,
+    /// `λxy.x y`
+    Dot,
+}
+
+impl From<DialectKind> for Dialect {
+    fn from(kind: DialectKind) -> Self {
+        match kind {
+            DialectKind::Standard => Dialect::Standard,
+            DialectKind::Dot => Dialect::Dot,
+        }
+    }
 }
 
 fn main() {
     let cli = Cli::parse();
     match &cli.command {
-        Commands::Parse => cmd_parse(&read_stdin()),
-        Commands::Eval => cmd_eval(&read_stdin()),
-        Commands::Repl => run_repl(),
+        Commands::Parse { format, dialect } => {
+            cmd_parse(&read_stdin(), *format, (*dialect).into());
+        }
+        Commands::Eval {
+            file,
+            sugar,
+            include,
+            dialect,
+            input_format,
+            format,
+            undefined,
+            strategy,
+            eta,
+            max_steps,
+        } => cmd_eval(
+            file.as_deref(),
+            make_sugar(sugar),
+            include,
+            (*dialect).into(),
+            (*input_format, *format),
+            (*undefined).into(),
+            ((*strategy).into(), *eta, *max_steps),
+        ),
+        Commands::Trace {
+            file,
+            include,
+            dialect,
+            strategy,
+            eta,
+            skip,
+            skip_globals,
+            limit,
+            max_steps,
+        } => {
+            let skip = match (skip_globals, skip.is_empty()) {
+                (true, _) => SkipGlobals::All,
+                (false, true) => SkipGlobals::Nothing,
+                (false, false) => SkipGlobals::Only(skip.iter().cloned().collect()),
+            };
+            let mut session = make_session(include, (*dialect).into());
+            session.set_strategy((*strategy).into());
+            session.set_eta(*eta);
+            session.set_max_steps(*max_steps);
+            cmd_trace(session, file.as_deref(), &skip, *limit)
+        }
+        Commands::Fmt {
+            files,
+            check,
+            style,
+            dialect,
+        } => {
+            let style = match style {
+                StyleKind::Backslash => LambdaStyle::Backslash,
+                StyleKind::Lambda => LambdaStyle::Lambda,
+            };
+            cmd_fmt(files, *check, style, (*dialect).into())
+        }
+        Commands::Lint {
+            files,
+            format,
+            include,
+            dialect,
+        } => cmd_lint(files, *format, include, (*dialect).into()),
+        Commands::Blc { command } => match command {
+            BlcCommands::Encode {
+                file,
+                packed,
+                include,
+            } => cmd_blc_encode(file.as_deref(), *packed, include),
+            BlcCommands::Decode { file, format } => cmd_blc_decode(file.as_deref(), *format),
+            BlcCommands::Run { program, mode } => {
+                let mode = match mode {
+                    IoModeKind::Bytes => IoMode::Bytes,
+                    IoModeKind::Bits => IoMode::Bits,
+                };
+                cmd_blc_run(program, mode)
+            }
+        },
+        Commands::Repl {
+            include,
+            dialect,
+            undefined,
+            strategy,
+            eta,
+        } => run_repl(
+            include,
+            (*dialect).into(),
+            (*undefined).into(),
+            (*strategy).into(),
+            *eta,
+        ),
     }
 }
 
@@ -50,29 +434,354 @@ fn read_stdin() -> String {
     input
 }
 
-fn cmd_parse(input: &str) -> ! {
-    let (code, msg) = match parse(input) {
-        Ok(module) => (0, format!("{module:#?}").green()),
-        Err(err) => (3, err.to_string().red()),
+fn cmd_parse(input: &str, format: AstFormat, dialect: Dialect) -> ! {
+    // Report all syntax errors at once, so that the user can fix them all
+    // before running the command again.
+    let mut session = Session::new(None);
+    session.set_dialect(dialect);
+    let (module, errors) = session.parse_recovering(input);
+    if !errors.is_empty() {
+        print_errors(&errors);
+        std::process::exit(3);
+    }
+    let output = match format {
+        AstFormat::Debug => format!("{module:#?}").green().to_string(),
+        // Machine-readable formats are never colored,
+        // so that other tools don't have to strip color codes.
+        AstFormat::Json => module.to_json(),
+        AstFormat::Sexpr => module.to_sexpr(),
+        AstFormat::Short => module.short_repr(),
+    };
+    println!("{output}");
+    std::process::exit(0);
+}
+
+/// Format the given files or stdin.
+///
+/// The exit code is 3 if there are syntax errors and 1 if `--check`
+/// has found files that aren't formatted.
+fn cmd_fmt(files: &[PathBuf], check: bool, style: LambdaStyle, dialect: Dialect) -> ! {
+    if files.is_empty() {
+        let input = read_stdin();
+        match format_source(&input, None, style, dialect) {
+            Ok(output) if check && output != input => std::process::exit(1),
+            Ok(_) if check => std::process::exit(0),
+            Ok(output) => print!("{output}"),
+            Err(errors) => {
+                print_errors(&errors);
+                std::process::exit(3);
+            }
+        }
+        std::process::exit(0);
+    }
+    let mut code = 0;
+    for path in files {
+        let input = match std::fs::read_to_string(path) {
+            Ok(input) => input,
+            Err(err) => {
+                println!("{}", format!("cannot read {}: {err}", path.display()).red());
+                std::process::exit(2);
+            }
+        };
+        let output = match format_source(&input, Some(&path.display().to_string()), style, dialect)
+        {
+            Ok(output) => output,
+            Err(errors) => {
+                print_errors(&errors);
+                code = 3;
+                continue;
+            }
+        };
+        if output == input {
+            continue;
+        }
+        if check {
+            println!("{} is not formatted", path.display());
+            code = code.max(1);
+        } else if let Err(err) = std::fs::write(path, output) {
+            println!(
+                "{}",
+                format!("cannot write {}: {err}", path.display()).red()
+            );
+            std::process::exit(2);
+        }
+    }
+    std::process::exit(code);
+}
+
+/// Lint the given files or stdin.
+///
+/// The exit code is 1 if any problems are found, 2 if a file can't be read
+/// or an import fails, and 3 if there are syntax errors.
+fn cmd_lint(files: &[PathBuf], format: LintFormat, include: &[PathBuf], dialect: Dialect) -> ! {
+    let inputs: Vec<(Option<String>, String)> = if files.is_empty() {
+        vec![(None, read_stdin())]
+    } else {
+        files
+            .iter()
+            .map(|path| match std::fs::read_to_string(path) {
+                Ok(input) => (Some(path.display().to_string()), input),
+                Err(err) => {
+                    println!("{}", format!("cannot read {}: {err}", path.display()).red());
+                    std::process::exit(2);
+                }
+            })
+            .collect()
+    };
+    let mut code = 0;
+    let mut diagnostics = Vec::new();
+    for (path, input) in &inputs {
+        // Each file gets a fresh session, so that the names defined
+        // or imported by one file aren't globals for the next one.
+        let mut session = make_session(include, dialect);
+        match session.lint(input, path.as_deref()) {
+            Ok(found) => diagnostics.extend(found),
+            Err(err) if err.downcast_ref::<SyntaxErrors>().is_some() => {
+                println!("{}", format!("{err:?}").red());
+                code = 3;
+            }
+            Err(err) => {
+                println!("{}", format!("{err:?}").red());
+                code = code.max(2);
+            }
+        }
+    }
+    if !diagnostics.is_empty() {
+        code = code.max(1);
+    }
+    match format {
+        LintFormat::Human => {
+            for diag in &diagnostics {
+                println!("{}", diag.to_string().yellow());
+            }
+        }
+        LintFormat::Json => {
+            let items: Vec<_> = diagnostics.iter().map(Diagnostic::to_json).collect();
+            println!("[{}]", items.join(", "));
+        }
+    }
+    std::process::exit(code);
+}
+
+/// Print all syntax errors, separated by an empty line.
+fn print_errors<E: ToString>(errors: &[E]) {
+    let msgs: Vec<_> = errors.iter().map(ToString::to_string).collect();
+    println!("{}", msgs.join("\n\n").red());
+}
+
+fn make_sugar(kinds: &[SugarKind]) -> Sugar {
+    let mut sugar = Sugar::default();
+    for kind in kinds {
+        match kind {
+            SugarKind::Lambdas => sugar.lambdas = true,
+            SugarKind::Lists => sugar.lists = true,
+            SugarKind::Strings => sugar.strings = true,
+        }
+    }
+    sugar
+}
+
+/// Evaluate the file or stdin and print the result.
+///
+/// The notations are for the input and for the output, in that order.
+/// An input in de Bruijn notation is a single term, so it can't have imports.
+/// The reduction is the strategy, if η-reduction is on, and the maximum number of steps.
+fn cmd_eval(
+    file: Option<&Path>,
+    sugar: Sugar,
+    include: &[PathBuf],
+    dialect: Dialect,
+    notations: (Notation, Notation),
+    undefined: UndefinedNames,
+    reduction: (Strategy, bool, usize),
+) -> ! {
+    let (strategy, eta, max_steps) = reduction;
+    let mut session = make_session(include, dialect);
+    session.set_undefined_names(undefined);
+    session.set_strategy(strategy);
+    session.set_eta(eta);
+    session.set_max_steps(max_steps);
+    let (input, output) = notations;
+    let result = match (input, file) {
+        (Notation::Lambda, Some(path)) => session.eval_file(path),
+        (Notation::Lambda, None) => match session.eval_source(&read_stdin(), None) {
+            Ok(val) => Ok(val.cloned()),
+            Err(err) => Err(err),
+        },
+        (Notation::Debruijn, path) => {
+            let input = match path {
+                Some(path) => std::fs::read_to_string(path)
+                    .with_context(|| format!("cannot read {}", path.display())),
+                None => Ok(read_stdin()),
+            };
+            input.and_then(|input| session.eval_debruijn(&input).map(|val| Some(val.clone())))
+        }
+    };
+    // Warnings go to stderr, so that they don't get mixed with the result
+    // when it's passed to another tool.
+    for warning in session.take_warnings() {
+        eprintln!("{}", format!("warning: {warning}").yellow());
+    }
+    let (code, msg) = match result {
+        // The de Bruijn notation is meant for other tools, so it's not colored.
+        Ok(Some(result)) if output == Notation::Debruijn => {
+            (0, result.to_debruijn().to_string().normal())
+        }
+        Ok(Some(result)) => (0, session.repr(&result, sugar).green()),
+        // The module has only comments (or ends with an import or a fixity declaration),
+        // so there is no result to print.
+        Ok(None) => std::process::exit(0),
+        // All syntax errors are reported at once, so that the user
+        // can fix them all before running the command again.
+        Err(err) if err.downcast_ref::<SyntaxErrors>().is_some() => (3, format!("{err:?}").red()),
+        Err(err) => (2, format!("{err:?}").red()),
     };
     println!("{msg}");
     std::process::exit(code);
 }
 
-fn cmd_eval(input: &str) -> ! {
+/// Trace the last expression of the file or stdin.
+///
+/// Lines are printed as soon as they're ready, so an infinite loop
+/// can be seen (and interrupted) right away.
+fn cmd_trace(
+    mut session: Session,
+    file: Option<&Path>,
+    skip: &SkipGlobals,
+    limit: Option<usize>,
+) -> ! {
+    let (input, path) = match file {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(input) => (input, Some(path.to_string_lossy().to_string())),
+            Err(err) => {
+                println!("{}", format!("cannot read {}: {err}", path.display()).red());
+                std::process::exit(2);
+            }
+        },
+        None => (read_stdin(), None),
+    };
+    let result = session.trace(&input, path.as_deref(), skip, limit, &mut |line| {
+        println!("{}", line.to_colored_string());
+    });
+    for warning in session.take_warnings() {
+        eprintln!("{}", format!("warning: {warning}").yellow());
+    }
+    let (code, msg) = match result {
+        Ok(Some(_)) => std::process::exit(0),
+        Ok(None) => (
+            0,
+            format!("stopped after {} steps", limit.unwrap_or(0)).yellow(),
+        ),
+        Err(err) if err.downcast_ref::<SyntaxErrors>().is_some() => (3, format!("{err:?}").red()),
+        Err(err) => (2, format!("{err:?}").red()),
+    };
+    println!("{msg}");
+    std::process::exit(code);
+}
+
+/// Make a session with the stdlib loaded, exiting if the stdlib fails.
+fn make_session(include: &[PathBuf], dialect: Dialect) -> Session<'static> {
     let mut session = Session::new(None);
+    session.set_dialect(dialect);
+    for dir in include {
+        session.add_search_path(dir);
+    }
     if let Err(err) = session.load_stdlib() {
         let msg = format!("{:?}", err.context("failed to load stdlib"));
         println!("{}", msg.red());
         std::process::exit(1);
     }
-    let (code, msg) = match parse(input) {
-        Ok(module) => match session.eval_module(&module) {
-            Ok(result) => (0, result.repr().green()),
-            Err(err) => (2, format!("{err:?}").red()),
+    session
+}
+
+fn cmd_blc_encode(file: Option<&Path>, packed: bool, include: &[PathBuf]) -> ! {
+    let mut session = make_session(include, Dialect::Standard);
+    let result = match file {
+        Some(path) => session.eval_file(path),
+        None => match session.eval_source(&read_stdin(), None) {
+            Ok(val) => Ok(val.cloned()),
+            Err(err) => Err(err),
         },
-        Err(err) => (3, err.to_string().red()),
     };
-    println!("{msg}");
-    std::process::exit(code);
+    let bits = match result.and_then(|val| match val {
+        Some(val) => val.to_debruijn().to_blc(),
+        None => anyhow::bail!("the module has no result to encode"),
+    }) {
+        Ok(bits) => bits,
+        Err(err) if err.downcast_ref::<SyntaxErrors>().is_some() => {
+            println!("{}", format!("{err:?}").red());
+            std::process::exit(3);
+        }
+        Err(err) => {
+            println!("{}", format!("{err:?}").red());
+            std::process::exit(2);
+        }
+    };
+    if packed {
+        let mut stdout = std::io::stdout();
+        if let Err(err) = stdout.write_all(&pack_bits(&bits)) {
+            println!("{}", format!("cannot write the program: {err}").red());
+            std::process::exit(2);
+        }
+    } else {
+        println!("{bits}");
+    }
+    std::process::exit(0);
+}
+
+fn cmd_blc_decode(file: Option<&Path>, format: Notation) -> ! {
+    let term = read_blc(file);
+    match format {
+        Notation::Lambda => println!("{}", term.to_value().repr().green()),
+        Notation::Debruijn => println!("{term}"),
+    }
+    std::process::exit(0);
+}
+
+fn cmd_blc_run(program: &Path, mode: IoMode) -> ! {
+    let term = read_blc(Some(program));
+    let mut input = Vec::new();
+    if let Err(err) = stdin().lock().read_to_end(&mut input) {
+        println!("{}", format!("cannot read the input: {err}").red());
+        std::process::exit(2);
+    }
+    let mut stdout = std::io::stdout().lock();
+    if let Err(err) = run_blc(&term, &input, mode, &mut stdout) {
+        // The output might not end with a newline, so the error would be
+        // glued to it without this one.
+        println!();
+        println!("{}", format!("{err:?}").red());
+        std::process::exit(2);
+    }
+    std::process::exit(0);
+}
+
+/// Read and decode a BLC program from the file or stdin.
+///
+/// Exits with code 2 if the program can't be read and 3 if it's not valid.
+fn read_blc(file: Option<&Path>) -> Term {
+    let input = if let Some(path) = file {
+        std::fs::read(path).with_context(|| format!("cannot read {}", path.display()))
+    } else {
+        let mut input = Vec::new();
+        stdin()
+            .lock()
+            .read_to_end(&mut input)
+            .map(|_| input)
+            .context("cannot read stdin")
+    };
+    let input = match input {
+        Ok(input) => input,
+        Err(err) => {
+            println!("{}", format!("{err:?}").red());
+            std::process::exit(2);
+        }
+    };
+    match Term::from_blc(&input) {
+        Ok(term) => term,
+        Err(err) => {
+            println!("{}", format!("{err:?}").red());
+            std::process::exit(3);
+        }
+    }
 }
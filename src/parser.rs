@@ -1,7 +1,11 @@
-use crate::ast_nodes::{Expr, Module, Stmt};
-use pest::error::Error;
+use crate::ast_nodes::{Assoc, Expr, Module, Span, Stmt};
+use crate::desugar;
+use crate::fixity::{self, Fixities, Fixity, Operator};
+use pest::error::{Error, ErrorVariant, InputLocation};
 use pest::iterators::Pair;
 use pest::Parser;
+use std::ops::Range;
+use std::rc::Rc;
 
 /// The parser, knows how to parse th language syntax using the grammar file.
 
@@ -10,9 +14,58 @@ use pest::Parser;
 #[grammar = "grammar.pest"]
 pub struct LangParser;
 
+/// The syntax flavor for lambdas.
+///
+/// The dialect can be chosen for the whole session (for example, with `--dialect`)
+/// or for a single file with a pragma comment before the first statement:
+///
+/// ```text
+/// # dialect: dot
+/// K = λxy.x
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    /// `λx λy x y` or `λx y. x y`. Arguments are names separated by spaces,
+    /// and the dot is needed only when there are multiple arguments.
+    #[default]
+    Standard,
+    /// `λx.λy.x y` or `λxy.x y`, the notation used in textbooks and papers.
+    /// The dot after arguments is required, and each argument is a single letter,
+    /// so `λxy.` is a function of two arguments, `x` and `y`.
+    /// Also, an argument in braces can go right after the function: `f(g x)`.
+    Dot,
+}
+
 /// Convert a plain text input into AST nodes.
+///
+/// Only the fixity declarations from the input itself are taken into account.
+/// Use `Session::parse` to also know about operators declared in the session.
 #[allow(clippy::result_large_err)]
 pub fn parse(input: &str) -> Result<Module, Error<Rule>> {
+    parse_source(input, None, &mut Fixities::new(), Dialect::Standard)
+}
+
+/// Convert the content of the given file into AST nodes.
+///
+/// The file is not read, the path is only used to be included
+/// into the spans of the nodes and into the syntax error messages.
+#[allow(clippy::result_large_err)]
+pub fn parse_file(input: &str, path: &str) -> Result<Module, Error<Rule>> {
+    parse_source(input, Some(path), &mut Fixities::new(), Dialect::Standard)
+}
+
+/// Parse the input using (and updating) the given fixity declarations.
+///
+/// The path, if given, is included into the spans and syntax errors,
+/// the same as `parse_file` does. The dialect is used unless the input
+/// has a pragma with another one.
+#[allow(clippy::result_large_err)]
+pub(crate) fn parse_source(
+    input: &str,
+    path: Option<&str>,
+    fixities: &mut Fixities,
+    dialect: Dialect,
+) -> Result<Module, Error<Rule>> {
     // The `Rule` enum is generated from the grammar by the `derive(Parser)` above.
     // It lists all rules specified in the grammar.
     // The `parse` method accepts the root rule, which is the `module` in our case.
@@ -32,39 +85,504 @@ pub fn parse(input: &str) -> Result<Module, Error<Rule>> {
     // and error messages aren't particularly friendly.
     //
     // [nom]: https://github.com/rust-bakery/nom
-    let root = LangParser::parse(Rule::module, input)?.next().unwrap();
-    Ok(parse_module(root))
+    let add_path = |err: Error<Rule>| match path {
+        Some(path) => err.with_path(path),
+        None => err,
+    };
+    let dialect = find_dialect(input).map_err(add_path)?.unwrap_or(dialect);
+    let root = LangParser::parse(Rule::module, input)
+        .map_err(add_path)?
+        .next()
+        .unwrap();
+    let file: Option<Rc<str>> = path.map(Into::into);
+    parse_module(root, file.as_ref(), fixities, dialect).map_err(add_path)
+}
+
+/// Convert a plain text input into AST nodes, recovering from syntax errors.
+///
+/// Unlike `parse`, it doesn't stop at the first syntax error. Instead,
+/// it skips the statement with the error and goes on, so that all errors
+/// in the input are reported at once, and the statements without errors
+/// are still available for tools that want to work with them.
+#[must_use]
+pub fn parse_recovering(input: &str) -> (Module, Vec<Error<Rule>>) {
+    parse_source_recovering(input, None, &mut Fixities::new(), Dialect::Standard)
+}
+
+/// The same as `parse_recovering` but for the content of the given file.
+#[must_use]
+pub fn parse_file_recovering(input: &str, path: &str) -> (Module, Vec<Error<Rule>>) {
+    parse_source_recovering(input, Some(path), &mut Fixities::new(), Dialect::Standard)
+}
+
+/// Parse the input, skipping statements with syntax errors.
+///
+/// Pest has no built-in error recovery, it stops as soon as the input
+/// doesn't match the grammar. So, we do it the simple way: parse the input,
+/// find the statement that has the error, replace it with spaces, and try again
+/// until there are no errors left.
+///
+/// Each character is replaced with as many spaces as it takes bytes,
+/// and newlines are kept. So, all the other statements stay exactly
+/// where they were, and both their spans and positions in error messages
+/// point to the right place in the original input.
+pub(crate) fn parse_source_recovering(
+    input: &str,
+    path: Option<&str>,
+    fixities: &mut Fixities,
+    dialect: Dialect,
+) -> (Module, Vec<Error<Rule>>) {
+    let (chunks, _) = split_statements(input);
+    let mut input = input.to_string();
+    let mut errors = Vec::new();
+    loop {
+        let err = match parse_source(&input, path, fixities, dialect) {
+            Ok(module) => return (module, errors),
+            Err(err) => err,
+        };
+        // If everything is skipped, the input is empty, and the parser
+        // complains about that. There is no need to report it: all the input
+        // has errors, and they are already reported.
+        if !errors.is_empty() && input.trim().is_empty() {
+            return (Module { stmts: Vec::new() }, errors);
+        }
+        let pos = match err.location {
+            InputLocation::Pos(pos) | InputLocation::Span((pos, _)) => pos,
+        };
+        errors.push(err);
+        // Find the statement with the error. The error is often reported
+        // at the very end of the statement, after the newline or at the end of input,
+        // so if the statement at the position is already blank or missing,
+        // the error belongs to the last non-blank statement before it.
+        let chunk = chunks
+            .iter()
+            .rev()
+            .filter(|chunk| chunk.start <= pos)
+            .find(|chunk| !input[(*chunk).clone()].trim().is_empty());
+        let Some(chunk) = chunk else {
+            return (Module { stmts: Vec::new() }, errors);
+        };
+        let blank: String = input[chunk.clone()]
+            .chars()
+            .map(|ch| match ch {
+                '\n' => "\n".to_string(),
+                ch => " ".repeat(ch.len_utf8()),
+            })
+            .collect();
+        input.replace_range(chunk.clone(), &blank);
+    }
+}
+
+/// Find the dialect pragma in the input, if any.
+///
+/// The pragma is a comment like `# dialect: dot`. It must be before
+/// the first statement, so that we don't need to parse the input to find it.
+/// If the dialect in the pragma is unknown, it's a syntax error,
+/// since it's surely not what the user wanted.
+#[allow(clippy::result_large_err)]
+pub(crate) fn find_dialect(input: &str) -> Result<Option<Dialect>, Error<Rule>> {
+    let mut offset = 0;
+    for line in input.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let Some(comment) = line.trim().strip_prefix('#') else {
+            if line.trim().is_empty() {
+                continue;
+            }
+            break;
+        };
+        let Some(name) = comment.trim().strip_prefix("dialect:") else {
+            continue;
+        };
+        return match name.trim() {
+            "standard" => Ok(Some(Dialect::Standard)),
+            "dot" => Ok(Some(Dialect::Dot)),
+            name => {
+                let message = format!("unknown dialect `{name}`, expected `standard` or `dot`");
+                let pos = start + line.len() - line.trim_start().len();
+                let pos = pest::Position::new(input, pos).unwrap();
+                Err(Error::new_from_pos(
+                    ErrorVariant::CustomError { message },
+                    pos,
+                ))
+            }
+        };
+    }
+    Ok(None)
+}
+
+/// Check if the input is an unfinished statement that continues on the next line.
+///
+/// That's the case when there are unclosed braces (or square brackets)
+/// or the last line ends with a backslash. The REPL uses it to decide
+/// whether to evaluate the input or to wait for more lines.
+///
+/// The check is much simpler than the grammar, and it doesn't care if the input
+/// is valid or not. Invalid input is considered complete, so that the user
+/// sees the syntax error as soon as possible instead of being stuck
+/// in the multiline mode.
+pub(crate) fn is_incomplete(input: &str) -> bool {
+    let (_, depth) = split_statements(input);
+    depth > 0 || input.trim_end_matches([' ', '\t']).ends_with('\\')
+}
+
+/// Find where each statement in the input starts and ends.
+///
+/// A statement ends with a newline, unless the newline is inside of braces
+/// or escaped with a backslash. Each chunk includes the newline at its end.
+/// Besides the chunks, it returns how many braces are left unclosed at the end.
+///
+/// Same as `is_incomplete`, it's much simpler than the grammar and works
+/// on any input, valid or not. That's what we need for error recovery:
+/// we can't rely on the grammar to tell where the statement ends
+/// when the statement doesn't match the grammar.
+pub(crate) fn split_statements(input: &str) -> (Vec<Range<usize>>, usize) {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut depth: usize = 0;
+    // If the last significant character on the line was a backslash.
+    let mut continued = false;
+    let mut chars = input.char_indices().peekable();
+    while let Some((pos, ch)) = chars.next() {
+        match ch {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            // Everything after `#` is a comment, and braces in it don't count.
+            // The newline at the end is not a part of the comment,
+            // so we leave it for the next iteration.
+            '#' => while chars.next_if(|(_, ch)| *ch != '\n').is_some() {},
+            // The same for string and character literals. A literal ends
+            // with the same quote it started with, unless the quote is escaped.
+            // Literals cannot span multiple lines, so a newline ends it as well.
+            '"' | '\'' => {
+                let mut escaped = false;
+                while let Some((_, next)) = chars.next_if(|(_, ch)| *ch != '\n') {
+                    match next {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        _ if next == ch => break,
+                        _ => {}
+                    }
+                }
+            }
+            '\n' if depth == 0 && !continued => {
+                chunks.push(start..pos + 1);
+                start = pos + 1;
+            }
+            _ => {}
+        }
+        match ch {
+            '\\' => continued = true,
+            ' ' | '\t' | '\r' => {}
+            _ => continued = false,
+        }
+    }
+    if start < input.len() {
+        chunks.push(start..input.len());
+    }
+    (chunks, depth)
+}
+
+/// Get the Unicode code points from a string or character literal.
+///
+/// The grammar makes sure that the literal is quoted and all escape sequences
+/// are valid, so here we only need to strip the quotes and replace escape
+/// sequences with characters they represent. We return code points
+/// rather than a string because `\u{...}` may be not a valid character
+/// (for example, a lone surrogate), but it's still a valid number.
+fn unescape(literal: &str) -> Vec<u32> {
+    let literal = &literal[1..literal.len() - 1];
+    let mut result = Vec::new();
+    let mut chars = literal.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            result.push(ch as u32);
+            continue;
+        }
+        let code = match chars.next().unwrap() {
+            'n' => '\n' as u32,
+            'r' => '\r' as u32,
+            't' => '\t' as u32,
+            '0' => 0,
+            'u' => {
+                // Skip `{`, take everything until `}`, and parse it as a hex number.
+                let hex: String = chars.by_ref().skip(1).take_while(|ch| *ch != '}').collect();
+                u32::from_str_radix(&hex, 16).unwrap()
+            }
+            // Quotes and backslash are escaped as themselves.
+            ch => ch as u32,
+        };
+        result.push(code);
+    }
+    result
+}
+
+/// Get the position of the given pair in the source code.
+///
+/// Pest knows everything about the position of each pair, and all we have
+/// to do is to copy this information into our own type. We can't use pest's `Span`
+/// directly because it holds a reference to the input, and we don't want
+/// the AST to depend on the lifetime of the input (see `parse_statement` below).
+fn make_span(pair: &Pair<Rule>, file: Option<&Rc<str>>) -> Span {
+    let span = pair.as_span();
+    let (line, col) = pair.line_col();
+    Span {
+        file: file.cloned(),
+        start: span.start(),
+        end: span.end(),
+        line,
+        col,
+    }
+}
+
+/// Split an argument of a definition in the dot dialect into single letters.
+///
+/// In `λxy.x`, the grammar sees one argument, `xy`, but in the dot dialect
+/// that's two arguments, `x` and `y`. Each of them gets its own span.
+/// Symbolic names, like `+`, aren't split: `λ+.` is still the same as `λ+ ...`.
+fn split_arg(arg: &Pair<Rule>, file: Option<&Rc<str>>) -> Vec<(String, Span)> {
+    let span = make_span(arg, file);
+    let name = arg.as_str();
+    if !name.chars().all(is_word_char) {
+        return vec![(name.to_owned(), span)];
+    }
+    let mut args: Vec<(String, Span)> = Vec::new();
+    for (index, (offset, ch)) in name.char_indices().enumerate() {
+        let end = span.start + offset + ch.len_utf8();
+        // Combining marks, like the accent in `é` written as two code points,
+        // can't start a name. They belong to the letter before them.
+        if let Some((arg, span)) = args
+            .last_mut()
+            .filter(|_| !unicode_ident::is_xid_start(ch) && !ch.is_ascii())
+        {
+            arg.push(ch);
+            span.end = end;
+            continue;
+        }
+        let span = Span {
+            start: span.start + offset,
+            end,
+            col: span.col + index,
+            ..span.clone()
+        };
+        args.push((ch.to_string(), span));
+    }
+    args
+}
+
+/// Check if the character can be a part of a word-like name, like `add` or `α1`.
+///
+/// It must be kept in sync with `WORD_CHAR` in the grammar.
+pub(crate) fn is_word_char(ch: char) -> bool {
+    ch != 'λ' && unicode_ident::is_xid_continue(ch)
+}
+
+/// Move the arguments of a definition that belong to its body back into the body.
+///
+/// The grammar reads `\x nat.add x 1` as a definition with two arguments,
+/// `x` and `nat`, and the body `add x 1`. But a word with a dot right after it
+/// is a module name, so the definition has only one argument, and the body
+/// is `nat.add x 1`. The arguments between the first one and the module name
+/// are in the body too: `\x f nat.add x` is `\x (f nat.add x)`.
+/// The first argument is never a module name, `\a.a` is `λa a`.
+///
+/// The body is already parsed, so an argument that is an infix operator
+/// can't be put back where it belongs. Then it's an error pointing to the argument.
+#[allow(clippy::result_large_err)]
+fn qualify_body(
+    args: &mut Vec<(String, Span)>,
+    body: &mut Expr,
+    body_start: usize,
+    arg_pairs: &[Pair<Rule>],
+    fixities: &Fixities,
+) -> Result<(), Error<Rule>> {
+    // More than one argument means there is a dot after them,
+    // and if the body is right after the last one, the dot is between them.
+    let [_, moved @ .., module] = &args[..] else {
+        return Ok(());
+    };
+    if body_start != module.1.end + 1
+        || !module.0.chars().all(is_word_char)
+        || !qualify_head(body, body_start, module, moved)
+    {
+        return Ok(());
+    }
+    // The first one is the target of the call, and it's never infix.
+    let infix = moved
+        .iter()
+        .skip(1)
+        .position(|(arg, _)| fixities.get(arg).is_some());
+    if let Some(index) = infix {
+        let msg = "wrap the body in braces, the operator would be infix in it".to_string();
+        return Err(make_error(&arg_pairs[index + 2], msg));
+    }
+    args.truncate(1);
+    Ok(())
+}
+
+/// Make the name the body starts with qualified by the module name,
+/// and put the moved arguments before it, see `qualify_body`.
+///
+/// Calls bind tighter than anything else, so the arguments go right before
+/// the qualified name: `add x 1` with `f` and `nat` is `f nat.add x 1`.
+/// The name is at the offset `pos`, and all calls starting with it now start
+/// with the first moved argument. If there is no plain name at the offset,
+/// like in `\a b.\c a` or `\a b."c"`, nothing changes and it returns `false`.
+fn qualify_head(
+    expr: &mut Expr,
+    pos: usize,
+    module: &(String, Span),
+    moved: &[(String, Span)],
+) -> bool {
+    match expr {
+        Expr::Id { name, span }
+            if span.start == pos && span.end == pos + name.len() && !name.contains('.') =>
+        {
+            let head = Expr::Id {
+                name: format!("{}.{name}", module.0),
+                span: module.1.to(span),
+            };
+            *expr = moved
+                .iter()
+                .map(|(name, span)| Expr::Id {
+                    name: name.clone(),
+                    span: span.clone(),
+                })
+                .chain(std::iter::once(head))
+                .reduce(|target, arg| Expr::Call {
+                    span: target.span().to(arg.span()),
+                    target: Box::new(target),
+                    arg: Box::new(arg),
+                })
+                .unwrap();
+            true
+        }
+        Expr::Call { target, arg, span } if span.start == pos => {
+            let found =
+                qualify_head(target, pos, module, moved) || qualify_head(arg, pos, module, moved);
+            if found {
+                let first = &moved.first().unwrap_or(module).1;
+                *span = first.to(span);
+            }
+            found
+        }
+        _ => false,
+    }
 }
 
-fn parse_module(root: Pair<Rule>) -> Module {
+/// Make a syntax error pointing to the given pair.
+///
+/// Pest produces errors only when the input doesn't match the grammar.
+/// Some errors we can detect only later, when building the AST. This function
+/// makes such errors look exactly the same as the ones produced by pest.
+fn make_error(pair: &Pair<Rule>, message: String) -> Error<Rule> {
+    Error::new_from_span(ErrorVariant::CustomError { message }, pair.as_span())
+}
+
+#[allow(clippy::result_large_err)]
+fn parse_module(
+    root: Pair<Rule>,
+    file: Option<&Rc<str>>,
+    fixities: &mut Fixities,
+    dialect: Dialect,
+) -> Result<Module, Error<Rule>> {
     // The Pair.into_inner method returns an iterator over the rules
     // inside of the given rule. In this case, it iterates over statements
     // extracted by `statement+` part of the `module` rule.
-    let stmts: Vec<Stmt> = root.into_inner().filter_map(parse_statement).collect();
-    Module { stmts }
+    //
+    // The statements are parsed in order, one by one, because a fixity declaration
+    // in one statement affects how the next statements are parsed.
+    let mut stmts: Vec<Stmt> = Vec::new();
+    for pair in root.into_inner() {
+        if let Some(stmt) = parse_statement(pair, file, fixities, dialect)? {
+            stmts.push(stmt);
+        }
+    }
+    Ok(Module { stmts })
 }
 
-fn parse_statement(root: Pair<Rule>) -> Option<Stmt> {
+#[allow(clippy::result_large_err)]
+fn parse_statement(
+    root: Pair<Rule>,
+    file: Option<&Rc<str>>,
+    fixities: &mut Fixities,
+    dialect: Dialect,
+) -> Result<Option<Stmt>, Error<Rule>> {
     match root.as_rule() {
         Rule::statement => {
-            // The statement includes only one subpair, either an assignment
-            // or an expression. We parse it recursively.
+            // The statement includes only one subpair, either an assignment,
+            // a fixity declaration, or an expression. We parse it recursively.
             let subpair = root.into_inner().next().unwrap();
-            parse_statement(subpair)
+            parse_statement(subpair, file, fixities, dialect)
         }
 
         Rule::assignment => {
+            // The span of the assignment must be extracted before `into_inner`
+            // because `into_inner` consumes the pair.
+            let span = make_span(&root, file);
             // The assignment rule has exactly 2 pairs: the target and the expression.
             let mut subpairs = root.into_inner();
             let p1 = subpairs.next().unwrap();
             let p2 = subpairs.next().unwrap();
-            Some(Stmt::Assign {
+            Ok(Some(Stmt::Assign {
                 // I clone the string here because I don't want to deal with lifetimes.
                 // Otherwise, we'd have to ensure that the user input is lives as long
                 // (or longer) as the parsed AST.
                 target: p1.as_str().to_string(),
-                expr: Box::new(parse_expression(p2)),
-            })
+                expr: Box::new(parse_expression(p2, file, fixities, dialect)?),
+                span,
+            }))
+        }
+
+        Rule::import => {
+            let span = make_span(&root, file);
+            let literal = root.into_inner().next().unwrap();
+            // A string literal may have code points that aren't valid characters,
+            // like `\u{D800}`. It's fine for a list of numbers but not for a path.
+            let path: Option<String> = unescape(literal.as_str())
+                .into_iter()
+                .map(char::from_u32)
+                .collect();
+            let Some(path) = path else {
+                let msg = "the path has invalid characters".to_string();
+                return Err(make_error(&literal, msg));
+            };
+            Ok(Some(Stmt::Import { path, span }))
+        }
+
+        Rule::export => {
+            let span = make_span(&root, file);
+            let names = root
+                .into_inner()
+                .map(|pair| pair.as_str().to_string())
+                .collect();
+            Ok(Some(Stmt::Export { names, span }))
+        }
+
+        Rule::fixity => {
+            let span = make_span(&root, file);
+            // The fixity rule has the kind, the precedence,
+            // and then one or more operators.
+            let mut subpairs = root.into_inner();
+            let assoc = match subpairs.next().unwrap().as_str() {
+                "infixl" => Assoc::Left,
+                "infixr" => Assoc::Right,
+                _ => Assoc::None,
+            };
+            // The grammar allows only a single digit, so it always fits.
+            let precedence: u8 = subpairs.next().unwrap().as_str().parse().unwrap();
+            let operators: Vec<String> = subpairs.map(|pair| pair.as_str().to_string()).collect();
+            // Declare the operators right away, so that
+            // the next statements can already use them.
+            for name in &operators {
+                fixities.declare(name, Fixity { assoc, precedence });
+            }
+            Ok(Some(Stmt::Fixity {
+                assoc,
+                precedence,
+                operators,
+                span,
+            }))
         }
 
         Rule::expression => {
@@ -73,8 +591,9 @@ fn parse_statement(root: Pair<Rule>) -> Option<Stmt> {
             // and it would work just fine. Unwrapping it here is an optimization
             // to have one fewer recursive function call.
             let subpair = root.into_inner().next().unwrap();
-            let expr = parse_expression(subpair);
-            Some(Stmt::Expr { expr })
+            let expr = parse_expression(subpair, file, fixities, dialect)?;
+            let span = expr.span().clone();
+            Ok(Some(Stmt::Expr { expr, span }))
         }
 
         // For some reason, pest included EOI in the list of generated rules
@@ -83,7 +602,7 @@ fn parse_statement(root: Pair<Rule>) -> Option<Stmt> {
         //
         // It's possible that some other cases in the future will also return `None`.
         // For example, if you consider comments a statement and don't silence the token.
-        Rule::EOI => None,
+        Rule::EOI => Ok(None),
 
         // If you don't add an explicit catch-all branch for `match`,
         // Rust will report that the match isn't exhaustive because `Rule`
@@ -95,39 +614,217 @@ fn parse_statement(root: Pair<Rule>) -> Option<Stmt> {
     }
 }
 
-fn parse_expression(root: Pair<Rule>) -> Expr {
-    match root.as_rule() {
+#[allow(clippy::result_large_err)]
+fn parse_expression(
+    root: Pair<Rule>,
+    file: Option<&Rc<str>>,
+    fixities: &Fixities,
+    dialect: Dialect,
+) -> Result<Expr, Error<Rule>> {
+    let expr = match root.as_rule() {
         Rule::expression => {
             let subpair = root.into_inner().next().unwrap();
-            parse_expression(subpair)
+            parse_expression(subpair, file, fixities, dialect)?
         }
         Rule::definition => {
-            let mut subpairs = root.into_inner();
-            let p1 = subpairs.next().unwrap();
-            let p2 = subpairs.next().unwrap();
-            Expr::Def {
-                arg: p1.as_str().to_owned(),
-                expr: Box::new(parse_expression(p2)),
+            let span = make_span(&root, file);
+            let text = root.as_str();
+            // The definition has one or more identifiers (arguments)
+            // and then exactly one expression (the body).
+            let mut subpairs: Vec<_> = root.into_inner().collect();
+            let body = subpairs.pop().unwrap();
+            let body_start = body.as_span().start();
+            let mut args: Vec<(String, Span)> = match dialect {
+                Dialect::Standard => subpairs
+                    .iter()
+                    .map(|arg| (arg.as_str().to_owned(), make_span(arg, file)))
+                    .collect(),
+                Dialect::Dot => {
+                    // The grammar allows the definition without the dot, `λx x`,
+                    // but in the dot dialect it's not an option.
+                    let last = subpairs.last().unwrap().as_span().end() - span.start;
+                    let first = body.as_span().start() - span.start;
+                    if !text[last..first].contains('.') {
+                        let msg = "expected `.` after the arguments".to_string();
+                        return Err(make_error(&body, msg));
+                    }
+                    subpairs
+                        .iter()
+                        .flat_map(|arg| split_arg(arg, file))
+                        .collect()
+                }
+            };
+            let mut expr = parse_expression(body, file, fixities, dialect)?;
+            // `\x nat.add x 1` is `\x (nat.add x 1)`, see `qualify_body`.
+            if dialect == Dialect::Standard {
+                qualify_body(&mut args, &mut expr, body_start, &subpairs, fixities)?;
+            }
+            // Desugar `λa b c. body` into `λa λb λc body`.
+            // We go from right to left, wrapping the body into a new definition
+            // for each argument. Each nested definition spans from its argument
+            // to the end of the whole definition.
+            for (arg, arg_span) in args.into_iter().rev() {
+                expr = Expr::Def {
+                    arg,
+                    expr: Box::new(expr),
+                    span: arg_span.to(&span),
+                };
             }
+            // The outermost definition should include the lambda symbol.
+            if let Expr::Def { span: def_span, .. } = &mut expr {
+                *def_span = span;
+            }
+            expr
         }
-        Rule::call => root
-            .into_inner()
-            .map(parse_expression)
-            .reduce(|target, arg| Expr::Call {
-                target: Box::new(target),
-                arg: Box::new(arg),
-            })
-            .unwrap(),
-        Rule::identifier => Expr::Id {
+        // Desugar `let x = a, y = b in c` into `(\x (\y c) b) a`.
+        // Same as with multi-argument definitions, we go from right to left,
+        // wrapping the body into a definition and a call for each binding.
+        Rule::let_in => {
+            let span = make_span(&root, file);
+            let mut subpairs: Vec<_> = root.into_inner().collect();
+            let body = subpairs.pop().unwrap();
+            let mut expr = parse_expression(body, file, fixities, dialect)?;
+            for binding in subpairs.into_iter().rev() {
+                let binding_span = make_span(&binding, file);
+                let mut subpairs = binding.into_inner();
+                let name = subpairs.next().unwrap();
+                let value = subpairs.next().unwrap();
+                let def = Expr::Def {
+                    arg: name.as_str().to_owned(),
+                    expr: Box::new(expr),
+                    span: binding_span,
+                };
+                expr = Expr::Call {
+                    target: Box::new(def),
+                    arg: Box::new(parse_expression(value, file, fixities, dialect)?),
+                    span: span.clone(),
+                };
+            }
+            expr
+        }
+        Rule::call => parse_call(root, file, fixities, dialect)?,
+        Rule::list => {
+            let span = make_span(&root, file);
+            let items = root
+                .into_inner()
+                .map(|pair| parse_expression(pair, file, fixities, dialect))
+                .collect::<Result<_, _>>()?;
+            desugar::list(items, &span)
+        }
+        Rule::string => {
+            let span = make_span(&root, file);
+            let codes = unescape(root.as_str());
+            let items = codes
+                .into_iter()
+                .map(|code| desugar::numeral(code.into(), &span))
+                .collect();
+            desugar::list(items, &span)
+        }
+        Rule::char => {
+            let span = make_span(&root, file);
+            let code = unescape(root.as_str())[0];
+            desugar::numeral(code.into(), &span)
+        }
+        // An operator in braces is just a name, the braces only make sure
+        // it's not treated as an infix operator.
+        // A qualified name is a name too, the scope knows how to look it up.
+        Rule::identifier | Rule::operator | Rule::qualified => Expr::Id {
             name: root.as_str().parse().unwrap(),
+            span: make_span(&root, file),
         },
         _ => unreachable!(),
+    };
+    Ok(expr)
+}
+
+/// Parse a call, resolving infix operators in it.
+///
+/// The call is split by infix operators into segments. Each segment
+/// is a regular call (or a single atom), and the operators are then
+/// applied to the segments according to their fixity. So, `f a + g b`
+/// is split into `f a` and `g b`, and the result is `+ (f a) (g b)`.
+///
+/// Each call in the segment spans from the start of the first target
+/// to the end of its own argument. So, for `a b c` the inner call `a b`
+/// covers `a b` and the outer one covers the whole `a b c`.
+#[allow(clippy::result_large_err)]
+fn parse_call(
+    root: Pair<Rule>,
+    file: Option<&Rc<str>>,
+    fixities: &Fixities,
+    dialect: Dialect,
+) -> Result<Expr, Error<Rule>> {
+    let mut segments: Vec<Vec<Expr>> = vec![Vec::new()];
+    let mut operators: Vec<(Operator, Pair<Rule>)> = Vec::new();
+    let text = root.as_str();
+    let start = root.as_span().start();
+    let mut prev_end = start;
+    for (index, pair) in root.into_inner().enumerate() {
+        // The grammar allows an argument in braces to go right after
+        // the previous atom, `f(x)`, without a space. It's common in the dot dialect,
+        // `(λx.x x)(λx.x x)`, but in the standard one, we want the space.
+        // The braces are silent, so we look at the text between two atoms:
+        // it ends with `(` and maybe some spaces after it, and if there is no space
+        // before `(`, there is nothing else or the closing brace of the previous atom.
+        let gap = &text[prev_end - start..pair.as_span().start() - start];
+        prev_end = pair.as_span().end();
+        let before = gap.trim_end().strip_suffix('(').unwrap_or(" ");
+        let joined = before.is_empty() || before.ends_with(')');
+        if index > 0 && dialect == Dialect::Standard && joined {
+            let msg = "expected a space before the argument".to_string();
+            return Err(make_error(&pair, msg));
+        }
+        // The first atom is never an infix operator. And an operator
+        // in braces is not an identifier but an `operator`, so it doesn't match.
+        let fixity = match pair.as_rule() {
+            Rule::identifier if index > 0 => fixities.get(pair.as_str()),
+            _ => None,
+        };
+        let Some(fixity) = fixity else {
+            segments
+                .last_mut()
+                .unwrap()
+                .push(parse_expression(pair, file, fixities, dialect)?);
+            continue;
+        };
+        if segments.last().unwrap().is_empty() {
+            let msg = format!("expected an operand before `{}`", pair.as_str());
+            return Err(make_error(&pair, msg));
+        }
+        let op = Operator {
+            name: pair.as_str().to_string(),
+            fixity,
+            span: make_span(&pair, file),
+        };
+        operators.push((op, pair));
+        segments.push(Vec::new());
+    }
+    if segments.last().unwrap().is_empty() {
+        let (op, pair) = operators.last().unwrap();
+        let msg = format!("expected an operand after `{}`", op.name);
+        return Err(make_error(pair, msg));
     }
+
+    let mut operands = segments.into_iter().map(|segment| {
+        segment
+            .into_iter()
+            .reduce(|target, arg| Expr::Call {
+                span: target.span().to(arg.span()),
+                target: Box::new(target),
+                arg: Box::new(arg),
+            })
+            .unwrap()
+    });
+    let first = operands.next().unwrap();
+    let (operators, pairs): (Vec<_>, Vec<_>) = operators.into_iter().unzip();
+    let rest = operators.into_iter().zip(operands).collect();
+    fixity::resolve(first, rest).map_err(|(index, msg)| make_error(&pairs[index], msg))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use pest::error::LineColLocation;
     use rstest::rstest;
 
     #[rstest]
@@ -155,6 +852,73 @@ mod tests {
     #[case::assign_punct(r"+ = \a \b a b", "let(def(def(call(id, id))))")]
     #[case(r"add = \a \b + a b", "let(def(def(call(call(id, id), id))))")]
     #[case::alias(r"add = +", "let(id)")]
+    #[case::multiline("f (a\n b)", "call(id, call(id, id))")]
+    #[case::multiline("f (a\n\n  b\n)", "call(id, call(id, id))")]
+    #[case::multiline("(\\a\n a) b", "call(def(id), id)")]
+    #[case::multiline("f (\n  \\x x\n)", "call(id, def(id))")]
+    #[case::multiline("x = \\f (\\x\n x)", "let(def(def(id)))")]
+    #[case::multiline("f (g (a\n b)\n c)", "call(id, call(call(id, call(id, id)), id))")]
+    #[case::continuation("a \\\n b", "call(id, id)")]
+    #[case::continuation("a \\  \n  b c", "call(call(id, id), id)")]
+    #[case::continuation("\\x \\\n x", "def(id)")]
+    #[case::multi_arg(r"\a b c. a", "def(def(def(id)))")]
+    #[case::multi_arg(r"λa b. a b", "def(def(call(id, id)))")]
+    #[case::multi_arg(r"\a  b .a", "def(def(id))")]
+    #[case::multi_arg(r"\a. a", "def(id)")]
+    #[case::multi_arg(r"\a.a", "def(id)")]
+    #[case::multi_arg(r"\a b.\c a", "def(def(def(id)))")]
+    #[case::multi_arg(r"\a b. (\x x) b", "def(def(call(def(id), id)))")]
+    #[case::multi_arg(
+        r"S = \a b c. a c (b c)",
+        "let(def(def(def(call(call(id, id), call(id, id))))))"
+    )]
+    #[case::multi_arg(r"f (\a b. a)", "call(id, def(def(id)))")]
+    #[case::let_in(r"let x = a in x", "call(def(id), id)")]
+    #[case::let_in(r"let x=a in f x", "call(def(call(id, id)), id)")]
+    #[case::let_in(r"let x = a b in x", "call(def(id), call(id, id))")]
+    #[case::let_in(r"let x = a, y = x in y", "call(def(call(def(id), id)), id)")]
+    #[case::let_in(r"let x = a , y = b in y", "call(def(call(def(id), id)), id)")]
+    #[case::let_in(r"let f = \x x in f", "call(def(id), def(id))")]
+    #[case::let_in(r"let x = a in let y = b in y", "call(def(call(def(id), id)), id)")]
+    #[case::let_in(r"\a let x = a in x", "def(call(def(id), id))")]
+    #[case::let_in(r"f (let x = a in x)", "call(id, call(def(id), id))")]
+    #[case::let_in("f (let x = a\n  in x)", "call(id, call(def(id), id))")]
+    #[case::let_in(r"z = let x = a in x", "let(call(def(id), id))")]
+    #[case::keyword_prefix(r"index inner letter", "call(call(id, id), id)")]
+    #[case::keyword_prefix(r"imports important", "call(id, id)")]
+    #[case::unicode(r"α = λβ β", "let(def(id))")]
+    #[case::unicode(r"Ω ñandú café", "call(call(id, id), id)")]
+    #[case::unicode(r"𝐘 f", "call(id, id)")]
+    #[case::unicode(r"λΛ Λ", "def(id)")]
+    #[case::unicode(r"x_1 ℕ", "call(id, id)")]
+    #[case::unicode(r"let iné = a in iné", "call(def(id), id)")]
+    #[case::unicode(r"∀ α", "call(id, id)")]
+    #[case::import(r#"import "lib/bool.rb""#, "import")]
+    #[case::import(r#"import "a\"b.rb"  # comment"#, "import")]
+    #[case::export(r"export head tail", "export")]
+    #[case::export(r"export + <=", "export")]
+    #[case::qualified(r"nat.add 2 3", "call(call(id, id), id)")]
+    #[case::qualified(r"f nat.+", "call(id, id)")]
+    #[case::qualified(r"x = \a (list.head a)", "let(def(call(id, id)))")]
+    #[case::qualified(r"x = \a. list.head a", "let(def(call(id, id)))")]
+    #[case::qualified(r"x = \a list.head a", "let(def(call(id, id)))")]
+    #[case::qualified(r"\x nat.add x 1", "def(call(call(id, id), id))")]
+    #[case::qualified(r"\x f nat.add x", "def(call(call(id, id), id))")]
+    #[case::qualified(r"\a b.a", "def(id)")]
+    #[case::qualified(r"\a b. a", "def(def(id))")]
+    #[case::qualified(r"\a b.\c a", "def(def(def(id)))")]
+    #[case::qualified(r"\a b.b.c", "def(def(id))")]
+    #[case::keyword_prefix(r"exports exported", "call(id, id)")]
+    #[case::string(r#"f "" x"#, "call(call(id, call(call(def(def(def(call(call(id, id), id)))), def(def(id))), def(def(id)))), id)")]
+    #[case::char(r"f '\u{2}' x", "call(call(id, numeral), id)")]
+    #[case::char(r"f '🦀' x", "call(call(id, numeral), id)")]
+    #[case::string(r#"f "🦀""#, "call(id, call(call(def(def(def(call(call(id, id), id)))), def(def(id))), call(call(def(def(def(call(call(id, id), id)))), numeral), call(call(def(def(def(call(call(id, id), id)))), def(def(id))), def(def(id))))))")]
+    #[case::comment("add 2 3  # five", "call(call(id, id), id)")]
+    #[case::comment("add 2 3# five\n", "call(call(id, id), id)")]
+    #[case::comment("# sum\nadd 2 3", "call(call(id, id), id)")]
+    #[case::comment("# sum\n\n# more\nadd 2 3\n# end", "call(call(id, id), id)")]
+    #[case::comment("x = \\a a # id", "let(def(id))")]
+    #[case::comment("f (a # first\n b # second\n)", "call(id, call(id, id))")]
     fn smoke_parse_stmt_ok(#[case] input: &str, #[case] exp: &str) {
         let module = parse(input).unwrap();
         assert_eq!(module.stmts.len(), 1);
@@ -164,6 +928,7 @@ mod tests {
 
     #[rstest]
     #[case(r"")]
+    #[case("  \n ")]
     #[case(r"\x")]
     #[case(r"a \x")]
     #[case(r"id = ")]
@@ -171,7 +936,441 @@ mod tests {
     #[case(r"(a)")]
     #[case(r"(((\a a)))")]
     #[case(r"\a a \b b a")]
+    #[case::unclosed("f (a\n b")]
+    #[case::let_in(r"let x = a")]
+    #[case::let_in(r"let in x")]
+    #[case::let_in(r"f let x = a in x")]
+    #[case::keyword(r"let = a")]
+    #[case::list(r"[")]
+    #[case::list(r"[a b")]
+    #[case::list(r"[a,, b]")]
+    #[case::list(r"[,]")]
+    #[case::string(r#""abc"#)]
+    #[case::string("\"a\nb\"")]
+    #[case::string(r#""\x""#)]
+    #[case::string(r#""\u{}""#)]
+    #[case::char(r"''")]
+    #[case::char(r"'ab'")]
+    #[case::keyword(r"\in in")]
+    #[case::multi_arg(r"\a b.")]
+    #[case::multi_arg(r"\. a")]
+    #[case::newline_in_def("\\x\nx")]
+    #[case::fixity(r"infixl 10 +")]
+    #[case::fixity(r"infixl + 6")]
+    #[case::fixity(r"infixl 6")]
+    #[case::fixity(r"infixl 6 add")]
+    #[case::fixity(r"infixl 6 =")]
+    #[case::fixity(r"f infixl 6 +")]
+    #[case::keyword(r"infix = a")]
+    #[case::import(r"import a")]
+    #[case::import(r#"import"a""#)]
+    #[case::import(r#"f import "a""#)]
+    #[case::import(r#"import "\u{D800}""#)]
+    #[case::keyword(r"import = a")]
+    #[case::export(r"export")]
+    #[case::export(r"export nat.add")]
+    #[case::export(r"f export a")]
+    #[case::keyword(r"export = a")]
+    #[case::qualified(r"nat.add = a")]
+    #[case::qualified(r"nat. add")]
+    #[case::operator(r"= = a")]
+    #[case::unicode(r"aλ = a")]
+    #[case::unicode(r"λ = a")]
+    #[case::unicode(r"a = λ")]
     fn smoke_parse_stmt_err(#[case] input: &str) {
         assert!(parse(input).is_err());
     }
+
+    // List literals are desugared into lambdas,
+    // so we compare them with the equivalent code without literals.
+    #[rstest]
+    #[case::empty(r"[]", r"C T T")]
+    #[case::empty(r"[ ]", r"C T T")]
+    #[case::one(r"[x]", r"C F (C x (C T T))")]
+    #[case::two(r"[x, y]", r"C F (C x (C F (C y (C T T))))")]
+    #[case::spaces(r"[ x ,y ]", r"C F (C x (C F (C y (C T T))))")]
+    #[case::trailing_comma(r"[x, y,]", r"C F (C x (C F (C y (C T T))))")]
+    #[case::multiline("[\n  x,\n  y,\n]", r"C F (C x (C F (C y (C T T))))")]
+    #[case::exprs(r"[f x, \a a]", r"C F (C (f x) (C F (C (\a a) (C T T))))")]
+    #[case::nested(r"[[]]", r"C F (C (C T T) (C T T))")]
+    #[case::call(r"f [x] y", r"f (C F (C x (C T T))) y")]
+    fn parse_list(#[case] input: &str, #[case] exp: &str) {
+        let exp = exp
+            .replace('C', r"(\a \b \c c a b)")
+            .replace('T', r"(\a \b a)")
+            .replace('F', r"(\a \b b)");
+        let actual = parse(input).unwrap();
+        let expected = parse(&exp).unwrap();
+        assert_eq!(actual.stmts[0].short_repr(), expected.stmts[0].short_repr());
+    }
+
+    #[rstest]
+    #[case("a b", false)]
+    #[case("(a b)", false)]
+    #[case("(a b", true)]
+    #[case("f (a\n (b c)", true)]
+    #[case("f (a\n (b c))", false)]
+    #[case("a \\", true)]
+    #[case("a \\  ", true)]
+    #[case("a \\\n b", false)]
+    #[case("a # (", false)]
+    #[case("(a b))", false)]
+    #[case("[a,", true)]
+    #[case("[a, b]", false)]
+    #[case("f \"(\"", false)]
+    #[case("f \"\\\"(\"", false)]
+    #[case("f '(' (", true)]
+    #[case("f \"#\" (", true)]
+    fn incomplete(#[case] input: &str, #[case] exp: bool) {
+        assert_eq!(is_incomplete(input), exp);
+    }
+
+    #[rstest]
+    #[case(r#""""#, &[])]
+    #[case(r#""hi""#, &[104, 105])]
+    #[case(r#""λ!""#, &[955, 33])]
+    #[case(r#""a\"b""#, &[97, 34, 98])]
+    #[case(r#""\n\t\r\0\\""#, &[10, 9, 13, 0, 92])]
+    #[case(r#""\u{1F980}\u{a}""#, &[0x1F980, 10])]
+    #[case(r"'a'", &[97])]
+    #[case(r"'\''", &[39])]
+    fn unescape_literal(#[case] input: &str, #[case] exp: &[u32]) {
+        assert_eq!(unescape(input), exp);
+    }
+
+    #[rstest]
+    #[case("#")]
+    #[case("# just a comment")]
+    #[case("# just a comment\n")]
+    #[case("\n  # one\n\n# two\n")]
+    fn comments_only(#[case] input: &str) {
+        let module = parse(input).unwrap();
+        assert!(module.stmts.is_empty());
+    }
+
+    #[test]
+    fn comments_between_statements() {
+        let module = parse("a b # one\n# two\n\nc\n  # three\nd").unwrap();
+        assert_eq!(module.stmts.len(), 3);
+    }
+
+    #[test]
+    fn newline_outside_braces_ends_statement() {
+        let module = parse("f (a\n b) c\nd e").unwrap();
+        assert_eq!(module.stmts.len(), 2);
+        assert_eq!(
+            module.stmts[0].short_repr(),
+            "call(call(id, call(id, id)), id)"
+        );
+        assert_eq!(module.stmts[1].short_repr(), "call(id, id)");
+    }
+
+    #[rstest]
+    #[case::ok("a\nb", 2, &[])]
+    #[case::one("a\nb =\nc", 2, &["2:4"])]
+    #[case::many("a = \\x\nb\nc = ]\nd", 2, &["1:5", "3:5"])]
+    #[case::first("(\nb", 0, &["2:1"])]
+    #[case::last("a\n\\", 1, &["2:2"])]
+    #[case::all("\\x\n]", 0, &["1:1", "2:1"])]
+    #[case::multiline("f (a\n  b c\n) ]\nd", 1, &["3:3"])]
+    #[case::continuation("a \\\n  b ]\nd", 1, &["2:5"])]
+    #[case::comment("a # (\n(b\nc", 1, &["2:2"])]
+    #[case::string("a \"(\"\n]\nc", 2, &["2:1"])]
+    #[case::operators("infix 4 ==\na == b == c\nd", 2, &["2:8"])]
+    #[case::empty("", 0, &["1:1"])]
+    fn recovering(#[case] input: &str, #[case] stmts: usize, #[case] errors: &[&str]) {
+        let (module, errs) = parse_recovering(input);
+        assert_eq!(module.stmts.len(), stmts);
+        let locations: Vec<_> = errs
+            .iter()
+            .map(|err| match err.line_col {
+                LineColLocation::Pos((line, col)) | LineColLocation::Span((line, col), _) => {
+                    format!("{line}:{col}")
+                }
+            })
+            .collect();
+        assert_eq!(locations, errors);
+    }
+
+    #[test]
+    fn recovering_keeps_positions() {
+        let (module, errs) = parse_file_recovering("λx ]\nid  λ\nid  abc", "a.rb");
+        assert_eq!(errs.len(), 2);
+        assert!(errs[0].to_string().contains("a.rb:1:"));
+        let Stmt::Expr { expr, .. } = &module.stmts[0] else {
+            panic!("bad statement")
+        };
+        assert_eq!(expr.span().to_string(), "a.rb:3:1");
+        let Expr::Call { arg, .. } = expr else {
+            panic!("bad expression")
+        };
+        assert_eq!(arg.span().to_string(), "a.rb:3:5");
+        assert_eq!(arg.span().start, "λx ]\nid  λ\nid  ".len());
+    }
+
+    /// Represent the expression as an S-expression with all names included.
+    ///
+    /// Unlike `short_repr`, it shows how operators are resolved.
+    /// Calls are flattened, so `(f a b)` is `f` called with `a` and then with `b`.
+    fn sexpr(expr: &Expr) -> String {
+        match expr {
+            Expr::Id { name, .. } => name.clone(),
+            Expr::Numeral { n, .. } => format!("#{n}"),
+            Expr::Def { arg, expr, .. } => format!("(λ{arg} {})", sexpr(expr)),
+            Expr::Call { .. } => {
+                let mut parts = Vec::new();
+                let mut node = expr;
+                while let Expr::Call { target, arg, .. } = node {
+                    parts.push(sexpr(arg));
+                    node = target;
+                }
+                parts.push(sexpr(node));
+                parts.reverse();
+                format!("({})", parts.join(" "))
+            }
+        }
+    }
+
+    const FIXITIES: &str = "infixl 6 + -\ninfixl 7 *\ninfixr 5 ++\ninfix 4 ==\ninfixr 0 $\n";
+
+    #[rstest]
+    #[case(r"a + b", "(+ a b)")]
+    #[case(r"a + b * c", "(+ a (* b c))")]
+    #[case(r"a * b + c", "(+ (* a b) c)")]
+    #[case(r"a - b - c", "(- (- a b) c)")]
+    #[case(r"a - b + c", "(+ (- a b) c)")]
+    #[case(r"a ++ b ++ c", "(++ a (++ b c))")]
+    #[case(r"a == b + c * d", "(== a (+ b (* c d)))")]
+    #[case(r"a * b == c - d", "(== (* a b) (- c d))")]
+    #[case(r"f $ g $ h x", "($ f ($ g (h x)))")]
+    #[case(r"f a + g b c", "(+ (f a) (g b c))")]
+    #[case(r"(a + b) * c", "(* (+ a b) c)")]
+    #[case(r"\x x + a", "(λx (+ x a))")]
+    #[case(r"let x = a + b in x", "((λx x) (+ a b))")]
+    #[case::prefix(r"+ a b", "(+ a b)")]
+    #[case::prefix(r"+ a b * c", "(* (+ a b) c)")]
+    #[case::braces(r"foldl (+) 0 xs", "(foldl + 0 xs)")]
+    #[case::braces("f ( ==\n)", "(f ==)")]
+    #[case::undeclared(r"a & b", "(a & b)")]
+    #[case::with_eq(r"a <= b", "(a <= b)")]
+    fn parse_operators(#[case] input: &str, #[case] exp: &str) {
+        let module = parse(&format!("{FIXITIES}{input}")).unwrap();
+        let Stmt::Expr { expr, .. } = module.stmts.last().unwrap() else {
+            panic!("bad statement")
+        };
+        assert_eq!(sexpr(expr), exp);
+    }
+
+    // A word with a dot right after it is a module name,
+    // and everything after the first argument is the body.
+    #[rstest]
+    #[case(r"\x nat.add x 1", "(λx (nat.add x 1))")]
+    #[case(r"\x f nat.add x", "(λx (f nat.add x))")]
+    #[case(r"\x f g nat.+", "(λx (f g nat.+))")]
+    #[case(r"\x f nat.add x + 1", "(λx (+ (f nat.add x) 1))")]
+    #[case(r"\x y. nat.add x y", "(λx (λy (nat.add x y)))")]
+    #[case(r"\x + nat.add", "(λx (+ nat.add))")]
+    fn parse_qualified_body(#[case] input: &str, #[case] exp: &str) {
+        let module = parse(&format!("{FIXITIES}{input}")).unwrap();
+        let Stmt::Expr { expr, .. } = module.stmts.last().unwrap() else {
+            panic!("bad statement")
+        };
+        assert_eq!(sexpr(expr), exp);
+    }
+
+    #[test]
+    fn qualified_body_spans() {
+        let module = parse(r"\x f nat.add x").unwrap();
+        let Stmt::Expr { expr, .. } = &module.stmts[0] else {
+            panic!("bad statement")
+        };
+        let Expr::Def { expr: body, .. } = expr else {
+            panic!("bad expression")
+        };
+        assert_eq!((body.span().start, body.span().end), (3, 14));
+        let Expr::Call { target, .. } = body.as_ref() else {
+            panic!("bad expression")
+        };
+        let Expr::Call { arg: name, .. } = target.as_ref() else {
+            panic!("bad expression")
+        };
+        assert_eq!((name.span().start, name.span().end), (5, 12));
+        assert_eq!(name.span().col, 6);
+    }
+
+    #[test]
+    fn qualified_body_infix_arg() {
+        let err = parse(&format!("{FIXITIES}\\x f + nat.add")).unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("the operator would be infix"), "{msg}");
+    }
+
+    #[rstest]
+    #[case::chained(r"a == b == c")]
+    #[case::mixed("infixr 6 <>\na + b <> c")]
+    #[case::no_left(r"a + * b")]
+    #[case::no_right(r"a b +")]
+    fn parse_operators_err(#[case] input: &str) {
+        assert!(parse(&format!("{FIXITIES}{input}")).is_err());
+    }
+
+    #[test]
+    fn fixity_declaration() {
+        let module = parse("+ = add\ninfixr 3 + <=>\na + b").unwrap();
+        assert_eq!(module.stmts.len(), 3);
+        let Stmt::Fixity {
+            assoc,
+            precedence,
+            operators,
+            ..
+        } = &module.stmts[1]
+        else {
+            panic!("bad statement")
+        };
+        assert_eq!(*assoc, Assoc::Right);
+        assert_eq!(*precedence, 3);
+        assert_eq!(operators, &["+", "<=>"]);
+        // The declaration applies only to the statements after it.
+        let module = parse("a + b\ninfixl 6 +\na + b").unwrap();
+        assert_eq!(module.stmts[0].short_repr(), "call(call(id, id), id)");
+        let Stmt::Expr { expr, .. } = &module.stmts[2] else {
+            panic!("bad statement")
+        };
+        assert_eq!(sexpr(expr), "(+ a b)");
+        // But it still can be passed from one input to another.
+        let mut fixities = Fixities::new();
+        parse_source("infixl 6 +", None, &mut fixities, Dialect::Standard).unwrap();
+        let module = parse_source("a + b * c", None, &mut fixities, Dialect::Standard).unwrap();
+        let Stmt::Expr { expr, .. } = &module.stmts[0] else {
+            panic!("bad statement")
+        };
+        assert_eq!(sexpr(expr), "(+ a (b * c))");
+    }
+
+    #[test]
+    fn import_path() {
+        let module = parse(r#"import "dir/a\"b\u{3bb}.rb""#).unwrap();
+        let Stmt::Import { path, .. } = &module.stmts[0] else {
+            panic!("bad statement")
+        };
+        assert_eq!(path, "dir/a\"bλ.rb");
+    }
+
+    #[test]
+    fn operator_error_location() {
+        let err = parse("infix 4 ==\n\na == b == c").unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("3:8"), "{msg}");
+        assert!(msg.contains("non-associative operator `==`"), "{msg}");
+    }
+
+    #[test]
+    fn operator_spans() {
+        let module = parse("infixl 6 +\nab + c").unwrap();
+        let Stmt::Expr { expr, .. } = &module.stmts[1] else {
+            panic!("bad statement")
+        };
+        assert_eq!((expr.span().start, expr.span().end), (11, 17));
+        let Expr::Call { target, .. } = expr else {
+            panic!("bad expression")
+        };
+        assert_eq!((target.span().start, target.span().end), (11, 15));
+    }
+
+    #[test]
+    fn spans() {
+        let module = parse_file("id = \\x x\n\nid  abc", "a.rb").unwrap();
+        let span = module.stmts[0].span();
+        assert_eq!(span.to_string(), "a.rb:1:1");
+        assert_eq!((span.start, span.end), (0, 9));
+        let Stmt::Expr { expr, .. } = &module.stmts[1] else {
+            panic!("bad statement")
+        };
+        assert_eq!(expr.span().to_string(), "a.rb:3:1");
+        assert_eq!((expr.span().start, expr.span().end), (11, 18));
+        let Expr::Call { arg, .. } = expr else {
+            panic!("bad expression")
+        };
+        assert_eq!(arg.span().to_string(), "a.rb:3:5");
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn parse_dot(input: &str) -> Result<Module, Error<Rule>> {
+        parse_source(input, None, &mut Fixities::new(), Dialect::Dot)
+    }
+
+    // The dot dialect is only a different way to write the same thing,
+    // so we compare it with the same code in the standard dialect.
+    #[rstest]
+    #[case(r"λx.x", r"λx x")]
+    #[case(r"\x.\y.x y", r"\x \y x y")]
+    #[case(r"λxy.x y", r"λx λy x y")]
+    #[case(r"λx y.x", r"λx λy x")]
+    #[case(r"λfx. f (f x)", r"λf λx f (f x)")]
+    #[case(r"(λx.x x)(λx.x x)", r"(λx x x) (λx x x)")]
+    #[case(r"λf.(λx.f(x x))(λx.f(x x))", r"λf (λx f (x x)) (λx f (x x))")]
+    #[case(r"λ+.+ a", r"λ+ + a")]
+    #[case(r"K = λxy.x", r"K = λx λy x")]
+    #[case(r"let k = λxy.x in k", r"let k = λx λy x in k")]
+    #[case(r"λαβ.β α", r"λα λβ β α")]
+    #[case("λe\u{301}x.x", "λe\u{301} λx x")]
+    fn dot_dialect(#[case] input: &str, #[case] exp: &str) {
+        let module = parse_dot(input).unwrap();
+        assert_eq!(module.to_sexpr(), parse(exp).unwrap().to_sexpr());
+    }
+
+    #[rstest]
+    #[case::no_dot(r"λx x")]
+    #[case::no_dot(r"λx y")]
+    #[case::no_dot(r"f (λx x)")]
+    #[case::no_body(r"λxy.")]
+    fn dot_dialect_err(#[case] input: &str) {
+        assert!(parse_dot(input).is_err());
+    }
+
+    #[rstest]
+    #[case::joined("f(g x)")]
+    #[case::joined("(f x)(g x)")]
+    #[case::joined("(f x )(g x)")]
+    #[case::joined("f( g x)")]
+    #[case::joined("f a(g x)")]
+    fn standard_dialect_err(#[case] input: &str) {
+        let err = parse(input).unwrap_err();
+        assert!(err.to_string().contains("expected a space"), "{err}");
+    }
+
+    #[test]
+    fn dot_dialect_spans() {
+        let module = parse_dot(r"λxy.x").unwrap();
+        let Stmt::Expr { expr, .. } = &module.stmts[0] else {
+            panic!("bad statement")
+        };
+        let Expr::Def { expr: inner, .. } = expr else {
+            panic!("bad expression")
+        };
+        assert_eq!((expr.span().start, expr.span().end), (0, 6));
+        assert_eq!((inner.span().start, inner.span().end), (3, 6));
+        assert_eq!(inner.span().col, 3);
+    }
+
+    #[rstest]
+    #[case("# dialect: dot\nλxy.x", Some(Dialect::Dot))]
+    #[case("\n  #dialect:dot  \nx", Some(Dialect::Dot))]
+    #[case("# id\n\n# dialect: standard\nx", Some(Dialect::Standard))]
+    #[case("# hello\nx", None)]
+    #[case("x\n# dialect: dot", None)]
+    #[case("", None)]
+    fn dialect_pragma(#[case] input: &str, #[case] exp: Option<Dialect>) {
+        assert_eq!(find_dialect(input).unwrap(), exp);
+    }
+
+    #[test]
+    fn dialect_pragma_overrides() {
+        let module = parse("# dialect: dot\nλxy.x").unwrap();
+        assert_eq!(module.stmts[0].short_repr(), "def(def(id))");
+        let module = parse_dot("# dialect: standard\nλxy x").unwrap();
+        assert_eq!(module.stmts[0].short_repr(), "def(id)");
+        let err = parse("# dialect: lisp\nx").unwrap_err();
+        assert!(err.to_string().contains("unknown dialect `lisp`"));
+    }
 }
@@ -20,3 +20,8 @@ xor = \a \b a (b false true) (b true false)
 
 # True if both `a` and `b` are the same
 xnor = \a \b not (xor a b)
+
+############
+
+# Make all names above available in other modules without the module name.
+export id if true false or and not xor xnor
@@ -1,9 +1,13 @@
-
 I = \a a
-K = \a \b a
-C = \a \b \c a b c
-B = \a \b \c a (b c)
-S = \a \b \c a c (b c)
+K = \a b. a
+C = \a b c. a b c
+B = \a b c. a (b c)
+S = \a b c. a c (b c)
 
 # Y-combinator, makes recursion possible (with black magic).
 Y = \f (\x f (x x)) (\x f (x x))
+
+############
+
+# Make all names above available in other modules without the module name.
+export I K C B S Y
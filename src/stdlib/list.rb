@@ -28,3 +28,8 @@ head = \xs car (cdr xs)
 
 # Remove the first element from the list.
 tail = \xs cdr (cdr xs)
+
+############
+
+# Make all names above available in other modules without the module name.
+export cons car cdr empty_list prepend is_empty head tail
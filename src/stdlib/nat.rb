@@ -20,18 +20,12 @@ sub = \a \b b dec a
 # Substract the bigger number from the smaller number
 diff = \a \b add (sub a b) (sub b a)
 
-# Natural numbers, church numerals
-0 = \a \b b
-1 = \a a
-2 = \a \b a (a b)
-3 = \a \b a (a (a b))
-4 = inc 3
-5 = inc 4
-6 = inc 5
-7 = inc 6
-8 = inc 7
-9 = inc 8
-10 = inc 9
+# Natural numbers are Church numerals: `n` is a function that accepts
+# a function and a value and applies the function to the value `n` times.
+# So, `0` is `\a \b b`, `1` is `\a \b a b`, and `2` is `\a \b a (a b)`.
+#
+# Numbers don't need to be defined, any number you type in the code
+# is automatically converted into a Church numeral.
 
 is_zero = \n n (\_ \a \b b) (\a \b a)
 gte = \a \b is_zero (sub b a)
@@ -45,3 +39,8 @@ min = \a \b lte a b a b
 
 # Get the bigger of the two numbers
 max = \a \b gte a b a b
+
+############
+
+# Make all names above available in other modules without the module name.
+export inc add mul pow dec sub diff is_zero gte lte gt lt eq min max